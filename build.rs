@@ -3,12 +3,22 @@ extern crate cbindgen;
 
 use std::env;
 
+// The header for FFI consumers, e.g. the DanXi app. See cbindgen.toml for how it is generated.
+const HEADER: &str = "libfdu.h";
+
 fn main() {
     let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+        .expect("Unable to read cbindgen.toml");
 
+    // Fail the build if the header cannot be generated, so a broken header never goes unnoticed.
     cbindgen::Builder::new()
-        .with_crate(crate_dir)
+        .with_crate(&crate_dir)
+        .with_config(config)
         .generate()
         .expect("Unable to generate bindings")
-        .write_to_file("bindings.h");
-}
\ No newline at end of file
+        .write_to_file(format!("{}/{}", crate_dir, HEADER));
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
@@ -3,7 +3,7 @@ package main
 /*
 #cgo CFLAGS: -I${SRCDIR}/../..
 #cgo LDFLAGS: -L${SRCDIR}/../../target/debug -lfdu
-#include "bindings.h"
+#include "libfdu.h"
 */
 import "C"
 import (
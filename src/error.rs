@@ -1,21 +1,35 @@
 use std::fmt::{Debug, Display, Formatter};
 
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static URL_QUERY_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(https?://[^\s?#]+)[?#]\S*").unwrap());
+
 pub type Result<T> = std::result::Result<T, SDKError>;
 
+#[derive(Default)]
 pub enum ErrorType {
     LoginError,
     ParseError,
     NetworkError,
     NoneError,
+    // The portal session timed out, e.g. jwfw's "业务流程已过期" page. Logging in again usually helps.
+    SessionExpired,
+    // More than one teaching class matches what is asked for, e.g. a course code with several sections.
+    AmbiguousSelection,
+    // Stopped by the caller, e.g. a grab loop cancelled from the UI.
+    Cancelled,
+    // An operation of many requests ran out of its time budget, e.g. a grab loop, see `XK::grab_many`.
+    Timeout,
+    // The system shows its maintenance page (系统维护中), e.g. at night. Trying again later usually helps.
+    ServiceUnavailable,
+    // The firewall (WAF) in front of the systems blocks us for sending too many requests, with its block page or a 429.
+    // Waiting a good while before trying again helps, retrying at once keeps us blocked.
+    RateLimited,
+    #[default]
     OtherError,
 }
 
-impl Default for ErrorType {
-    fn default() -> Self {
-        ErrorType::OtherError
-    }
-}
-
 impl Display for ErrorType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -23,20 +37,43 @@ impl Display for ErrorType {
             ErrorType::ParseError => write!(f, "ParseError"),
             ErrorType::NetworkError => write!(f, "NetworkError"),
             ErrorType::NoneError => write!(f, "NoneError"),
+            ErrorType::SessionExpired => write!(f, "SessionExpired"),
+            ErrorType::AmbiguousSelection => write!(f, "AmbiguousSelection"),
+            ErrorType::Cancelled => write!(f, "Cancelled"),
+            ErrorType::Timeout => write!(f, "Timeout"),
+            ErrorType::ServiceUnavailable => write!(f, "ServiceUnavailable"),
+            ErrorType::RateLimited => write!(f, "RateLimited"),
             ErrorType::OtherError => write!(f, "OtherError"),
         }
     }
 }
 
+// Where a failed login ended up, e.g. still the login page (wrong password), or a password-reset page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginFailure {
+    // the final url after redirects
+    pub url: String,
+    pub status: u16,
+}
+
 #[derive(Default)]
 pub struct SDKError {
     r#type: ErrorType,
     message: String,
-    cause: Option<Box<dyn Display>>,
+    // Send and Sync, so that errors can be handed over from other threads
+    cause: Option<Box<dyn Display + Send + Sync>>,
+    login_failure: Option<LoginFailure>,
 }
 
 impl SDKError {
     pub fn is_none_error(&self) -> bool { matches!(self.r#type, ErrorType::NoneError) }
+    pub fn is_login_error(&self) -> bool { matches!(self.r#type, ErrorType::LoginError) }
+    pub fn is_session_expired(&self) -> bool { matches!(self.r#type, ErrorType::SessionExpired) }
+    pub fn is_ambiguous_selection(&self) -> bool { matches!(self.r#type, ErrorType::AmbiguousSelection) }
+    pub fn is_cancelled(&self) -> bool { matches!(self.r#type, ErrorType::Cancelled) }
+    pub fn is_timeout(&self) -> bool { matches!(self.r#type, ErrorType::Timeout) }
+    pub fn is_service_unavailable(&self) -> bool { matches!(self.r#type, ErrorType::ServiceUnavailable) }
+    pub fn is_rate_limited(&self) -> bool { matches!(self.r#type, ErrorType::RateLimited) }
     pub fn none() -> Self { SDKError::with_type(ErrorType::NoneError, Default::default()) }
     pub fn new(message: String) -> Self {
         SDKError::with_type(ErrorType::NoneError, message)
@@ -45,14 +82,52 @@ impl SDKError {
         SDKError {
             r#type,
             message,
-            cause: None,
+            ..Default::default()
         }
     }
-    pub fn with_cause(r#type: ErrorType, message: String, cause: Box<dyn Display>) -> Self {
+    pub fn with_cause(r#type: ErrorType, message: String, cause: Box<dyn Display + Send + Sync>) -> Self {
         SDKError {
             r#type,
             message,
             cause: Some(cause),
+            ..Default::default()
+        }
+    }
+    // A `LoginError` recording where the login ended up. The url and status are in the message as well, for logs.
+    pub fn login_failed(message: &str, url: &str, status: u16) -> Self {
+        SDKError {
+            r#type: ErrorType::LoginError,
+            message: format!("{}: ended up at {} with status {}", message, url, status),
+            login_failure: Some(LoginFailure { url: url.to_string(), status }),
+            ..Default::default()
+        }
+    }
+    pub fn login_failure(&self) -> Option<&LoginFailure> { self.login_failure.as_ref() }
+}
+
+impl SDKError {
+    // A message that is safe to show to end users.
+    //
+    // The cause is left out, and query strings (which may carry tickets or session tokens) are cut from any URL.
+    // Use `Display` or `Debug` for the detailed form in logs.
+    pub fn user_message(&self) -> String {
+        let hint = match self.r#type {
+            ErrorType::LoginError => "登录失败",
+            ErrorType::ParseError => "解析页面失败",
+            ErrorType::NetworkError => "网络错误",
+            ErrorType::SessionExpired => "会话已过期，请重新登录",
+            ErrorType::AmbiguousSelection => "有多个教学班，请指定课程序号",
+            ErrorType::Cancelled => "已取消",
+            ErrorType::Timeout => "操作超时",
+            ErrorType::ServiceUnavailable => "系统维护中，请稍后再试",
+            ErrorType::RateLimited => "访问过于频繁，请稍后再试",
+            ErrorType::NoneError | ErrorType::OtherError => "发生错误",
+        };
+        let message = URL_QUERY_REGEX.replace_all(&self.message, "$1");
+        if message.is_empty() {
+            hint.to_string()
+        } else {
+            format!("{}: {}", hint, message)
         }
     }
 }
@@ -82,4 +157,32 @@ impl From<serde_json::error::Error> for SDKError {
     fn from(e: serde_json::error::Error) -> Self {
         SDKError::with_cause(ErrorType::ParseError, "serde_json reported an error".to_string(), Box::new(e))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_message() {
+        let e = SDKError::with_cause(
+            ErrorType::NetworkError,
+            "request to https://uis.fudan.edu.cn/authserver/login?ticket=ST-123-abc failed".to_string(),
+            Box::new("https://uis.fudan.edu.cn/authserver/login?ticket=ST-123-abc"),
+        );
+        let message = e.user_message();
+        assert!(!message.contains("ticket"));
+        assert!(message.contains("https://uis.fudan.edu.cn/authserver/login"));
+        // the detailed form is kept for logging
+        assert!(e.to_string().contains("ticket=ST-123-abc"));
+    }
+
+    #[test]
+    fn test_login_failed() {
+        let e = SDKError::login_failed("login failed", "https://uis.fudan.edu.cn/authserver/login?service=x", 200);
+        assert!(e.is_login_error());
+        assert_eq!(e.login_failure(), Some(&LoginFailure { url: "https://uis.fudan.edu.cn/authserver/login?service=x".to_string(), status: 200 }));
+        assert!(e.to_string().contains("https://uis.fudan.edu.cn/authserver/login?service=x"));
+        assert!(SDKError::with_type(ErrorType::LoginError, "login failed".to_string()).login_failure().is_none());
+    }
+}
@@ -0,0 +1,40 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::error::*;
+
+// The JSON envelope of the portal APIs behind CAS (thesis, tuition), eg. {"code":0,"msg":"success","data":{...}}.
+// 0 means ok, and `data` is null or missing when there is nothing to give, eg. no thesis record yet.
+#[derive(Deserialize)]
+pub(crate) struct ApiResponse<T> {
+    code: i32,
+    #[serde(default)]
+    msg: String,
+    data: Option<T>,
+}
+
+// Parse `json` as an `ApiResponse` and get its data, or an `OtherError` with the message of the server,
+// eg. "get thesis status error: 未登录" for `what` = "get thesis status".
+pub(crate) fn parse_api_data<T: DeserializeOwned>(json: &str, what: &str) -> Result<Option<T>> {
+    let response: ApiResponse<T> = serde_json::from_str(json)?;
+    if response.code != 0 {
+        return Err(SDKError::with_type(ErrorType::OtherError, format!("{} error: {}", what, response.msg)));
+    }
+    Ok(response.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_api_data() {
+        assert_eq!(parse_api_data::<Vec<i32>>(r#"{"code":0,"msg":"success","data":[1,2]}"#, "test").unwrap(), Some(vec![1, 2]));
+        assert_eq!(parse_api_data::<Vec<i32>>(r#"{"code":0,"data":null}"#, "test").unwrap(), None);
+        assert_eq!(parse_api_data::<Vec<i32>>(r#"{"code":0}"#, "test").unwrap(), None);
+
+        let e = parse_api_data::<Vec<i32>>(r#"{"code":401,"msg":"未登录"}"#, "test").expect_err("expect error");
+        assert_eq!(e.to_string(), "test error: 未登录");
+        parse_api_data::<Vec<i32>>("<html>统一身份认证</html>", "test").expect_err("expect error");
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{Datelike, Local, NaiveTime, Weekday};
+use serde::Deserialize;
+
+use super::prelude::*;
+
+impl BusClient for Fdu {}
+
+const BUS_SCHEDULE_URL: &str = "https://zlapp.fudan.edu.cn/fudanbus/wap/default/lists";
+
+const WORKDAYS: [Weekday; 5] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri];
+const HOLIDAYS: [Weekday; 2] = [Weekday::Sat, Weekday::Sun];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusTrip {
+    // eg. 邯郸
+    from: String,
+    // eg. 江湾
+    to: String,
+    departure: NaiveTime,
+    days: Vec<Weekday>,
+}
+
+#[derive(Deserialize)]
+struct BusResponse {
+    // 0 means ok
+    e: i32,
+    // the error message
+    #[serde(default)]
+    m: String,
+    d: Option<BusData>,
+}
+
+#[derive(Deserialize)]
+struct BusData {
+    #[serde(default)]
+    data: Vec<BusRoute>,
+}
+
+#[derive(Deserialize)]
+struct BusRoute {
+    #[serde(default)]
+    lists: Vec<BusRouteItem>,
+}
+
+// A row of the timetable, eg. {"id":"1","start":"邯郸","end":"江湾","stime":"07:10","etime":"07:40"}
+// means a bus leaves 邯郸 for 江湾 at 07:10, and one leaves 江湾 for 邯郸 at 07:40. Either time may be empty.
+#[derive(Deserialize)]
+struct BusRouteItem {
+    start: String,
+    end: String,
+    #[serde(default)]
+    stime: String,
+    #[serde(default)]
+    etime: String,
+}
+
+// Parse the timetable. There is no trip at all when there is no service, e.g. during the winter vacation.
+fn parse_bus_schedule(json: &str, holiday: bool) -> Result<Vec<BusTrip>> {
+    let response: BusResponse = serde_json::from_str(json)?;
+    if response.e != 0 {
+        return Err(SDKError::with_type(ErrorType::OtherError, format!("get bus schedule error: {}", response.m)));
+    }
+
+    let days = if holiday { HOLIDAYS.to_vec() } else { WORKDAYS.to_vec() };
+    let mut trips = Vec::new();
+    let routes = response.d.map(|d| d.data).unwrap_or_default();
+    for item in routes.iter().flat_map(|route| &route.lists) {
+        for (from, to, time) in [(&item.start, &item.end, &item.stime), (&item.end, &item.start, &item.etime)] {
+            if time.trim().is_empty() {
+                continue;
+            }
+            let departure = NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse bus time {} error", time)))?;
+            trips.push(BusTrip { from: from.clone(), to: to.clone(), departure, days: days.clone() });
+        }
+    }
+    trips.sort_by_key(|trip| trip.departure);
+    Ok(trips)
+}
+
+pub trait BusClient: Account {
+    // Get the shuttle bus timetable between campuses, on workdays or on weekends and holidays.
+    fn get_bus_schedule(&self, holiday: bool) -> Result<Vec<BusTrip>> {
+        let payload = [("holiday", if holiday { "1" } else { "0" })];
+        let json = self.send_and_get_json(self.get_client().post(self.resolve_url(BUS_SCHEDULE_URL)).form(&payload))?;
+        parse_bus_schedule(&json, holiday)
+    }
+
+    // Get today's trips. It is empty if there is no service today.
+    fn get_today_bus_schedule(&self) -> Result<Vec<BusTrip>> {
+        let today = Local::now().weekday();
+        let trips = self.get_bus_schedule(HOLIDAYS.contains(&today))?;
+        Ok(trips.into_iter().filter(|trip| trip.days.contains(&today)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bus_schedule() {
+        const SCHEDULE: &str = r#"{"e":0,"m":"操作成功","d":{"data":[
+            {"id":"1","route":"邯郸校区-江湾校区","lists":[
+                {"id":"2","start":"邯郸","end":"江湾","stime":"12:00","etime":""},
+                {"id":"1","start":"邯郸","end":"江湾","stime":"07:10","etime":"07:40"}
+            ]},
+            {"id":"2","route":"邯郸校区-枫林校区","lists":[
+                {"id":"1","start":"邯郸","end":"枫林","stime":"","etime":"08:30"}
+            ]}
+        ]}}"#;
+        let trips = parse_bus_schedule(SCHEDULE, false).unwrap();
+        assert_eq!(trips.len(), 4);
+        assert_eq!(trips[0], BusTrip {
+            from: "邯郸".to_string(),
+            to: "江湾".to_string(),
+            departure: NaiveTime::from_hms_opt(7, 10, 0).unwrap(),
+            days: WORKDAYS.to_vec(),
+        });
+        assert_eq!((trips[1].from.as_str(), trips[1].to.as_str()), ("江湾", "邯郸"));
+        assert_eq!((trips[2].from.as_str(), trips[2].to.as_str()), ("枫林", "邯郸"));
+        assert_eq!(trips[3].departure, NaiveTime::from_hms_opt(12, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_parse_no_service() {
+        assert!(parse_bus_schedule(r#"{"e":0,"m":"操作成功","d":{"data":[]}}"#, true).unwrap().is_empty());
+        assert!(parse_bus_schedule(r#"{"e":0,"m":"操作成功","d":{}}"#, true).unwrap().is_empty());
+        parse_bus_schedule(r#"{"e":1,"m":"请先登录"}"#, true).expect_err("expect error");
+    }
+
+    #[test]
+    fn test_bus_schedule_mock() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("POST /fudanbus/wap/default/lists", vec![MockResponse::json(r#"{"e":0,"m":"操作成功","d":{"data":[
+            {"id":"1","route":"邯郸校区-江湾校区","lists":[{"id":"1","start":"邯郸","end":"江湾","stime":"07:10","etime":"07:40"}]}
+        ]}}"#)]);
+
+        let fd = mock_fdu(&server, &["https://zlapp.fudan.edu.cn"]);
+        let trips = fd.get_bus_schedule(true).unwrap();
+        assert_eq!(trips.len(), 2);
+        assert_eq!(trips[0].days, HOLIDAYS.to_vec());
+        assert!(server.requests()[0].ends_with("holiday=1"));
+    }
+
+    #[test]
+    #[ignore = "needs the real UIS and UID/PWD"]
+    fn test_bus_schedule() {
+        dotenv::dotenv().ok();  // load env from .env file
+        let uid = std::env::var("UID").expect("environment variable UID not set");
+        let pwd = std::env::var("PWD").expect("environment variable PWD not set");
+
+        let mut fd = Fdu::new();
+        fd.login(uid.as_str(), pwd.as_str()).expect("login error");
+        fd.get_today_bus_schedule().expect("bus schedule error");
+        fd.logout().expect("logout error");
+    }
+}
@@ -0,0 +1,122 @@
+use once_cell::sync::Lazy;
+use scraper::{Html, Selector};
+
+use super::prelude::*;
+
+impl CampusClient for Fdu {}
+
+// the free classroom query of jwfw, whose building filter lists the buildings of a campus
+const JWFW_FREE_CLASSROOM_URL: &str = "https://jwfw.fudan.edu.cn/eams/classroom/apply/free.action";
+
+static BUILDING_OPTION_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"select[name="building.id"] option"#).unwrap());
+
+// The campuses, coded as in xk (`campusCode`) and the room names, eg. H for 邯郸校区 and HGX509.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Campus {
+    Handan,
+    Jiangwan,
+    Fenglin,
+    Zhangjiang,
+}
+
+impl Campus {
+    pub const ALL: [Campus; 4] = [Campus::Handan, Campus::Jiangwan, Campus::Fenglin, Campus::Zhangjiang];
+
+    // eg. H
+    pub fn code(&self) -> &'static str {
+        match self {
+            Campus::Handan => "H",
+            Campus::Jiangwan => "J",
+            Campus::Fenglin => "F",
+            Campus::Zhangjiang => "Z",
+        }
+    }
+
+    // eg. 邯郸校区
+    pub fn name(&self) -> &'static str {
+        match self {
+            Campus::Handan => "邯郸校区",
+            Campus::Jiangwan => "江湾校区",
+            Campus::Fenglin => "枫林校区",
+            Campus::Zhangjiang => "张江校区",
+        }
+    }
+
+    // The campus of a code, eg. H, ignoring case. None for a code we do not know of.
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|campus| campus.code().eq_ignore_ascii_case(code.trim()))
+    }
+
+    // The campus of a room, told by the first letter of its name, eg. HGX509 is in 邯郸校区.
+    pub fn of_room(room: &str) -> Option<Self> {
+        Self::from_code(room.trim().get(..1)?)
+    }
+}
+
+// A teaching building, eg. 第三教学楼
+#[derive(Debug, Clone, PartialEq)]
+pub struct Building {
+    // jwfw's id of the building, eg. 3
+    id: String,
+    name: String,
+}
+
+// Parse the building filter of the free classroom page, eg. <select name="building.id"><option value="3">第三教学楼</option></select>.
+// The "all" option with an empty value is left out.
+fn parse_buildings(html: &str) -> Result<Vec<Building>> {
+    let document = Html::parse_document(html);
+    let options: Vec<_> = document.select(&BUILDING_OPTION_SELECTOR).collect();
+    if options.is_empty() {
+        return Err(SDKError::with_type(ErrorType::ParseError, "building list not found".to_string()));
+    }
+    Ok(options.into_iter().filter_map(|option| {
+        let id = option.value().attr("value")?.trim();
+        if id.is_empty() {
+            return None;
+        }
+        Some(Building { id: id.to_string(), name: option.text().collect::<String>().trim().to_string() })
+    }).collect())
+}
+
+pub trait CampusClient: Account {
+    // List the teaching buildings of `campus`, from jwfw, which needs to be logged in, see `JwfwClient::login_jwfw`.
+    fn get_buildings(&self, campus: Campus) -> Result<Vec<Building>> {
+        let request = self.get_client().get(self.resolve_url(JWFW_FREE_CLASSROOM_URL)).query(&[("campus.code", campus.code())]);
+        parse_buildings(&self.send_and_get_html(request)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_campus() {
+        assert_eq!(Campus::from_code("H"), Some(Campus::Handan));
+        assert_eq!(Campus::from_code(" z "), Some(Campus::Zhangjiang));
+        assert_eq!(Campus::from_code("X"), None);
+        for campus in Campus::ALL {
+            assert_eq!(Campus::from_code(campus.code()), Some(campus));
+        }
+        assert_eq!(Campus::Jiangwan.name(), "江湾校区");
+
+        assert_eq!(Campus::of_room("HGX509"), Some(Campus::Handan));
+        assert_eq!(Campus::of_room("F102"), Some(Campus::Fenglin));
+        assert_eq!(Campus::of_room(""), None);
+        assert_eq!(Campus::of_room("线上"), None);
+    }
+
+    #[test]
+    fn test_parse_buildings() {
+        const PAGE: &str = r#"<form><select name="building.id">
+            <option value="">全部</option>
+            <option value="3">第三教学楼</option>
+            <option value="16"> 光华楼西辅楼 </option>
+        </select></form>"#;
+        assert_eq!(parse_buildings(PAGE).unwrap(), vec![
+            Building { id: "3".to_string(), name: "第三教学楼".to_string() },
+            Building { id: "16".to_string(), name: "光华楼西辅楼".to_string() },
+        ]);
+        parse_buildings("<html>本科生教务系统</html>").expect_err("expect error");
+    }
+}
@@ -0,0 +1,138 @@
+use serde::Deserialize;
+
+use super::prelude::*;
+
+impl CanteenClient for Fdu {}
+
+// the menus of this week, of the canteens publishing them
+const CANTEEN_MENU_URL: &str = "https://zlapp.fudan.edu.cn/fudancanteen/wap/default/menu";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dish {
+    // eg. 红烧牛肉面
+    name: String,
+    // in yuan, None if the menu does not say
+    price: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanteenWindow {
+    // eg. 一楼面食档口
+    name: String,
+    dishes: Vec<Dish>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanteenMenu {
+    // eg. 北区食堂
+    canteen: String,
+    windows: Vec<CanteenWindow>,
+}
+
+#[derive(Deserialize)]
+struct MenuResponse {
+    // 0 means ok
+    e: i32,
+    #[serde(default)]
+    m: String,
+    d: Option<MenuData>,
+}
+
+#[derive(Deserialize)]
+struct MenuData {
+    #[serde(default)]
+    list: Vec<CanteenData>,
+}
+
+// eg. {"name":"北区食堂","windows":[{"name":"一楼面食档口","dishes":[{"name":"红烧牛肉面","price":"12.00"}]}]}
+#[derive(Deserialize)]
+struct CanteenData {
+    name: String,
+    #[serde(default)]
+    windows: Vec<WindowData>,
+}
+
+#[derive(Deserialize)]
+struct WindowData {
+    name: String,
+    #[serde(default)]
+    dishes: Vec<DishData>,
+}
+
+// The price is a string, and empty when not given.
+#[derive(Deserialize)]
+struct DishData {
+    name: String,
+    #[serde(default)]
+    price: String,
+}
+
+// Parse the menus. `list` is empty (or `d` is null) when no canteen publishes a menu this week, which gives an empty vector.
+fn parse_canteen_menus(json: &str) -> Result<Vec<CanteenMenu>> {
+    let response: MenuResponse = serde_json::from_str(json)?;
+    if response.e != 0 {
+        return Err(SDKError::with_type(ErrorType::OtherError, format!("get canteen menu error: {}", response.m)));
+    }
+
+    let canteens = response.d.map(|data| data.list).unwrap_or_default();
+    canteens.into_iter().map(|canteen| {
+        let windows = canteen.windows.into_iter().map(|window| {
+            let dishes = window.dishes.into_iter().map(|dish| {
+                let price = match dish.price.trim() {
+                    "" => None,
+                    price => Some(price.parse()
+                        .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse price {} of {} error", price, dish.name)))?),
+                };
+                Ok(Dish { name: dish.name, price })
+            }).collect::<Result<Vec<_>>>()?;
+            Ok(CanteenWindow { name: window.name, dishes })
+        }).collect::<Result<Vec<_>>>()?;
+        Ok(CanteenMenu { canteen: canteen.name, windows })
+    }).collect()
+}
+
+pub trait CanteenClient: Account {
+    // Get this week's menus (食堂菜单) of the canteens publishing them. Empty if none is published.
+    fn get_canteen_menus(&self) -> Result<Vec<CanteenMenu>> {
+        let json = self.send_and_get_json(self.get_client().get(self.resolve_url(CANTEEN_MENU_URL)))?;
+        parse_canteen_menus(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_canteen_menus() {
+        const MENU: &str = r#"{"e":0,"m":"操作成功","d":{"list":[
+            {"name":"北区食堂","windows":[
+                {"name":"一楼面食档口","dishes":[{"name":"红烧牛肉面","price":"12.00"},{"name":"葱油拌面","price":"6.5"}]},
+                {"name":"二楼自选","dishes":[{"name":"时令蔬菜","price":""}]}
+            ]},
+            {"name":"旦苑食堂","windows":[]}
+        ]}}"#;
+        let menus = parse_canteen_menus(MENU).unwrap();
+        assert_eq!(menus.len(), 2);
+        assert_eq!(menus[0].canteen, "北区食堂");
+        assert_eq!(menus[0].windows[0], CanteenWindow {
+            name: "一楼面食档口".to_string(),
+            dishes: vec![
+                Dish { name: "红烧牛肉面".to_string(), price: Some(12.0) },
+                Dish { name: "葱油拌面".to_string(), price: Some(6.5) },
+            ],
+        });
+        assert_eq!(menus[0].windows[1].dishes[0].price, None);
+        assert!(menus[1].windows.is_empty());
+
+        parse_canteen_menus(r#"{"e":0,"d":{"list":[{"name":"北区食堂","windows":[{"name":"面食","dishes":[{"name":"面","price":"十元"}]}]}]}}"#)
+            .expect_err("expect error");
+    }
+
+    #[test]
+    fn test_parse_no_menu() {
+        assert!(parse_canteen_menus(r#"{"e":0,"m":"本周暂无菜单","d":{"list":[]}}"#).unwrap().is_empty());
+        assert!(parse_canteen_menus(r#"{"e":0,"m":"本周暂无菜单","d":null}"#).unwrap().is_empty());
+        parse_canteen_menus(r#"{"e":1,"m":"请先登录"}"#).expect_err("expect error");
+    }
+}
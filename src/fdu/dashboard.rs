@@ -0,0 +1,111 @@
+use std::thread;
+
+use chrono::{Local, NaiveDate};
+
+use super::prelude::*;
+use crate::fdu::ecard::ECardClient;
+use crate::fdu::grade::{get_gpa_from_jwfw, GPA};
+use crate::fdu::jwfw::{JwfwClient, ScheduleSlot};
+
+// What a home screen shows at a glance. Each part is fetched on its own, so one failing leaves the others intact.
+pub struct Dashboard {
+    gpa: Result<GPA>,
+    // today's classes, in the order of units
+    classes: Result<Vec<ScheduleSlot>>,
+    // the ecard balance, None if there is no transaction to tell it from
+    ecard_balance: Result<Option<f64>>,
+    // whether the daily report (每日上报) is done today
+    daily_reported: Result<bool>,
+}
+
+impl Dashboard {
+    // Fetch everything with a logged-in `fdu`.
+    //
+    // The services are on different hosts, so they are asked at the same time, one thread for each.
+    // Requests to the same host still go one after another with the usual delays in between, or jwfw would throttle them.
+    pub fn fetch(fdu: &Fdu) -> Self {
+        Self::fetch_on(fdu, Local::now().date_naive())
+    }
+
+    fn fetch_on(fdu: &Fdu, today: NaiveDate) -> Self {
+        thread::scope(|scope| {
+            let jwfw = scope.spawn(|| match fdu.login_jwfw() {
+                Ok(()) => (get_gpa_from_jwfw(fdu), fdu.get_classes_on(today)),
+                Err(e) => {
+                    // `SDKError` cannot be cloned, so the second one is made from the message
+                    let message = format!("jwfw login failed: {}", e);
+                    (Err(e), Err(SDKError::with_type(ErrorType::LoginError, message)))
+                }
+            });
+            let ecard = scope.spawn(|| fdu.get_balance());
+            let daily = scope.spawn(|| fdu_daily::has_tick(fdu));
+
+            // a panicking part is reported as its error, like any other failure
+            let panicked = |part: &str| SDKError::with_type(ErrorType::OtherError, format!("{} thread panicked", part));
+            let (gpa, classes) = jwfw.join().unwrap_or_else(|_| (Err(panicked("jwfw")), Err(panicked("jwfw"))));
+            Self {
+                gpa,
+                classes,
+                ecard_balance: ecard.join().unwrap_or_else(|_| Err(panicked("ecard"))),
+                daily_reported: daily.join().unwrap_or_else(|_| Err(panicked("daily report"))),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdu::test_support::{fixture, logged_in_fdu, MockResponse, MockServer};
+
+    const GPA_RANKING: &str = r#"<table><tbody>
+        <tr><td>*****0002</td><td>2020</td><td>计算机学院</td><td>计算机科学与技术</td><td>***</td><td>3.80</td><td>62</td></tr>
+        <tr><td>20300180001</td><td>2020</td><td>计算机学院</td><td>计算机科学与技术</td><td>张三</td><td>3.50</td><td>60</td></tr>
+    </tbody></table>"#;
+
+    #[test]
+    fn test_dashboard() {
+        let server = MockServer::start();
+        server.route("/eams/home.action", vec![MockResponse::ok("<html>本科生教务系统</html>")]);
+        server.route("/eams/myActualGpa!search.action", vec![MockResponse::ok(GPA_RANKING)]);
+        server.route("/eams/schoolCalendar.action", vec![MockResponse::ok("<tr><td>第1周</td><td>2022-09-05</td></tr>")]);
+        server.route("GET /eams/courseTableForStd.action", vec![MockResponse::ok(r#"bg.form.addInput(form,"ids","123456");"#)]);
+        server.route("POST /eams/courseTableForStd!courseTable.action", vec![MockResponse::ok(&fixture("jwfw_course_table.html"))]);
+        // the ecard is down
+        server.route("/epay/myepay/index", vec![MockResponse::new(502, "<html>Bad Gateway</html>")]);
+        let today = Local::now().format("%Y%m%d").to_string();
+        server.route("/ncov/wap/fudan/get-info", vec![MockResponse::ok(&format!(r#"{{"d":{{"info":{{"date":"{}"}}}}}}"#, today))]);
+
+        let origins = ["https://jwfw.fudan.edu.cn", "https://ecard.fudan.edu.cn", "https://zlapp.fudan.edu.cn"];
+        let fd = logged_in_fdu(&server, &origins, &["/eams/home.action"]);
+
+        // the Wednesday of week 1
+        let dashboard = Dashboard::fetch_on(&fd, NaiveDate::from_ymd_opt(2022, 9, 7).unwrap());
+        assert_eq!(dashboard.gpa.unwrap().to_string(), "gpa: 3.5, ranking: 2/2 100.0%, credits: 60");
+        assert_eq!(dashboard.classes.unwrap(), vec![ScheduleSlot::new(
+            "数据结构(COMP130004.03)".to_string(), "HGX304".to_string(), 3, 1, 3, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 13, 14, 15, 16],
+        )]);
+        dashboard.ecard_balance.expect_err("expect ecard error");
+        assert!(dashboard.daily_reported.unwrap());
+    }
+
+    #[test]
+    fn test_dashboard_bad_gpa() {
+        let server = MockServer::start();
+        server.route("/eams/home.action", vec![MockResponse::ok("<html>本科生教务系统</html>")]);
+        server.route("/eams/myActualGpa!search.action", vec![MockResponse::ok(&GPA_RANKING.replace("3.50", "暂无"))]);
+        server.route("/eams/schoolCalendar.action", vec![MockResponse::ok("<tr><td>第1周</td><td>2022-09-05</td></tr>")]);
+        server.route("GET /eams/courseTableForStd.action", vec![MockResponse::ok(r#"bg.form.addInput(form,"ids","123456");"#)]);
+        server.route("POST /eams/courseTableForStd!courseTable.action", vec![MockResponse::ok(&fixture("jwfw_course_table.html"))]);
+        server.route("/epay/myepay/index", vec![MockResponse::new(502, "<html>Bad Gateway</html>")]);
+        server.route("/ncov/wap/fudan/get-info", vec![MockResponse::new(502, "<html>Bad Gateway</html>")]);
+
+        let origins = ["https://jwfw.fudan.edu.cn", "https://ecard.fudan.edu.cn", "https://zlapp.fudan.edu.cn"];
+        let fd = logged_in_fdu(&server, &origins, &["/eams/home.action"]);
+
+        // a gpa that is not a number fails the gpa alone, rather than panicking the whole dashboard
+        let dashboard = Dashboard::fetch_on(&fd, NaiveDate::from_ymd_opt(2022, 9, 7).unwrap());
+        assert!(dashboard.gpa.err().expect("expect gpa error").to_string().contains("暂无"));
+        assert_eq!(dashboard.classes.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,119 @@
+use serde::Deserialize;
+
+use super::api::parse_api_data;
+use super::prelude::*;
+
+impl DormClient for Fdu {}
+
+// the accommodation management (住宿管理) portal
+const DORM_INDEX_URL: &str = "https://zsgl.fudan.edu.cn/dorm/index";
+const DORM_ASSIGNMENT_URL: &str = "https://zsgl.fudan.edu.cn/dorm/api/student/assignment";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DormAssignment {
+    // eg. 邯郸校区
+    campus: String,
+    // eg. 北区5号楼
+    building: String,
+    // eg. 5101
+    room: String,
+    // eg. 2, None if the portal does not say
+    bed: Option<String>,
+}
+
+impl DormAssignment {
+    pub fn campus(&self) -> &str {
+        &self.campus
+    }
+
+    pub fn building(&self) -> &str {
+        &self.building
+    }
+
+    pub fn room(&self) -> &str {
+        &self.room
+    }
+
+    pub fn bed(&self) -> Option<&str> {
+        self.bed.as_deref()
+    }
+}
+
+// eg. {"xqmc":"邯郸校区","ldmc":"北区5号楼","fjh":"5101","cwh":"2"} for 校区名称, 楼栋名称, 房间号 and 床位号.
+// The room is empty for students without a room, e.g. living off campus or not assigned yet.
+#[derive(Deserialize)]
+struct DormData {
+    #[serde(default)]
+    xqmc: String,
+    #[serde(default)]
+    ldmc: String,
+    #[serde(default)]
+    fjh: String,
+    #[serde(default)]
+    cwh: String,
+}
+
+// Parse the assignment. None if the student has no room.
+fn parse_dorm_assignment(json: &str) -> Result<Option<DormAssignment>> {
+    let data = match parse_api_data::<DormData>(json, "get dorm assignment")? {
+        Some(data) if !data.fjh.trim().is_empty() => data,
+        _ => return Ok(None),
+    };
+    let bed = data.cwh.trim();
+    Ok(Some(DormAssignment {
+        campus: data.xqmc.trim().to_string(),
+        building: data.ldmc.trim().to_string(),
+        room: data.fjh.trim().to_string(),
+        bed: if bed.is_empty() { None } else { Some(bed.to_string()) },
+    }))
+}
+
+pub trait DormClient: Account {
+    // Get the dorm (宿舍) building, room and bed assigned to the student.
+    // None if the student has none, e.g. living off campus or not assigned yet.
+    fn get_dorm_assignment(&self) -> Result<Option<DormAssignment>> {
+        self.follow_cas(DORM_INDEX_URL)?;
+        let json = self.send_and_get_json(self.get_client().get(self.resolve_url(DORM_ASSIGNMENT_URL)))?;
+        parse_dorm_assignment(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdu::test_support::fixture;
+
+    #[test]
+    fn test_parse_dorm_assignment() {
+        assert_eq!(parse_dorm_assignment(&fixture("dorm_assignment.json")).unwrap(), Some(DormAssignment {
+            campus: "邯郸校区".to_string(),
+            building: "北区5号楼".to_string(),
+            room: "5101".to_string(),
+            bed: Some("2".to_string()),
+        }));
+
+        let assignment = parse_dorm_assignment(r#"{"code":0,"data":{"xqmc":"江湾校区","ldmc":"1号楼","fjh":"1101","cwh":""}}"#).unwrap().unwrap();
+        assert_eq!((assignment.room.as_str(), assignment.bed), ("1101", None));
+    }
+
+    #[test]
+    fn test_get_dorm_assignment() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/dorm/index", vec![MockResponse::ok("<html>住宿管理</html>")]);
+        server.route("/dorm/api/student/assignment", vec![MockResponse::json(&fixture("dorm_assignment.json"))]);
+
+        let fd = logged_in_fdu(&server, &["https://zsgl.fudan.edu.cn"], &["/dorm/index"]);
+        let assignment = fd.get_dorm_assignment().unwrap().unwrap();
+        assert_eq!((assignment.building(), assignment.room(), assignment.bed()), ("北区5号楼", "5101", Some("2")));
+        assert!(server.request_lines().iter().any(|line| line.starts_with("GET /dorm/index?ticket=ST-mock")));
+    }
+
+    #[test]
+    fn test_parse_no_dorm() {
+        assert_eq!(parse_dorm_assignment(r#"{"code":0,"msg":"未分配宿舍","data":{"xqmc":"","ldmc":"","fjh":""}}"#).unwrap(), None);
+        assert_eq!(parse_dorm_assignment(r#"{"code":0,"msg":"无住宿信息","data":null}"#).unwrap(), None);
+        parse_dorm_assignment(r#"{"code":401,"msg":"未登录"}"#).expect_err("expect error");
+    }
+}
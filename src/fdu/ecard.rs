@@ -1,18 +1,200 @@
-use scraper::{Html, Selector};
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use scraper::Html;
+use super::html::{select_attr, BODY_ROW_SELECTOR, CELL_SELECTOR};
 use super::prelude::*;
 
 impl ECardClient for Fdu {}
 
 const ECARD_QR_CODE_URL: &str = "https://ecard.fudan.edu.cn/epay/wxpage/fudan/zfm/qrcode";
+const ECARD_INDEX_URL: &str = "https://ecard.fudan.edu.cn/epay/myepay/index";
+const ECARD_CONSUME_QUERY_URL: &str = "https://ecard.fudan.edu.cn/epay/consume/query";
+// How many more times to try a failed QR code refresh in `qr_stream`, and how long to wait before each.
+const QR_REFRESH_RETRIES: usize = 3;
+const QR_REFRESH_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    time: NaiveDateTime,
+    // eg. 消费, 充值
+    kind: String,
+    // eg. 北区食堂
+    location: String,
+    // always positive, see `kind` for the direction
+    amount: f64,
+    balance: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct SpendingSummary {
+    spent: f64,
+    recharged: f64,
+    // location -> money spent there
+    by_location: HashMap<String, f64>,
+}
+
+impl SpendingSummary {
+    fn from_transactions(transactions: &[Transaction]) -> Self {
+        let mut summary = SpendingSummary::default();
+        for transaction in transactions {
+            if transaction.kind == "充值" {
+                summary.recharged += transaction.amount;
+            } else {
+                summary.spent += transaction.amount;
+                *summary.by_location.entry(transaction.location.clone()).or_default() += transaction.amount;
+            }
+        }
+        summary
+    }
+}
+
+// Parse the csrf token from the meta tags of the ecard index page, e.g.
+// <meta name="_csrf" content="a6f1c0e2-...."/>
+fn parse_csrf(html: &str) -> Result<String> {
+    select_attr(&Html::parse_document(html), r#"meta[name="_csrf"]"#, "content")
+}
+
+// Parse the QR code, which is in <input id="myText" value="..."/>
+fn parse_qr_code(html: &str) -> Result<String> {
+    select_attr(&Html::parse_document(html), "#myText", "value")
+}
+
+// Parse one page of the consume query result. Every row is like
+// <tr><td><div>2022.09.01</div><div>12:03:46</div></td><td>消费</td><td>北区食堂</td><td>-12.00</td><td>88.00</td></tr>
+// and the rows are in descending order of time.
+fn parse_transactions(html: &str) -> Result<Vec<Transaction>> {
+    let pe = |what: &str| SDKError::with_type(ErrorType::ParseError, format!("parse transaction {} error", what));
+
+    let document = Html::parse_document(html);
+    let mut transactions = Vec::new();
+    for tr in document.select(&BODY_ROW_SELECTOR) {
+        let cells: Vec<String> = tr.select(&CELL_SELECTOR)
+            .map(|td| td.text().map(str::trim).filter(|t| !t.is_empty()).collect::<Vec<_>>().join(" "))
+            .collect();
+        // the placeholder row when there is no record
+        if cells.len() < 5 {
+            continue;
+        }
+        transactions.push(Transaction {
+            time: NaiveDateTime::parse_from_str(&cells[0], "%Y.%m.%d %H:%M:%S").map_err(|_| pe("time"))?,
+            kind: cells[1].clone(),
+            location: cells[2].clone(),
+            amount: cells[3].parse::<f64>().map_err(|_| pe("amount"))?.abs(),
+            balance: cells[4].parse::<f64>().map_err(|_| pe("balance"))?,
+        });
+    }
+    Ok(transactions)
+}
+
+// Walk through the pages returned by `fetch_page` (starting from 1) and keep the transactions in [from, to].
+//
+// Pages are in descending order of time, so we stop as soon as a transaction earlier than `from` shows up,
+// or a page is empty.
+fn collect_transactions_between<F>(mut fetch_page: F, from: NaiveDate, to: NaiveDate) -> Result<Vec<Transaction>>
+    where F: FnMut(u32) -> Result<Vec<Transaction>> {
+    let mut result = Vec::new();
+    let mut page = 1;
+    loop {
+        let transactions = fetch_page(page)?;
+        if transactions.is_empty() {
+            break;
+        }
+        let mut reached_from = false;
+        for transaction in transactions {
+            let date = transaction.time.date();
+            if date < from {
+                reached_from = true;
+                break;
+            }
+            if date <= to {
+                result.push(transaction);
+            }
+        }
+        if reached_from {
+            break;
+        }
+        page += 1;
+    }
+    Ok(result)
+}
 
 pub trait ECardClient: Account {
-    fn get_qr_code(&self) -> reqwest::Result<String> {
+    fn get_qr_code(&self) -> Result<String> {
+        let html = self.send_and_get_text(self.get_client().get(self.resolve_url(ECARD_QR_CODE_URL)))?;
+        parse_qr_code(&html)
+    }
+
+    // Get `count` QR codes, one every `interval`, since a code expires in about a minute. See `qr_stream_with`.
+    fn qr_stream(&self, interval: Duration, count: usize) -> Result<Vec<String>> {
+        let mut codes = Vec::with_capacity(count);
+        self.qr_stream_with(interval, count, |code| codes.push(code.to_string()))?;
+        Ok(codes)
+    }
+
+    // Same as `qr_stream`, but hand each code to `on_code` as soon as it is there, e.g. to redraw the QR code.
+    //
+    // A refresh failing (e.g. on a flaky connection) is tried again a few times before it gives up,
+    // so one bad request does not end the stream.
+    fn qr_stream_with<F: FnMut(&str)>(&self, interval: Duration, count: usize, mut on_code: F) -> Result<()> {
+        for i in 0..count {
+            if i > 0 {
+                thread::sleep(interval);
+            }
+            let mut result = self.get_qr_code();
+            for _ in 0..QR_REFRESH_RETRIES {
+                if result.is_ok() {
+                    break;
+                }
+                thread::sleep(QR_REFRESH_RETRY_DELAY);
+                result = self.get_qr_code();
+            }
+            on_code(&result?);
+        }
+        Ok(())
+    }
+
+    // Get one page of transactions in the last year, 10 records per page, newest first.
+    fn get_transactions(&self, page: u32) -> Result<Vec<Transaction>> {
         let client = self.get_client();
-        let html = client.get(ECARD_QR_CODE_URL).send()?.text()?;
-        let document = Html::parse_document(html.as_str());
-        let selector = Selector::parse(r##"#myText"##).unwrap();
-        let element = document.select(&selector).next().unwrap();
-        Ok(element.value().attr("value").unwrap().to_string())
+        let csrf = parse_csrf(&self.send_and_get_html(client.get(self.resolve_url(ECARD_INDEX_URL)))?)?;
+        self.query_transactions(&csrf, page)
+    }
+
+    // The balance after the latest transaction, None if there is no transaction in the last year to tell.
+    fn get_balance(&self) -> Result<Option<f64>> {
+        Ok(self.get_transactions(1)?.first().map(|transaction| transaction.balance))
+    }
+
+    // Get all transactions from `from` to `to` (both inclusive), looping through pages internally.
+    fn get_transactions_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Transaction>> {
+        let client = self.get_client();
+        let csrf = parse_csrf(&self.send_and_get_html(client.get(self.resolve_url(ECARD_INDEX_URL)))?)?;
+        collect_transactions_between(|page| self.query_transactions(&csrf, page), from, to)
+    }
+
+    // Sum up the money spent and recharged from `from` to `to`, with a breakdown by location.
+    // An empty range gives a summary of zeros.
+    fn spending_summary(&self, from: NaiveDate, to: NaiveDate) -> Result<SpendingSummary> {
+        let transactions = self.get_transactions_between(from, to)?;
+        Ok(SpendingSummary::from_transactions(&transactions))
+    }
+
+    fn query_transactions(&self, csrf: &str, page: u32) -> Result<Vec<Transaction>> {
+        let page = page.to_string();
+        let payload = [
+            ("aaxmlrequest", "true"),
+            ("pageNo", page.as_str()),
+            ("tabNo", "1"),
+            ("pager.offset", "10"),
+            ("tradename", ""),
+            ("timetype", "1"),
+            ("_csrf", csrf),
+        ];
+        let html = self.send_and_get_html(self.get_client().post(self.resolve_url(ECARD_CONSUME_QUERY_URL)).form(&payload))?;
+        parse_transactions(&html)
     }
 }
 
@@ -31,4 +213,106 @@ mod tests {
         assert!(fd.get_qr_code().expect("qr code error").starts_with("SWL2"));
         fd.logout().expect("logout error");
     }
+
+    const TRANSACTION_PAGE_1: &str = r#"<table><tbody>
+        <tr><td><div>2022.10.02</div><div>12:03:46</div></td><td>消费</td><td>北区食堂</td><td>-12.00</td><td>88.00</td></tr>
+        <tr><td><div>2022.10.01</div><div>08:00:00</div></td><td>充值</td><td>微信充值</td><td>100.00</td><td>100.00</td></tr>
+        <tr><td><div>2022.09.30</div><div>18:30:12</div></td><td>消费</td><td>旦苑食堂</td><td>-15.50</td><td>0.00</td></tr>
+    </tbody></table>"#;
+    const TRANSACTION_PAGE_2: &str = r#"<table><tbody>
+        <tr><td><div>2022.09.01</div><div>11:45:00</div></td><td>消费</td><td>南区食堂</td><td>-10.00</td><td>15.50</td></tr>
+        <tr><td><div>2022.08.31</div><div>17:20:00</div></td><td>消费</td><td>南区食堂</td><td>-9.00</td><td>25.50</td></tr>
+    </tbody></table>"#;
+    const TRANSACTION_PAGE_3: &str = r#"<table><tbody>
+        <tr><td><div>2022.08.01</div><div>09:00:00</div></td><td>消费</td><td>北区食堂</td><td>-5.00</td><td>34.50</td></tr>
+    </tbody></table>"#;
+
+    #[test]
+    fn test_parse_qr_code() {
+        assert_eq!(parse_qr_code(r#"<input id="myText" type="hidden" value="SWL2abc"/>"#).unwrap(), "SWL2abc");
+        // not logged in, we get the login page instead
+        parse_qr_code(r#"<form id="casLoginForm"></form>"#).expect_err("expect error");
+        parse_csrf("<head></head>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_qr_stream() {
+        use std::time::Instant;
+
+        use crate::fdu::mock::{MockResponse, MockServer};
+
+        let qr_code = |code: &str| MockResponse::ok(&format!(r#"<input id="myText" type="hidden" value="{}"/>"#, code));
+        let server = MockServer::start();
+        // the second refresh fails once, and succeeds on retry
+        server.route("/epay/wxpage/fudan/zfm/qrcode", vec![
+            qr_code("SWL2a"),
+            MockResponse::new(502, "<html>Bad Gateway</html>"),
+            qr_code("SWL2b"),
+            qr_code("SWL2c"),
+        ]);
+
+        // the request delay of the ecard applies between refreshes as well
+        let delay = Duration::from_millis(100);
+        let fd = Fdu::builder().endpoint("https://ecard.fudan.edu.cn", &server.url(""))
+            .request_delay("https://ecard.fudan.edu.cn", delay).build().unwrap();
+        let start = Instant::now();
+        assert_eq!(fd.qr_stream(Duration::ZERO, 3).unwrap(), vec!["SWL2a", "SWL2b", "SWL2c"]);
+        assert!(start.elapsed() >= delay * 4, "{:?}", start.elapsed());
+
+        // it gives up when the refresh keeps failing
+        server.route("/epay/wxpage/fudan/zfm/qrcode", vec![MockResponse::new(502, "<html>Bad Gateway</html>")]);
+        let mut codes = Vec::new();
+        fd.qr_stream_with(Duration::ZERO, 2, |code| codes.push(code.to_string())).expect_err("expect error");
+        assert!(codes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_transactions() {
+        let transactions = parse_transactions(TRANSACTION_PAGE_1).unwrap();
+        assert_eq!(transactions.len(), 3);
+        assert_eq!(transactions[0].location, "北区食堂");
+        assert_eq!(transactions[0].amount, 12.0);
+        assert_eq!(transactions[1].kind, "充值");
+        assert_eq!(transactions[2].time.to_string(), "2022-09-30 18:30:12");
+
+        assert!(parse_transactions("<table><tbody><tr><td>暂无记录</td></tr></tbody></table>").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_transactions_between() {
+        let mut fetched = Vec::new();
+        let fetch = |page: u32| {
+            fetched.push(page);
+            match page {
+                1 => parse_transactions(TRANSACTION_PAGE_1),
+                2 => parse_transactions(TRANSACTION_PAGE_2),
+                3 => parse_transactions(TRANSACTION_PAGE_3),
+                _ => Ok(Vec::new()),
+            }
+        };
+        let from = NaiveDate::from_ymd_opt(2022, 9, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2022, 9, 30).unwrap();
+        let transactions = collect_transactions_between(fetch, from, to).unwrap();
+
+        let locations: Vec<_> = transactions.iter().map(|t| t.location.as_str()).collect();
+        assert_eq!(locations, vec!["旦苑食堂", "南区食堂"]);
+        // stop at page 2 since 2022.08.31 is earlier than `from`
+        assert_eq!(fetched, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_spending_summary() {
+        let mut transactions = parse_transactions(TRANSACTION_PAGE_1).unwrap();
+        transactions.extend(parse_transactions(TRANSACTION_PAGE_2).unwrap());
+        let summary = SpendingSummary::from_transactions(&transactions);
+        assert_eq!(summary.spent, 46.5);
+        assert_eq!(summary.recharged, 100.0);
+        assert_eq!(summary.by_location.len(), 3);
+        assert_eq!(summary.by_location["南区食堂"], 19.0);
+
+        let summary = SpendingSummary::from_transactions(&[]);
+        assert_eq!(summary.spent, 0.0);
+        assert_eq!(summary.recharged, 0.0);
+        assert!(summary.by_location.is_empty());
+    }
 }
@@ -0,0 +1,127 @@
+use serde::Deserialize;
+
+use super::prelude::*;
+
+impl ElectricityClient for Fdu {}
+
+// the balance of the student's own room, found from their accommodation record
+const ELECTRICITY_INFO_URL: &str = "https://zlapp.fudan.edu.cn/fudanelec/wap/default/info";
+// the balance of any room
+const ELECTRICITY_SEARCH_URL: &str = "https://zlapp.fudan.edu.cn/fudanelec/wap/default/search";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElectricityBalance {
+    // eg. 邯郸校区
+    campus: String,
+    // eg. 5号楼
+    building: String,
+    // eg. 5101
+    room: String,
+    // the energy used and left, in kWh
+    used: f64,
+    left: f64,
+    // when the meter was last read, eg. 2022-10-08 12:00:00
+    updated: String,
+}
+
+#[derive(Deserialize)]
+struct ElectricityResponse {
+    // 0 means ok
+    e: i32,
+    #[serde(default)]
+    m: String,
+    d: Option<ElectricityData>,
+}
+
+// eg. {"xq":"邯郸校区","ting":"5号楼","room":"5101","fj_used":"120.5","fj_left":"25.3","fj_update_time":"2022-10-08 12:00:00"}
+#[derive(Deserialize)]
+struct ElectricityData {
+    #[serde(default)]
+    xq: String,
+    #[serde(default)]
+    ting: String,
+    #[serde(default)]
+    room: String,
+    #[serde(default)]
+    fj_used: String,
+    #[serde(default)]
+    fj_left: String,
+    #[serde(default)]
+    fj_update_time: String,
+}
+
+fn parse_kwh(text: &str) -> Result<f64> {
+    text.trim().parse()
+        .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse electricity {} error", text)))
+}
+
+// Parse the balance. There is no room (and so no data) for students living off campus, which gives None.
+fn parse_electricity_balance(json: &str) -> Result<Option<ElectricityBalance>> {
+    let response: ElectricityResponse = serde_json::from_str(json)?;
+    if response.e != 0 {
+        return Err(SDKError::with_type(ErrorType::OtherError, format!("get electricity balance error: {}", response.m)));
+    }
+    let data = match response.d {
+        Some(data) if !data.room.trim().is_empty() => data,
+        _ => return Ok(None),
+    };
+    Ok(Some(ElectricityBalance {
+        used: parse_kwh(&data.fj_used)?,
+        left: parse_kwh(&data.fj_left)?,
+        campus: data.xq,
+        building: data.ting,
+        room: data.room,
+        updated: data.fj_update_time,
+    }))
+}
+
+pub trait ElectricityClient: Account {
+    // Get the electricity balance of the dorm room. The room is the student's own, from their accommodation record,
+    // unless `room` is given as (building, room), eg. ("5号楼", "5101"). None if there is no such room, e.g. off campus.
+    fn get_electricity_balance(&self, room: Option<(&str, &str)>) -> Result<Option<ElectricityBalance>> {
+        let request = match room {
+            Some((building, room)) => self.get_client().post(self.resolve_url(ELECTRICITY_SEARCH_URL))
+                .form(&[("ting", building), ("room", room)]),
+            None => self.get_client().get(self.resolve_url(ELECTRICITY_INFO_URL)),
+        };
+        parse_electricity_balance(&self.send_and_get_json(request)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_electricity_balance() {
+        const BALANCE: &str = r#"{"e":0,"m":"操作成功","d":{"xq":"邯郸校区","ting":"5号楼","room":"5101","fj_used":"120.5","fj_left":"25.3","fj_update_time":"2022-10-08 12:00:00"}}"#;
+        assert_eq!(parse_electricity_balance(BALANCE).unwrap(), Some(ElectricityBalance {
+            campus: "邯郸校区".to_string(),
+            building: "5号楼".to_string(),
+            room: "5101".to_string(),
+            used: 120.5,
+            left: 25.3,
+            updated: "2022-10-08 12:00:00".to_string(),
+        }));
+        parse_electricity_balance(r#"{"e":1,"m":"请先登录"}"#).expect_err("expect error");
+    }
+
+    #[test]
+    fn test_parse_off_campus() {
+        assert_eq!(parse_electricity_balance(r#"{"e":0,"m":"操作成功","d":{"room":""}}"#).unwrap(), None);
+        assert_eq!(parse_electricity_balance(r#"{"e":0,"m":"未找到住宿信息","d":null}"#).unwrap(), None);
+    }
+
+    #[test]
+    fn test_electricity_balance_of_room() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("POST /fudanelec/wap/default/search", vec![MockResponse::json(r#"{"e":0,"d":{"xq":"江湾校区","ting":"1号楼","room":"1101","fj_used":"3","fj_left":"0.5"}}"#)]);
+
+        let fd = mock_fdu(&server, &["https://zlapp.fudan.edu.cn"]);
+        let balance = fd.get_electricity_balance(Some(("1号楼", "1101"))).unwrap().unwrap();
+        assert_eq!((balance.room.as_str(), balance.left), ("1101", 0.5));
+        assert!(server.requests()[0].ends_with("room=1101"));
+    }
+}
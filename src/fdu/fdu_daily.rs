@@ -7,7 +7,7 @@ const GET_INFO_URL: &str = "https://zlapp.fudan.edu.cn/ncov/wap/fudan/get-info";
 
 
 pub fn get_history_info(fdu: &Fdu) -> Result<String> {
-    Ok(fdu.get_client().get(GET_INFO_URL).send()?.text()?)
+    fdu.send_and_get_text(fdu.get_client().get(fdu.resolve_url(GET_INFO_URL)))
 }
 
 pub fn has_tick(fdu: &Fdu) -> Result<bool> {
@@ -68,14 +68,23 @@ pub fn has_tick(fdu: &Fdu) -> Result<bool> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fdu::test_support::{fixture, logged_in_fdu, MockResponse, MockServer};
 
     #[test]
     fn check_fdu_daily() {
-        dotenv::dotenv().ok();
-        let uid = std::env::var("UID").expect("environment variable UID not set");
-        let pwd = std::env::var("PWD").expect("environment variable PWD not set");
-        let mut fd = Fdu::new();
-        fd.login(uid.as_str(), pwd.as_str()).expect("login error");
-        assert!(has_tick(&fd).unwrap())
+        let server = MockServer::start();
+        let today = Local::now().format("%Y%m%d").to_string();
+        server.route("/ncov/wap/fudan/get-info", vec![
+            MockResponse::ok(&format!(r#"{{"e":0,"d":{{"info":{{"date":"{}"}}}}}}"#, today)),
+            // last reported on 2022-08-28
+            MockResponse::ok(&fixture("fdu_daily_info.json")),
+            // never reported
+            MockResponse::ok(r#"{"e":0,"d":{}}"#),
+        ]);
+
+        let fd = logged_in_fdu(&server, &["https://zlapp.fudan.edu.cn"], &[]);
+        assert!(has_tick(&fd).unwrap());
+        assert!(!has_tick(&fd).unwrap());
+        assert!(!has_tick(&fd).unwrap());
     }
-}
\ No newline at end of file
+}
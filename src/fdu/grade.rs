@@ -1,10 +1,16 @@
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use reqwest::blocking::Client;
 use reqwest::cookie::Jar;
-use scraper::{Html, Selector};
+use scraper::Html;
+use serde::{Deserialize, Serialize};
 
+use super::html::BODY_ROW_SELECTOR;
+use super::number::parse_number;
 use super::prelude::*;
 
 struct Grade {
@@ -26,6 +32,23 @@ impl Grade {
             grades: Vec::new(),
         }
     }
+
+    // Serialize the cached grades, so that they can be stored and restored on the next launch without scraping again.
+    fn to_snapshot(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.grades).expect("serialize grades error")
+    }
+
+    fn from_snapshot(fdu: Fdu, bytes: &[u8]) -> Result<Self> {
+        let mut grades: Vec<CourseGrade> = serde_json::from_slice(bytes)?;
+        // marked again, for the snapshots taken before `excluded_from_gpa` was added
+        mark_excluded_from_gpa(&mut grades);
+        Ok(Self { fdu, grades })
+    }
+
+    // Log out and drop the client, see `Fdu::close`.
+    fn close(self) -> Result<()> {
+        self.fdu.close()
+    }
 }
 
 impl HttpClient for Grade {
@@ -33,13 +56,41 @@ impl HttpClient for Grade {
         self.fdu.get_client()
     }
 
+    fn get_client_following(&self, follow: bool) -> &Client {
+        self.fdu.get_client_following(follow)
+    }
+
     fn get_cookie_store(&self) -> &Arc<Jar> {
         self.fdu.get_cookie_store()
     }
+
+    fn get_capture_dir(&self) -> Option<&Path> {
+        self.fdu.get_capture_dir()
+    }
+
+    fn get_endpoint_overrides(&self) -> &[(String, String)] {
+        self.fdu.get_endpoint_overrides()
+    }
+
+    fn get_max_body_size(&self) -> usize {
+        self.fdu.get_max_body_size()
+    }
+
+    fn get_request_delays(&self) -> &[(String, Duration)] {
+        self.fdu.get_request_delays()
+    }
+
+    fn get_extra_headers(&self) -> &[(String, String, String)] {
+        self.fdu.get_extra_headers()
+    }
+
+    fn get_strict_decoding(&self) -> bool {
+        self.fdu.get_strict_decoding()
+    }
 }
 
-#[derive(Clone, Debug)]
-struct CourseGrade {
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CourseGrade {
     code: String,
     name: String,
     year: String,
@@ -47,10 +98,73 @@ struct CourseGrade {
     credit: f64,
     grade: String,
     point: f64,
+    // not counted in the gpa, see `mark_excluded_from_gpa`. Missing in the snapshots taken before it was added.
+    #[serde(default)]
+    excluded_from_gpa: bool,
+}
+
+impl CourseGrade {
+    // The grade point of the grade on `scale`. `point` is the one on Fudan's scale.
+    pub fn point_on(&self, scale: GradeScale) -> f64 {
+        match scale {
+            GradeScale::Fudan4_0 => self.point,
+            GradeScale::Standard4_0 => standard_point(&self.grade),
+            GradeScale::Percentage => percentage(&self.grade),
+        }
+    }
+
+    // Whether the grade is left out of the gpa: a pass/fail or audit grade, or an attempt of a course taken again later.
+    pub fn is_excluded_from_gpa(&self) -> bool {
+        self.excluded_from_gpa
+    }
+
+    // The term of the grade, to sort or compare grades by. The raw `year` and `semester` are kept as they are.
+    pub fn term(&self) -> Result<Term> {
+        Term::parse(&self.year, &self.semester)
+    }
+}
+
+// A term (学期) of a school year, ordered chronologically: the fall semester (1), then the spring one (2),
+// then the summer term (3, 暑期) of the same school year.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Term {
+    // the year the school year starts in, eg. 2022 for 2022-2023
+    start_year: i32,
+    semester: u8,
+}
+
+impl Term {
+    // Parse a term from the school year (eg. 2022-2023) and the semester (eg. 1, 2, 3 or 暑期) as shown on the grade list.
+    pub fn parse(year: &str, semester: &str) -> Result<Self> {
+        let error = || SDKError::with_type(ErrorType::ParseError, format!("parse term {} {} error", year, semester));
+        let start_year = match year.trim().split_once('-') {
+            Some((start, end)) => {
+                let (start, end): (i32, i32) = (start.parse().map_err(|_| error())?, end.parse().map_err(|_| error())?);
+                if end != start + 1 {
+                    return Err(error());
+                }
+                start
+            }
+            None => return Err(error()),
+        };
+        let semester = match semester.trim() {
+            "1" => 1,
+            "2" => 2,
+            "3" | "暑期" => 3,
+            _ => return Err(error()),
+        };
+        Ok(Self { start_year, semester })
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{} {}", self.start_year, self.start_year + 1, self.semester)
+    }
 }
 
 #[derive(Default)]
-struct GPA {
+pub(crate) struct GPA {
     gpa: f64,
     ranking: i32,
     total: i32,
@@ -67,48 +181,93 @@ impl Display for GPA {
     }
 }
 
+// A ranking by gpa among some of the students, eg. 3 (`ranking`) of 120 (`total`), 1 being the highest.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rank {
+    ranking: i32,
+    total: i32,
+}
+
+// The rankings among the students of the same major, the same department (院系) and the same grade (年级, eg. 2020).
+// jwfw does not list the class (班级), so there is no ranking within a class.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rankings {
+    major: Rank,
+    department: Rank,
+    grade: Rank,
+}
+
+// the gpa ranking of all the majors in the school, see `get_gpa_from_jwfw`
+const GPA_SEARCH_URL: &str = "https://jwfw.fudan.edu.cn/eams/myActualGpa!search.action";
+
+// The highest grade point, of an A.
+const MAX_POINT: f64 = 4.0;
+
+impl GPA {
+    // The average grade point needed over `additional_credits` more credits to bring the gpa up (or down) to `target_gpa`.
+    // It is 0 if the target is reached whatever the grades are, and an error if it cannot be reached even with all A's.
+    fn required_average(&self, target_gpa: f64, additional_credits: f64) -> Result<f64> {
+        if additional_credits <= 0.0 {
+            return Err(SDKError::with_type(ErrorType::OtherError, format!("additional credits must be positive, got {}", additional_credits)));
+        }
+        let required = (target_gpa * (self.credits + additional_credits) - self.gpa * self.credits) / additional_credits;
+        // leave some room for rounding, so that a target reachable with exactly all A's is not rejected
+        if required > MAX_POINT + 1e-9 {
+            return Err(SDKError::with_type(ErrorType::OtherError,
+                format!("gpa {} is unreachable with {} more credits, it takes an average of {:.2}", target_gpa, additional_credits, required)));
+        }
+        Ok(required.clamp(0.0, MAX_POINT))
+    }
+}
+
 impl Grade {
+    // Iterate over the grades without cloning them. It is empty until the grades are fetched, see `fetch_grades`.
+    fn iter_grades(&self) -> impl Iterator<Item = &CourseGrade> {
+        self.grades.iter()
+    }
+
     fn get_all_grades(&mut self) -> Result<Vec<CourseGrade>> {
-        if self.grades.len() != 0 {
-            return Ok(self.grades.to_vec());
+        self.fetch_grades()?;
+        Ok(self.iter_grades().cloned().collect())
+    }
+
+    // Fetch the grades into the cache, unless they are there already.
+    fn fetch_grades(&mut self) -> Result<()> {
+        if !self.grades.is_empty() {
+            return Ok(());
         }
 
         const GRADE_URL: &str = "https://my.fudan.edu.cn/list/bks_xx_cj";
         let mut grades: Vec<CourseGrade> = Vec::new();
 
-        let html = self.send_and_get_text(self.get_client().get(GRADE_URL))?;
+        let html = self.send_and_get_html(self.get_client().get(self.resolve_url(GRADE_URL)))?;
         let document = Html::parse_document(html.as_str());
-        for tr in document.select(&Selector::parse("tbody tr").unwrap()) {
+        for tr in document.select(&BODY_ROW_SELECTOR) {
             let v = tr.text().collect::<Vec<_>>();
             grades.push(CourseGrade {
                 code: v[0].to_string(),
                 year: v[1].to_string(),
                 semester: v[2].to_string(),
                 name: v[3].to_string(),
-                credit: v[4].parse::<f64>().expect("parse credict error"),
+                credit: parse_number(v[4])
+                    .ok_or_else(|| SDKError::with_type(ErrorType::ParseError, format!("parse credit {} of {} error", v[4], v[3])))?,
                 grade: v[5].to_string(),
                 point: grade_to_point(v[5]),
+                excluded_from_gpa: false,
             });
         }
+        mark_excluded_from_gpa(&mut grades);
 
         self.grades = grades;
-        Ok(self.grades.to_vec())
+        Ok(())
     }
 
     fn get_grades_of_this_semester(&mut self) -> Result<Vec<CourseGrade>> {
-        if self.get_all_grades()?.len() == 0 {
-            return Ok(Vec::new());
-        }
-        let year = &self.grades[0].year;
-        let semester = &self.grades[0].semester;
-        let mut i = 0;
-        for grade in &self.grades[..] {
-            if !grade.year.eq(year) || !grade.semester.eq(semester) {
-                break;
-            }
-            i += 1;
-        }
-        Ok(self.grades[..i].to_vec())
+        self.fetch_grades()?;
+        Ok(split_by_semester(&self.grades).into_iter()
+            .next()
+            .map(|(_, _, grades)| grades)
+            .unwrap_or_default())
     }
 
     fn get_gpa(&mut self) -> GPA {
@@ -127,16 +286,22 @@ impl Grade {
     }
 
     fn get_gpa_from_grades(&mut self) -> Result<GPA> {
-        let grades = self.get_all_grades()?;
-        if grades.len() == 0 {
+        self.get_gpa_from_grades_on(GradeScale::Fudan4_0)
+    }
+
+    // The gpa on another scale than Fudan's, eg. for a transcript sent abroad, see `GradeScale`.
+    // Only the `gpa` and `credits` are filled in, as the rankings are on Fudan's scale.
+    fn get_gpa_from_grades_on(&mut self, scale: GradeScale) -> Result<GPA> {
+        self.fetch_grades()?;
+        if self.grades.is_empty() {
             return Ok(GPA::default());
         }
         let mut gpa = GPA::default();
-        for grade in grades {
-            if grade.grade.eq("P") { // P isn't calculated
+        for grade in self.iter_grades() {
+            if grade.excluded_from_gpa {
                 continue;
             }
-            gpa.gpa += grade.point * grade.credit;
+            gpa.gpa += grade.point_on(scale) * grade.credit;
             gpa.credits += grade.credit;
         }
         gpa.gpa /= gpa.credits;
@@ -144,52 +309,197 @@ impl Grade {
     }
 
     fn get_gpa_from_jwfw(&mut self) -> Result<GPA> {
-        let mut gpa = GPA::default();
-        let mut major = "";
+        get_gpa_from_jwfw(self)
+    }
 
-        // get data
-        const GPA_SEARCH_URL: &str = "https://jwfw.fudan.edu.cn/eams/myActualGpa!search.action";
-        let html = self.send_and_get_text(
-            self.get_client().get(GPA_SEARCH_URL)
-        )?;
-        let document = Html::parse_document(html.as_str());
-        let selector = Selector::parse("tbody tr").unwrap();
-
-        // it contains all majors in a school, so we have to find my major
-        for tr in document.select(&selector) {
-            let mut v = tr.text().collect::<Vec<_>>();
-            v.retain(|&x| x.trim() != "");
-            if !v[0].starts_with("*") { // it's me!
-                major = v[3];
-                gpa.gpa = v[5].parse::<f64>().expect("parse gpa error");
-                gpa.credits = v[6].parse::<f64>().expect("parse credits error");
-                break;
-            }
+    // Get just the rankings from jwfw, without the gpa, see `Rankings`. It needs to be logged in to jwfw as well.
+    fn get_rankings(&mut self) -> Result<Rankings> {
+        let html = self.send_and_get_html(self.get_client().get(self.resolve_url(GPA_SEARCH_URL)))?;
+        parse_rankings(&html)
+    }
+}
+
+// Parse the rankings from the gpa ranking page. The columns are
+// 学号 (masked as *****0001 except ours), 年级, 院系, 专业, 姓名, 绩点 and 学分, with the highest gpa first.
+fn parse_rankings(html: &str) -> Result<Rankings> {
+    const GRADE: usize = 1;
+    const DEPARTMENT: usize = 2;
+    const MAJOR: usize = 3;
+
+    let document = Html::parse_document(html);
+    let rows: Vec<Vec<&str>> = document.select(&BODY_ROW_SELECTOR)
+        .map(|tr| tr.text().map(str::trim).filter(|text| !text.is_empty()).collect::<Vec<_>>())
+        .filter(|cells| cells.len() > MAJOR)
+        .collect();
+    let me = rows.iter().position(|cells| !cells[0].starts_with('*'))
+        .ok_or_else(|| SDKError::with_type(ErrorType::ParseError, "cannot find my row in the gpa ranking".to_string()))?;
+
+    let rank = |column: usize| {
+        let same = |cells: &&Vec<&str>| cells[column] == rows[me][column];
+        Rank {
+            ranking: rows[..=me].iter().filter(same).count() as i32,
+            total: rows.iter().filter(same).count() as i32,
         }
+    };
+    Ok(Rankings { major: rank(MAJOR), department: rank(DEPARTMENT), grade: rank(GRADE) })
+}
 
-        // find ranking, because records are in descending order
-        for tr in document.select(&selector) {
-            let mut v = tr.text().collect::<Vec<_>>();
-            v.retain(|&x| x.trim() != "");
-            if v[3] != major {
-                continue;
-            }
-            // my major
-            gpa.total += 1;
-            if !v[0].starts_with("*") { // it's me!
-                gpa.ranking = gpa.total
-            }
+// Get the gpa and the ranking in the major from jwfw, which needs to be logged in, see `JwfwClient::login_jwfw`.
+// It takes any client rather than a `Grade`, so that it can be fetched along with other things, see `Dashboard`.
+pub(crate) fn get_gpa_from_jwfw<C: HttpClient>(client: &C) -> Result<GPA> {
+    let mut gpa = GPA::default();
+    let mut major = "";
+
+    // get data
+    let html = client.send_and_get_html(
+        client.get_client().get(client.resolve_url(GPA_SEARCH_URL))
+    )?;
+    let document = Html::parse_document(html.as_str());
+
+    // it contains all majors in a school, so we have to find my major
+    for tr in document.select(&BODY_ROW_SELECTOR) {
+        let mut v = tr.text().collect::<Vec<_>>();
+        v.retain(|&x| x.trim() != "");
+        if v.len() < 7 {
+            return Err(SDKError::with_type(ErrorType::ParseError, format!("unexpected gpa ranking row {:?}", v)));
         }
+        if !v[0].starts_with("*") { // it's me!
+            major = v[3];
+            gpa.gpa = v[5].trim().parse::<f64>()
+                .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse gpa {} error", v[5])))?;
+            gpa.credits = parse_number(v[6])
+                .ok_or_else(|| SDKError::with_type(ErrorType::ParseError, format!("parse credits {} error", v[6])))?;
+            break;
+        }
+    }
 
-        if gpa.total != 0 { // calculate percentage
-            gpa.percentage = gpa.ranking as f64 / gpa.total as f64;
+    // find ranking, because records are in descending order
+    for tr in document.select(&BODY_ROW_SELECTOR) {
+        let mut v = tr.text().collect::<Vec<_>>();
+        v.retain(|&x| x.trim() != "");
+        // every row is checked to be long enough above
+        if v[3] != major {
+            continue;
+        }
+        // my major
+        gpa.total += 1;
+        if !v[0].starts_with("*") { // it's me!
+            gpa.ranking = gpa.total
         }
+    }
 
-        Ok(gpa)
+    if gpa.total != 0 { // calculate percentage
+        gpa.percentage = gpa.ranking as f64 / gpa.total as f64;
+    }
+
+    Ok(gpa)
+}
+
+// Group the grades into semesters, as (year, semester, grades), e.g. ("2022-2023", "1", [...]) for a transcript view.
+//
+// The grades come newest first with the courses of a term next to each other, so a semester is a run of grades in the
+// same term, and the semesters are newest first as well. A term showing up again later (which it should not) makes a new group.
+pub fn split_by_semester(grades: &[CourseGrade]) -> Vec<(&str, &str, Vec<CourseGrade>)> {
+    let mut semesters: Vec<(&str, &str, Vec<CourseGrade>)> = Vec::new();
+    for grade in grades {
+        match semesters.last_mut() {
+            Some((year, semester, group)) if *year == grade.year && *semester == grade.semester => group.push(grade.clone()),
+            _ => semesters.push((&grade.year, &grade.semester, vec![grade.clone()])),
+        }
+    }
+    semesters
+}
+
+// The lowest scores of the letter grades for the courses graded in 百分制 (0-100), eg. 85-89 is an A-.
+// There is no band of D+, and below 60 is an F.
+const SCORE_BANDS: [(f64, &str); 9] = [
+    (90.0, "A"), (85.0, "A-"), (82.0, "B+"), (78.0, "B"), (75.0, "B-"), (71.0, "C+"), (66.0, "C"), (62.0, "C-"), (60.0, "D"),
+];
+
+// The letter grade of a 百分制 score, or None if `grade` is not a score in 0-100.
+fn score_to_grade(grade: &str) -> Option<&'static str> {
+    let score: f64 = grade.trim().parse().ok()?;
+    if !(0.0..=100.0).contains(&score) {
+        return None;
+    }
+    Some(SCORE_BANDS.iter().find(|(lowest, _)| score >= *lowest).map_or("F", |(_, grade)| grade))
+}
+
+// The scales a gpa can be computed on, see `CourseGrade::point_on`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradeScale {
+    // Fudan's own: A 4.0, A- 3.7, B+ 3.3, B 3.0, B- 2.7, C+ 2.3, C 2.0, C- 1.7, D+ 1.3, D 1.0, F 0,
+    // with the 百分制 scores taken as their letters, see `SCORE_BANDS`.
+    Fudan4_0,
+    // The common 4.0 scale of US admissions, without the +/-: A and A- 4, B's 3, C's 2, D's 1, F 0.
+    // Scores are taken as their letters too, eg. 84 is a B+ and so 3.
+    Standard4_0,
+    // The 百分制 score itself, 0-100. A letter takes the middle of its band, eg. 95 for A (90-100) and 87.5 for A- (85-90),
+    // and F takes 0, as its band says nothing about the score.
+    Percentage,
+}
+
+// The letter of a letter grade or a 百分制 score, or None for anything else, eg. P.
+fn letter_of(grade: &str) -> Option<&str> {
+    score_to_grade(grade).or_else(|| {
+        let grade = grade.trim();
+        (SCORE_BANDS.iter().any(|(_, letter)| *letter == grade) || grade == "D+" || grade == "F").then_some(grade)
+    })
+}
+
+fn standard_point(grade: &str) -> f64 {
+    match letter_of(grade).and_then(|letter| letter.chars().next()) {
+        Some('A') => 4.0,
+        Some('B') => 3.0,
+        Some('C') => 2.0,
+        Some('D') => 1.0,
+        _ => 0.0,
+    }
+}
+
+fn percentage(grade: &str) -> f64 {
+    if let Some(score) = grade.trim().parse::<f64>().ok().filter(|score| (0.0..=100.0).contains(score)) {
+        return score;
+    }
+    // D+ has no band, and lies between D and C-
+    let letter = match letter_of(grade) {
+        Some("D+") => "D",
+        Some(letter) => letter,
+        None => return 0.0,
+    };
+    let bands: Vec<f64> = [100.0].into_iter().chain(SCORE_BANDS.iter().map(|(lowest, _)| *lowest)).collect();
+    SCORE_BANDS.iter().position(|(_, band)| *band == letter)
+        .map_or(0.0, |i| (bands[i] + bands[i + 1]) / 2.0)
+}
+
+// The grades that carry no grade point: pass/fail (P, NP, 通过, 不通过), audit (旁听), exempted (免修) and withdrawn (W).
+const NON_GPA_GRADES: [&str; 8] = ["P", "NP", "通过", "不通过", "旁听", "免修", "W", "退课"];
+
+// Mark the grades left out of the gpa: those of `NON_GPA_GRADES`, and the earlier attempts of a course taken again
+// (重修), as only the latest attempt counts. The attempts are told by the course code without the class number,
+// eg. COMP130004 for COMP130004.03, and ordered by term. Grades of a term that cannot be parsed are never earlier.
+fn mark_excluded_from_gpa(grades: &mut [CourseGrade]) {
+    let base_code = |grade: &CourseGrade| grade.code.split('.').next().unwrap_or_default().to_string();
+    let mut latest: HashMap<String, Term> = HashMap::new();
+    for grade in grades.iter() {
+        if let Ok(term) = grade.term() {
+            let latest = latest.entry(base_code(grade)).or_insert(term);
+            *latest = (*latest).max(term);
+        }
+    }
+    for grade in grades.iter_mut() {
+        let retaken = match (grade.term(), latest.get(&base_code(grade))) {
+            (Ok(term), Some(latest)) => term < *latest,
+            _ => false,
+        };
+        grade.excluded_from_gpa = NON_GPA_GRADES.contains(&grade.grade.trim()) || retaken;
     }
 }
 
 fn grade_to_point(grade: &str) -> f64 {
+    if let Some(letter) = score_to_grade(grade) {
+        return grade_to_point(letter);
+    }
     match grade {
         "A" => 4.0,
         "A-" => 3.7,
@@ -216,6 +526,237 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_snapshot() {
+        let mut grade = Grade::new();
+        grade.grades = vec![CourseGrade {
+            code: "COMP130004.03".to_string(),
+            name: "数据结构".to_string(),
+            year: "2021-2022".to_string(),
+            semester: "1".to_string(),
+            credit: 3.0,
+            grade: "A-".to_string(),
+            point: 3.7,
+            excluded_from_gpa: false,
+        }];
+
+        let snapshot = grade.to_snapshot();
+        let restored = Grade::from_snapshot(Fdu::new(), &snapshot).unwrap();
+        assert_eq!(restored.grades, grade.grades);
+
+        assert!(Grade::from_snapshot(Fdu::new(), b"not a snapshot").is_err());
+    }
+
+    #[test]
+    fn test_iter_grades() {
+        let grade = |code: &str, semester: &str, grade: &str, point: f64| CourseGrade {
+            code: code.to_string(),
+            name: code.to_string(),
+            year: "2021-2022".to_string(),
+            semester: semester.to_string(),
+            credit: 2.0,
+            grade: grade.to_string(),
+            point,
+            excluded_from_gpa: grade == "P",
+        };
+        let mut grades = Grade::new();
+        // cached, so nothing is fetched
+        grades.grades = vec![grade("A.01", "2", "A", 4.0), grade("B.01", "2", "P", 0.0), grade("C.01", "1", "B", 3.0)];
+
+        // borrowed from the cache, not cloned
+        let first = grades.iter_grades().next().unwrap();
+        assert!(std::ptr::eq(first, &grades.grades[0]));
+        assert_eq!(grades.iter_grades().map(|grade| grade.code.as_str()).collect::<Vec<_>>(), vec!["A.01", "B.01", "C.01"]);
+
+        assert_eq!(grades.get_grades_of_this_semester().unwrap().len(), 2);
+        let gpa = grades.get_gpa_from_grades().unwrap();
+        assert_eq!((gpa.gpa, gpa.credits), (3.5, 4.0));
+    }
+
+    fn course_grade(code: &str, year: &str, semester: &str) -> CourseGrade {
+        CourseGrade {
+            code: code.to_string(),
+            name: code.to_string(),
+            year: year.to_string(),
+            semester: semester.to_string(),
+            credit: 2.0,
+            grade: "A".to_string(),
+            point: 4.0,
+            excluded_from_gpa: false,
+        }
+    }
+
+    #[test]
+    fn test_grade_scale() {
+        let graded = |code: &str, grade: &str, credit: f64| CourseGrade {
+            grade: grade.to_string(),
+            point: grade_to_point(grade),
+            credit,
+            ..course_grade(code, "2022-2023", "1")
+        };
+        let mut grades = Grade::new();
+        grades.grades = vec![graded("A.01", "A-", 3.0), graded("B.01", "B", 2.0), graded("C.01", "84", 2.0), graded("D.01", "F", 1.0)];
+
+        let fudan = grades.get_gpa_from_grades_on(GradeScale::Fudan4_0).unwrap();
+        let standard = grades.get_gpa_from_grades_on(GradeScale::Standard4_0).unwrap();
+        assert_eq!(fudan.credits, 8.0);
+        assert!((fudan.gpa - (3.7 * 3.0 + 3.0 * 2.0 + 3.3 * 2.0) / 8.0).abs() < 1e-9);
+        // the +/- are gone, so the A- and the B+ (84) count for more
+        assert_eq!(standard.gpa, (4.0 * 3.0 + 3.0 * 2.0 + 3.0 * 2.0) / 8.0);
+        assert!(standard.gpa > fudan.gpa);
+        assert_eq!(grades.get_gpa_from_grades().unwrap().gpa, fudan.gpa);
+
+        let percentage = grades.get_gpa_from_grades_on(GradeScale::Percentage).unwrap();
+        assert_eq!(percentage.gpa, (87.5 * 3.0 + 80.0 * 2.0 + 84.0 * 2.0) / 8.0);
+        assert_eq!(grades.grades[0].point_on(GradeScale::Percentage), 87.5);
+        assert_eq!(graded("E.01", "A", 1.0).point_on(GradeScale::Percentage), 95.0);
+        assert_eq!(graded("E.01", "D+", 1.0).point_on(GradeScale::Standard4_0), 1.0);
+        assert_eq!(graded("E.01", "P", 1.0).point_on(GradeScale::Standard4_0), 0.0);
+    }
+
+    #[test]
+    fn test_excluded_from_gpa() {
+        let graded = |code: &str, year: &str, grade: &str| CourseGrade {
+            grade: grade.to_string(),
+            point: grade_to_point(grade),
+            ..course_grade(code, year, "1")
+        };
+        let mut grades = vec![
+            graded("PEDU110018.01", "2021-2022", "P"),
+            graded("PEDU110019.01", "2021-2022", "NP"),
+            graded("ECON130003.01", "2021-2022", "旁听"),
+            // failed, then retaken in another class
+            graded("COMP130004.01", "2020-2021", "F"),
+            graded("COMP130004.03", "2021-2022", "B+"),
+            graded("MATH120016.01", "2021-2022", "A"),
+        ];
+        mark_excluded_from_gpa(&mut grades);
+        assert_eq!(grades.iter().map(CourseGrade::is_excluded_from_gpa).collect::<Vec<_>>(), vec![true, true, true, true, false, false]);
+
+        let mut grade = Grade::new();
+        grade.grades = grades;
+        let gpa = grade.get_gpa_from_grades().unwrap();
+        assert_eq!((gpa.gpa, gpa.credits), (3.65, 4.0));
+
+        // an old snapshot without the flag
+        let json = r#"{"code":"A.01","name":"A","year":"2021-2022","semester":"1","credit":2.0,"grade":"A","point":4.0}"#;
+        assert!(!serde_json::from_str::<CourseGrade>(json).unwrap().is_excluded_from_gpa());
+    }
+
+    #[test]
+    fn test_bad_credit() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/list/bks_xx_cj", vec![MockResponse::ok("<table><tbody>\
+            <tr><td>COMP130004.03</td><td>2021-2022</td><td>1</td><td>数据结构</td><td>3 学分</td><td>A</td></tr>\
+            <tr><td>COMP130011.01</td><td>2021-2022</td><td>1</td><td>操作系统</td><td>1 2</td><td>A</td></tr>\
+            </tbody></table>")]);
+        let mut grade = Grade::new_from_fdu(mock_fdu(&server, &["https://my.fudan.edu.cn"]));
+        let err = grade.get_all_grades().expect_err("expect parse error");
+        assert!(err.to_string().contains("操作系统"), "{}", err);
+    }
+
+    #[test]
+    fn test_term_order() {
+        let term = |year: &str, semester: &str| Term::parse(year, semester).unwrap();
+        // a string comparison would put 2022-2023 暑期 first
+        let mut terms = [term("2022-2023", "暑期"), term("2022-2023", "2"), term("2021-2022", "1"), term("2022-2023", "1")];
+        terms.sort();
+        assert_eq!(terms.iter().map(Term::to_string).collect::<Vec<_>>(), vec!["2021-2022 1", "2022-2023 1", "2022-2023 2", "2022-2023 3"]);
+        assert_eq!(term(" 2022-2023", "3 "), term("2022-2023", "暑期"));
+        assert!(course_grade("A.01", "2021-2022", "2").term().unwrap() < course_grade("B.01", "2022-2023", "1").term().unwrap());
+
+        Term::parse("2022", "1").expect_err("expect error");
+        Term::parse("2022-2024", "1").expect_err("expect error");
+        Term::parse("2022-2023", "春季").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_split_by_semester() {
+        let grades = vec![
+            course_grade("A.01", "2022-2023", "1"),
+            course_grade("B.01", "2022-2023", "1"),
+            course_grade("C.01", "2021-2022", "2"),
+            course_grade("D.01", "2021-2022", "1"),
+            course_grade("E.01", "2021-2022", "1"),
+        ];
+        let semesters = split_by_semester(&grades);
+        let summary: Vec<(&str, &str, Vec<&str>)> = semesters.iter()
+            .map(|(year, semester, grades)| (*year, *semester, grades.iter().map(|grade| grade.code.as_str()).collect()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("2022-2023", "1", vec!["A.01", "B.01"]),
+            ("2021-2022", "2", vec!["C.01"]),
+            ("2021-2022", "1", vec!["D.01", "E.01"]),
+        ]);
+    }
+
+    #[test]
+    fn test_split_single_semester() {
+        let grades = vec![course_grade("A.01", "2022-2023", "1"), course_grade("B.01", "2022-2023", "1")];
+        let semesters = split_by_semester(&grades);
+        assert_eq!(semesters.len(), 1);
+        assert_eq!((semesters[0].0, semesters[0].1), ("2022-2023", "1"));
+        assert_eq!(semesters[0].2, grades);
+
+        assert!(split_by_semester(&[]).is_empty());
+
+        let mut cached = Grade::new();
+        cached.grades = grades.clone();
+        assert_eq!(cached.get_grades_of_this_semester().unwrap(), grades);
+    }
+
+    #[test]
+    fn test_parse_rankings() {
+        use crate::fdu::test_support::fixture;
+
+        let rankings = parse_rankings(&fixture("jwfw_gpa_ranking.html")).unwrap();
+        assert_eq!(rankings, Rankings {
+            major: Rank { ranking: 3, total: 4 },
+            department: Rank { ranking: 4, total: 6 },
+            grade: Rank { ranking: 3, total: 6 },
+        });
+
+        parse_rankings("<table><tbody><tr><td>*****0011</td><td>2020</td><td>计算机学院</td><td>软件工程</td></tr></tbody></table>")
+            .expect_err("expect error");
+    }
+
+    #[test]
+    fn test_numeric_grade() {
+        assert_eq!(grade_to_point("92"), 4.0);
+        assert_eq!(grade_to_point("60"), 1.0);
+        assert_eq!(grade_to_point("59.5"), 0.0);
+        assert_eq!(grade_to_point("84"), 3.3);
+        assert_eq!(grade_to_point(" 85 "), 3.7);
+        assert_eq!(grade_to_point("B+"), 3.3);
+        assert_eq!(score_to_grade("101"), None);
+
+        // counted in the gpa like the letter grades
+        let mut grades = Grade::new();
+        grades.grades = vec![
+            CourseGrade { grade: "92".to_string(), point: grade_to_point("92"), ..course_grade("A.01", "2022-2023", "1") },
+            CourseGrade { grade: "60".to_string(), point: grade_to_point("60"), ..course_grade("B.01", "2022-2023", "1") },
+        ];
+        let gpa = grades.get_gpa_from_grades().unwrap();
+        assert_eq!((gpa.gpa, gpa.credits), (2.5, 4.0));
+    }
+
+    #[test]
+    fn test_required_average() {
+        let gpa = GPA { gpa: 3.5, credits: 60.0, ..Default::default() };
+        let required = gpa.required_average(3.7, 40.0).unwrap();
+        assert!((required - 4.0).abs() < 1e-9);
+        assert!((gpa.required_average(3.6, 60.0).unwrap() - 3.7).abs() < 1e-9);
+        // already there, anything works
+        assert_eq!(gpa.required_average(2.0, 20.0).unwrap(), 0.0);
+
+        gpa.required_average(3.8, 20.0).expect_err("expect unreachable");
+        gpa.required_average(3.7, 0.0).expect_err("expect error");
+        // no grade yet
+        assert!((GPA::default().required_average(3.7, 20.0).unwrap() - 3.7).abs() < 1e-9);
+    }
+
     #[test]
     fn test_get_grades() {
         dotenv::dotenv().ok();  // load env from .env file
@@ -0,0 +1,151 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use scraper::{ElementRef, Html, Selector};
+
+use crate::error::*;
+
+// The selectors used all over the crate, parsed once when first used instead of `Selector::parse(..).unwrap()` at every call.
+// They are all constant, so `test_static_selectors` makes sure none of them panics.
+pub(crate) static LINK_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("a").unwrap());
+pub(crate) static HIDDEN_INPUT_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"input[type="hidden"]"#).unwrap());
+// the rows in the body of a table, without the header row
+pub(crate) static BODY_ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("tbody tr").unwrap());
+// all rows of a table, when the header row is told apart by its `th` cells
+pub(crate) static ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table tr").unwrap());
+pub(crate) static HEADER_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table th").unwrap());
+pub(crate) static CELL_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("td").unwrap());
+pub(crate) static TITLE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("title").unwrap());
+pub(crate) static FORM_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("form").unwrap());
+// the election profile of a form on the xk default page
+pub(crate) static PROFILE_ID_INPUT_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"input[name="electionProfile.id"]"#).unwrap());
+// the options of the course category filter of xk
+pub(crate) static CATEGORY_OPTION_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"select[name="courseTypeId"] option"#).unwrap());
+// the tables of jwfw
+pub(crate) static NOTICE_HEADER_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table#notices th").unwrap());
+pub(crate) static NOTICE_ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table#notices tr").unwrap());
+pub(crate) static MATERIAL_HEADER_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table#materials th").unwrap());
+pub(crate) static MATERIAL_ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table#materials tr").unwrap());
+pub(crate) static ADVISOR_HEADER_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table#advisors th").unwrap());
+pub(crate) static ADVISOR_ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table#advisors tr").unwrap());
+// the PE summary and its fitness test (体质测试) table
+pub(crate) static PE_SUMMARY_ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table.summary tr").unwrap());
+pub(crate) static FITNESS_TEST_ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table#tzcs tr").unwrap());
+// the labelled fields of the student id card
+pub(crate) static CARD_ITEM_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(".card-item").unwrap());
+pub(crate) static CARD_LABEL_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(".label").unwrap());
+pub(crate) static CARD_VALUE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(".value").unwrap());
+
+static ENTITY_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"&(#[0-9]{1,7}|#[xX][0-9a-fA-F]{1,6}|[a-zA-Z]+);").unwrap());
+
+// Select the first element matching `selector` in `document`.
+//
+// Unlike `document.select(..).next().unwrap()`, a missing element (usually because the page layout changed
+// or we are not logged in) is reported as a `ParseError` naming the selector, instead of a panic.
+pub(crate) fn select_one<'a>(document: &'a Html, selector: &str) -> Result<ElementRef<'a>> {
+    let parsed = Selector::parse(selector)
+        .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("invalid selector {}", selector)))?;
+    document.select(&parsed).next()
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("no element matches selector {}", selector)))
+}
+
+// Get the attribute `attr` of the first element matching `selector`, or a `ParseError` if either is missing.
+pub(crate) fn select_attr(document: &Html, selector: &str, attr: &str) -> Result<String> {
+    select_one(document, selector)?.value().attr(attr)
+        .map(|value| value.to_string())
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("element {} has no attribute {}", selector, attr)))
+}
+
+// Decode the HTML entities in `text`, eg. 经济&amp;管理 to 经济&管理, &#x6570;&#23398; to 数学.
+//
+// The html parser decodes the entities of the markup, but the systems often escape names twice (so `.text()` still
+// gives &amp;), and the names in the js of xk are not decoded at all. Unknown entities are kept as they are.
+pub(crate) fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    ENTITY_REGEX.replace_all(text, |captures: &Captures| {
+        let entity = &captures[1];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()),
+            }.and_then(char::from_u32),
+        };
+        decoded.map_or_else(|| captures[0].to_string(), String::from)
+    }).to_string()
+}
+
+// The text of `element` with its entities decoded and the whitespace around trimmed, see `decode_entities`.
+pub(crate) fn element_text(element: ElementRef) -> String {
+    decode_entities(&element.text().collect::<String>()).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_one() {
+        let document = Html::parse_document(r#"<div id="a" class="b">text</div>"#);
+        assert_eq!(select_one(&document, "#a").unwrap().inner_html(), "text");
+        assert_eq!(select_attr(&document, "div", "class").unwrap(), "b");
+
+        let e = select_one(&document, "#missing").expect_err("expect error");
+        assert!(e.to_string().contains("#missing"));
+        select_attr(&document, "#a", "value").expect_err("expect error");
+        select_one(&document, "<>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_static_selectors() {
+        let document = Html::parse_document(r#"<title>t</title><form><input type="hidden" name="electionProfile.id" value="1"/></form><table>
+            <thead><tr><th>name</th></tr></thead>
+            <tbody><tr><td><a href="/">a</a><input type="hidden" name="lt" value="LT-1"/></td></tr></tbody>
+        </table>"#);
+        let count = |selector: &Selector| document.select(selector).count();
+        assert_eq!(count(&LINK_SELECTOR), 1);
+        assert_eq!(count(&HIDDEN_INPUT_SELECTOR), 2);
+        assert_eq!(count(&BODY_ROW_SELECTOR), 1);
+        assert_eq!(count(&ROW_SELECTOR), 2);
+        assert_eq!(count(&HEADER_SELECTOR), 1);
+        assert_eq!(count(&CELL_SELECTOR), 1);
+        assert_eq!(count(&TITLE_SELECTOR), 1);
+        assert_eq!(count(&FORM_SELECTOR), 1);
+        assert_eq!(count(&PROFILE_ID_INPUT_SELECTOR), 1);
+
+        let document = Html::parse_document(r#"<select name="courseTypeId"><option value="">全部</option><option value="7">专业必修课程</option></select>
+            <table id="notices"><tr><th>标题</th></tr><tr><td>n</td></tr></table>
+            <table id="materials"><tr><th>文件名</th></tr><tr><td>m</td></tr></table>
+            <table id="advisors"><tr><th>姓名</th></tr><tr><td>a</td></tr></table>
+            <table class="summary"><tr><td>体育课程</td><td>篮球</td></tr></table>
+            <table id="tzcs"><tr><th>项目</th></tr><tr><td>50米跑</td></tr></table>
+            <div class="card-item"><span class="label">姓名</span><span class="value">张三</span></div>"#);
+        let count = |selector: &Selector| document.select(selector).count();
+        assert_eq!(count(&CATEGORY_OPTION_SELECTOR), 2);
+        for selector in [&NOTICE_ROW_SELECTOR, &MATERIAL_ROW_SELECTOR, &ADVISOR_ROW_SELECTOR, &FITNESS_TEST_ROW_SELECTOR] {
+            assert_eq!(count(selector), 2);
+        }
+        for selector in [&NOTICE_HEADER_SELECTOR, &MATERIAL_HEADER_SELECTOR, &ADVISOR_HEADER_SELECTOR, &PE_SUMMARY_ROW_SELECTOR,
+            &CARD_ITEM_SELECTOR, &CARD_LABEL_SELECTOR, &CARD_VALUE_SELECTOR] {
+            assert_eq!(count(selector), 1);
+        }
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("经济&amp;管理"), "经济&管理");
+        assert_eq!(decode_entities("&#x6570;&#23398; &lt;A&gt; &quot;B&quot; &#39;C&apos;"), "数学 <A> \"B\" 'C'");
+        // kept as they are
+        assert_eq!(decode_entities("A & B &unknown; &#xFFFFFF; &amp"), "A & B &unknown; &#xFFFFFF; &amp");
+
+        let document = Html::parse_document(&crate::fdu::test_support::fixture("html_entities.html"));
+        let texts: Vec<String> = document.select(&CELL_SELECTOR).map(element_text).collect();
+        assert_eq!(texts, vec!["经济&管理", "数学分析(上)", "R&D <实验>", "C++ & Java"]);
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ErrorType, Result, SDKError};
+
+// A student number (学号) of Fudan, eg. 20300180001: 11 digits, the first two of which are the year of enrollment.
+// Staff use their work numbers (工号) to log in instead, see `Account::login`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StudentId(String);
+
+impl StudentId {
+    pub fn parse(id: &str) -> Result<Self> {
+        let id = id.trim();
+        if id.len() != 11 || !id.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(SDKError::with_type(ErrorType::ParseError, format!("{} is not a student id, which has 11 digits", id)));
+        }
+        Ok(StudentId(id.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    // eg. 2020 for 20300180001
+    pub fn enrollment_year(&self) -> i32 {
+        2000 + self.0[..2].parse::<i32>().unwrap()
+    }
+}
+
+impl FromStr for StudentId {
+    type Err = SDKError;
+
+    fn from_str(id: &str) -> Result<Self> {
+        StudentId::parse(id)
+    }
+}
+
+impl AsRef<str> for StudentId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for StudentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// The id xk gives a section (教学班) of a course, eg. 698266, which courses are selected and dropped by.
+// Not to be mixed up with the lesson no (课程序号, eg. ECON130213.01) shown to students.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CourseId(pub(crate) i32);
+
+impl CourseId {
+    pub fn value(&self) -> i32 {
+        self.0
+    }
+}
+
+impl fmt::Display for CourseId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_student_id() {
+        let id = StudentId::parse(" 20300180001 ").unwrap();
+        assert_eq!(id.as_str(), "20300180001");
+        assert_eq!(id.to_string(), "20300180001");
+        assert_eq!(id.enrollment_year(), 2020);
+        assert_eq!("19307130001".parse::<StudentId>().unwrap().enrollment_year(), 2019);
+    }
+
+    #[test]
+    fn test_invalid_student_id() {
+        for id in ["", "2030018000", "203001800011", "2030018000a", "20300180001.", "２0300180001", "698266"] {
+            StudentId::parse(id).expect_err(id);
+        }
+    }
+}
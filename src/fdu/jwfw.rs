@@ -1,27 +1,633 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use once_cell::sync::Lazy;
 use regex::Regex;
-use scraper::{Html, Selector};
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
 
+use crate::error::*;
 use crate::fdu::fdu::{Account, Fdu};
+use crate::fdu::html::{element_text, select_one, ADVISOR_HEADER_SELECTOR, ADVISOR_ROW_SELECTOR, BODY_ROW_SELECTOR, CELL_SELECTOR, HEADER_SELECTOR,
+    LINK_SELECTOR, MATERIAL_HEADER_SELECTOR, MATERIAL_ROW_SELECTOR, NOTICE_HEADER_SELECTOR, NOTICE_ROW_SELECTOR, ROW_SELECTOR};
+use crate::fdu::number::parse_number;
 
 const JWFW_URL: &str = "https://jwfw.fudan.edu.cn/eams/home.action";
 const JWFW_COURSE_TABLE_QUERY_URL: &str = "https://jwfw.fudan.edu.cn/eams/courseTableForStd!courseTable.action";
 const JWFW_COURSE_TABLE_MAIN_URL: &str = "https://jwfw.fudan.edu.cn/eams/courseTableForStd.action";
+const JWFW_CALENDAR_URL: &str = "https://jwfw.fudan.edu.cn/eams/schoolCalendar.action";
+const JWFW_PLAN_COMPLETION_URL: &str = "https://jwfw.fudan.edu.cn/eams/myPlanCompl.action";
+const JWFW_EVALUATION_URL: &str = "https://jwfw.fudan.edu.cn/eams/quality/stdEvaluate.action";
+const JWFW_EXAM_TABLE_URL: &str = "https://jwfw.fudan.edu.cn/eams/stdExamTable!examTable.action";
+const JWFW_SYLLABUS_URL: &str = "https://jwfw.fudan.edu.cn/eams/stdSyllabus!search.action";
+const JWFW_COURSE_INFO_URL: &str = "https://jwfw.fudan.edu.cn/eams/courseInfo!info.action";
+const JWFW_TRANSCRIPT_URL: &str = "https://jwfw.fudan.edu.cn/eams/stdTranscript!download.action";
+// the student info page (学籍信息), with the advisors of the student
+const JWFW_STD_DETAIL_URL: &str = "https://jwfw.fudan.edu.cn/eams/stdDetail.action";
+const JWFW_COURSE_MATERIAL_URL: &str = "https://jwfw.fudan.edu.cn/eams/lessonMaterial!search.action";
+const JWFW_NOTICE_LIST_URL: &str = "https://jwfw.fudan.edu.cn/eams/noticeView!search.action";
+
+// Shown on the plan completion page when there is no program to audit against, e.g. for exchange students.
+const NO_PLAN_MARKERS: [&str; 2] = ["没有找到培养方案", "暂无培养方案"];
+
+// Compiling a regex is expensive, so compile each of them only once, when it is first used.
+static IDS_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r##"bg.form.addInput\(form,"ids","(\d+)"\);"##).unwrap());
+static TERM_START_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"第\s*1\s*周[\s\S]*?(\d{4}-\d{1,2}-\d{1,2})").unwrap());
+static COURSE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r##"activity = new TaskActivity\("(\d+)","(\S+)","\d+\(\w+.\w+\)","(\S+\(\w+.\w+\))","\d+","(\S+)","([01]+)"\);((?:\s*index =\d+\*unitCount\+\d+;\s*table0.activities\[index]\[table0.activities\[index].length]=activity;)+)"##).unwrap());
+static LESSON_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r##"index =(\d+)\*unitCount\+(\d+);"##).unwrap());
+static TERM_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d{4}-\d{4}学年\s*\S+?学期").unwrap());
+static NOTICE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(".notice a, #notice a").unwrap());
+static CREDIT_SUMMARY_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#creditSummary tr").unwrap());
+static UNFINISHED_COURSE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#unfinishedCourses tr").unwrap());
+static COURSE_INFO_ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(".infoTable tr").unwrap());
+static ASSESSMENT_ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#assessment tr").unwrap());
+static TH_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("th").unwrap());
+
+// Shown instead of the requested page once a long-idle jwfw session has timed out.
+const SESSION_EXPIRED_MARKERS: [&str; 2] = ["业务流程已过期", "会话已过期"];
+// Shown instead of the requested page when `semester.id` is not one of the semesters jwfw allows.
+const SEMESTER_OUT_OF_RANGE_MARKERS: [&str; 2] = ["学期不在允许范围内", "学期不存在"];
+// Shown instead of the transcript PDF when it cannot be generated, e.g. while the grades of the term are being entered.
+const TRANSCRIPT_UNAVAILABLE_MARKERS: [&str; 2] = ["成绩单暂不可用", "暂不能打印成绩单"];
+// shown instead of the materials of a course the student is not enrolled in
+const NOT_ENROLLED_MARKERS: [&str; 2] = ["您没有权限查看该课程", "非本课程学生"];
+// jwfw's id of the semester the course table is fetched for.
+const COURSE_TABLE_SEMESTER_ID: i32 = 385;
+// When the units (节次) start, as (hour, minute), the first unit first. Each unit is 45 minutes.
+const UNIT_START_TIMES: [(u32, u32); 14] = [
+    (8, 0), (8, 55), (9, 55), (10, 50), (11, 45), (13, 30), (14, 25), (15, 25), (16, 20), (17, 15), (18, 30), (19, 25), (20, 20), (21, 15),
+];
+const UNIT_MINUTES: i64 = 45;
+// the course table container, and the script filling it in, of which at least one is on every course table page
+const COURSE_TABLE_MARKERS: [&str; 2] = [r#"id="manualArrangeCourseTable""#, "new CourseTable("];
 
 impl JwfwClient for Fdu {}
 
+// Turn the session-expired page into a `SessionExpired` error, so that callers do not get a confusing parse error
+// and know that logging in again is enough.
+fn check_session(html: String) -> Result<String> {
+    if SESSION_EXPIRED_MARKERS.iter().any(|marker| html.contains(marker)) {
+        return Err(SDKError::with_type(ErrorType::SessionExpired, "jwfw session expired".to_string()));
+    }
+    Ok(html)
+}
+
+// Turn the page shown for a semester out of range into an error naming `semester_id`, rather than letting it fail
+// to parse as an empty table.
+fn check_semester(html: String, semester_id: i32) -> Result<String> {
+    if SEMESTER_OUT_OF_RANGE_MARKERS.iter().any(|marker| html.contains(marker)) {
+        return Err(SDKError::with_type(ErrorType::OtherError, format!("semester {} is out of the range jwfw allows", semester_id)));
+    }
+    Ok(html)
+}
+
 // Parse the ids(a value related to student id) from courseTableForStd.action
-fn parse_ids(html: &String) -> String {
-    let regex = Regex::new(r##"bg.form.addInput\(form,"ids","(\d+)"\);"##).unwrap();
-    let cap = regex.captures_iter(html).next().unwrap();
-    cap[1].to_string()
+fn parse_ids(html: &String) -> Result<String> {
+    let cap = IDS_REGEX.captures(html)
+        .ok_or(SDKError::with_type(ErrorType::ParseError, "ids not found in the course table page".to_string()))?;
+    Ok(cap[1].to_string())
+}
+
+// One block of consecutive lessons of a course on a weekday, in the weeks listed.
+//
+// Both jwfw and xk describe schedules in this shape, so it is shared by them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleSlot {
+    name: String,
+    classroom: String,
+    // 1 = Monday, ..., 7 = Sunday
+    weekday: i32,
+    // 1-based, both inclusive
+    start_unit: i32,
+    end_unit: i32,
+    weeks: Vec<i32>,
+}
+
+impl ScheduleSlot {
+    pub(crate) fn new(name: String, classroom: String, weekday: i32, start_unit: i32, end_unit: i32, weeks: Vec<i32>) -> Self {
+        Self { name, classroom, weekday, start_unit, end_unit, weeks }
+    }
+
+    // When the slot starts, eg. 15:25 for unit 8. None for a unit past the last one, see `UNIT_START_TIMES`.
+    pub fn start_time(&self) -> Option<NaiveTime> {
+        unit_start(self.start_unit)
+    }
+
+    // When the slot ends, eg. 17:05 for a slot ending with unit 9.
+    pub fn end_time(&self) -> Option<NaiveTime> {
+        unit_start(self.end_unit).map(|start| start + Duration::minutes(UNIT_MINUTES))
+    }
+
+    // Whether the two slots take up the same unit of the same weekday in some week.
+    pub fn overlaps(&self, other: &ScheduleSlot) -> bool {
+        self.weekday == other.weekday
+            && self.start_unit <= other.end_unit
+            && other.start_unit <= self.end_unit
+            && self.weeks.iter().any(|week| other.weeks.contains(week))
+    }
+}
+
+fn unit_start(unit: i32) -> Option<NaiveTime> {
+    let (hour, minute) = *UNIT_START_TIMES.get(usize::try_from(unit).ok()?.checked_sub(1)?)?;
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+// Convert the week info to vector.
+// e.g. "01111111111011111000000000000000000000000000000000000" converts to vec![1,2,3,4,5,6,7,8,9,10,12,13,14,15,16]
+// The position with value 1 means there's a lesson in the week of its index.
+pub(crate) fn parse_week_bitmap(bitmap: &str) -> Vec<i32> {
+    bitmap.chars().enumerate()
+        .filter(|(_, c)| *c == '1')
+        .map(|(i, _)| i as i32)
+        .collect()
+}
+
+// Split a list of teachers, e.g. "陈钊,王永钦,张晏" converts to vec!["陈钊", "王永钦", "张晏"].
+// Both the ASCII and the full-width comma are seen as separators. An empty list gives an empty vector.
+pub(crate) fn split_teachers(teachers: &str) -> Vec<String> {
+    teachers.split([',', '，'])
+        .map(str::trim)
+        .filter(|teacher| !teacher.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Parse the first day of week 1 from the school calendar, whose rows are like
+// <tr><td>第1周</td><td>2022-09-05</td><td>2022-09-06</td>...</tr>
+// The date is moved back to Monday in case the row starts on some other day.
+fn parse_term_start_date(html: &str) -> Result<NaiveDate> {
+    let date = TERM_START_REGEX.captures(html)
+        .and_then(|cap| NaiveDate::parse_from_str(&cap[1], "%Y-%m-%d").ok())
+        .ok_or(SDKError::with_type(ErrorType::ParseError, "term start date not found in calendar".to_string()))?;
+    Ok(date - Duration::days(date.weekday().num_days_from_monday() as i64))
+}
+
+// The useful bits of the jwfw homepage.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JwfwHome {
+    // the current term, eg. 2022-2023学年1学期. None if the page does not show it.
+    term: Option<String>,
+    // titles of the notices on the homepage, eg. 关于2022-2023学年第二学期选课的通知
+    notices: Vec<String>,
+}
+
+// Parse the homepage. Both parts are optional on the page, so this never fails;
+// a missing part is just None or empty.
+fn parse_jwfw_home(html: &str) -> JwfwHome {
+    let document = Html::parse_document(html);
+    JwfwHome {
+        term: TERM_REGEX.find(html).map(|m| m.as_str().split_whitespace().collect()),
+        notices: document.select(&NOTICE_SELECTOR)
+            .map(element_text)
+            .filter(|title| !title.is_empty())
+            .collect(),
+    }
+}
+
+// An academic notice (教务通知) posted on jwfw, eg. of the exam arrangements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notice {
+    // eg. 关于2022-2023学年第二学期选课的通知
+    title: String,
+    date: NaiveDate,
+    // absolute, eg. https://jwfw.fudan.edu.cn/eams/noticeView.action?id=1
+    url: String,
+}
+
+// Parse the notice list, a table like
+// <table id="notices"><tr><th>标题</th><th>发布时间</th></tr>
+// <tr><td><a href="noticeView.action?id=1">关于选课的通知</a></td><td>2023-02-10</td></tr></table>
+// The notices are given the latest first, whatever order the page lists them in.
+fn parse_notices(html: &str) -> Result<Vec<Notice>> {
+    let document = Html::parse_document(html);
+    let base = Url::parse(JWFW_NOTICE_LIST_URL).unwrap();
+
+    let headers: Vec<String> = document.select(&NOTICE_HEADER_SELECTOR).map(element_text).collect();
+    let column = |name: &str| headers.iter().position(|header| header == name)
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("column {} not found in notice table", name)));
+    let (title, date) = (column("标题")?, column("发布时间")?);
+
+    let mut notices = Vec::new();
+    for tr in document.select(&NOTICE_ROW_SELECTOR) {
+        let cells: Vec<ElementRef> = tr.select(&CELL_SELECTOR).collect();
+        if cells.len() < headers.len() {
+            continue;
+        }
+        let href = cells[title].select(&LINK_SELECTOR).find_map(|a| a.value().attr("href"));
+        let (title, date) = (element_text(cells[title]), element_text(cells[date]));
+        let href = href.ok_or(SDKError::with_type(ErrorType::ParseError, format!("no link to notice {}", title)))?;
+        notices.push(Notice {
+            url: base.join(href)
+                .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("invalid notice link {}", href)))?
+                .to_string(),
+            // sometimes with the time, eg. 2023-02-10 09:30
+            date: NaiveDate::parse_from_str(date.split_whitespace().next().unwrap_or_default(), "%Y-%m-%d")
+                .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse date {} of notice {} error", date, title)))?,
+            title,
+        });
+    }
+    notices.sort_by_key(|notice| Reverse(notice.date));
+    Ok(notices)
+}
+
+// Credits of one category of the program, eg. 专业必修课程 40/32.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreditCategory {
+    name: String,
+    required: f32,
+    completed: f32,
+}
+
+impl CreditCategory {
+    pub fn is_completed(&self) -> bool {
+        self.completed >= self.required
+    }
+}
+
+// A required course that is not passed yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutstandingCourse {
+    // eg. COMP130004
+    code: String,
+    name: String,
+    credits: f32,
+}
+
+// The progress towards the graduation requirements of the program (培养方案完成情况).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegreeAudit {
+    categories: Vec<CreditCategory>,
+    outstanding: Vec<OutstandingCourse>,
+}
+
+fn cell_texts(row: ElementRef) -> Vec<String> {
+    row.select(&CELL_SELECTOR).map(element_text).collect()
+}
+
+fn parse_credits(text: &str) -> Result<f32> {
+    parse_number(text).ok_or_else(|| SDKError::with_type(ErrorType::ParseError, format!("parse credits {} error", text)))
+}
+
+// Parse the plan completion page, which has a table of credits per category and a table of courses not passed yet:
+// <table id="creditSummary"><tr><th>课程类别</th><th>要求学分</th><th>已修学分</th></tr><tr><td>专业必修课程</td><td>40</td><td>32</td></tr></table>
+// <table id="unfinishedCourses"><tr><th>课程代码</th><th>课程名称</th><th>学分</th></tr><tr><td>COMP130004</td><td>数据结构</td><td>3</td></tr></table>
+// None if the student has no program to audit against.
+fn parse_degree_audit(html: &str) -> Result<Option<DegreeAudit>> {
+    if NO_PLAN_MARKERS.iter().any(|marker| html.contains(marker)) {
+        return Ok(None);
+    }
+
+    let document = Html::parse_document(html);
+
+    let mut categories = Vec::new();
+    for tr in document.select(&CREDIT_SUMMARY_SELECTOR) {
+        let cells = cell_texts(tr);
+        // the header row
+        if cells.len() < 3 {
+            continue;
+        }
+        categories.push(CreditCategory { name: cells[0].clone(), required: parse_credits(&cells[1])?, completed: parse_credits(&cells[2])? });
+    }
+    if categories.is_empty() {
+        return Err(SDKError::with_type(ErrorType::ParseError, "no credit category found in plan completion".to_string()));
+    }
+
+    let mut outstanding = Vec::new();
+    for tr in document.select(&UNFINISHED_COURSE_SELECTOR) {
+        let cells = cell_texts(tr);
+        if cells.len() < 3 {
+            continue;
+        }
+        outstanding.push(OutstandingCourse { code: cells[0].clone(), name: cells[1].clone(), credits: parse_credits(&cells[2])? });
+    }
+    Ok(Some(DegreeAudit { categories, outstanding }))
+}
+
+// The teaching evaluation (教学评价) of a course this term.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    // eg. COMP130004.03
+    code: String,
+    name: String,
+    teachers: Vec<String>,
+    evaluated: bool,
+}
+
+// Parse the evaluation list, one row for each course:
+// <tr><td>COMP130004.03</td><td>数据结构</td><td>陈彤兵</td><td><a href="...">进行评教</a></td></tr>
+// The last cell says 已评教 once the course is evaluated. The list has no row when there is nothing to evaluate.
+fn parse_evaluations(html: &str) -> Result<Vec<Evaluation>> {
+    let document = Html::parse_document(html);
+
+    let table = select_one(&document, "table.gridtable")?;
+    let mut evaluations = Vec::new();
+    for tr in table.select(&BODY_ROW_SELECTOR) {
+        let cells = cell_texts(tr);
+        // the placeholder row when there is no course
+        if cells.len() < 4 {
+            continue;
+        }
+        evaluations.push(Evaluation {
+            code: cells[0].clone(),
+            name: cells[1].clone(),
+            teachers: split_teachers(&cells[2]),
+            evaluated: cells[3].contains("已评"),
+        });
+    }
+    Ok(evaluations)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exam {
+    // eg. COMP130004.03
+    code: String,
+    name: String,
+    date: NaiveDate,
+    // eg. 08:30-10:30
+    time: String,
+    // `time` on `date`, None if the time is not like 08:30-10:30, e.g. not decided yet
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+    // eg. H3109
+    room: String,
+    // the seat number (座位号), None until seats are assigned
+    seat: Option<String>,
+}
+
+// Parse an exam time like 08:30-10:30 on `date` into its start and end.
+fn parse_exam_time(date: NaiveDate, time: &str) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let (start, end) = time.split_once('-')?;
+    let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok().map(|time| date.and_time(time));
+    Some((parse(start)?, parse(end)?))
+}
+
+// Check that the response to the transcript download is a PDF. Anything else is a page telling why there is none.
+fn check_transcript(content_type: &str, bytes: Vec<u8>) -> Result<Vec<u8>> {
+    if content_type.starts_with("application/pdf") && bytes.starts_with(b"%PDF-") {
+        return Ok(bytes);
+    }
+    let html = check_session(String::from_utf8_lossy(&bytes).into_owned())?;
+    if TRANSCRIPT_UNAVAILABLE_MARKERS.iter().any(|marker| html.contains(marker)) {
+        return Err(SDKError::with_type(ErrorType::OtherError, "transcript is not available now".to_string()));
+    }
+    Err(SDKError::with_type(ErrorType::ParseError, format!("expect a PDF transcript, got {} of {} bytes", content_type, bytes.len())))
+}
+
+// Find the exams overlapping in time, as pairs of their indices (i, j) with i < j. One exam ending as another starts
+// does not overlap it, and the exams without a known time are left out, as they cannot be told to overlap or not.
+pub fn find_exam_conflicts(exams: &[Exam]) -> Vec<(usize, usize)> {
+    let mut conflicts = Vec::new();
+    for (i, a) in exams.iter().enumerate() {
+        for (j, b) in exams.iter().enumerate().skip(i + 1) {
+            if let (Some(a_start), Some(a_end), Some(b_start), Some(b_end)) = (a.start, a.end, b.start, b.end) {
+                if a_start < b_end && b_start < a_end {
+                    conflicts.push((i, j));
+                }
+            }
+        }
+    }
+    conflicts
+}
+
+// Parse the exam table. The columns are found by their headers (课程序号, 课程名称, 考试日期, 考试时间, 考试地点, 座位号),
+// and the seat column is missing altogether before any seat is assigned. A seat not assigned yet is shown as empty or 未安排.
+fn parse_exams(html: &str) -> Result<Vec<Exam>> {
+    let document = Html::parse_document(html);
+
+    let headers: Vec<String> = document.select(&HEADER_SELECTOR).map(|th| th.text().collect::<String>().trim().to_string()).collect();
+    let position = |name: &str| headers.iter().position(|header| header == name);
+    let column = |name: &str| position(name)
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("column {} not found in exam table", name)));
+    let (code, name, date, time, room) = (column("课程序号")?, column("课程名称")?, column("考试日期")?, column("考试时间")?, column("考试地点")?);
+    let seat = position("座位号");
+
+    let mut exams = Vec::new();
+    for tr in document.select(&ROW_SELECTOR) {
+        let cells = cell_texts(tr);
+        if cells.len() < headers.len() {
+            continue;
+        }
+        let date = NaiveDate::parse_from_str(&cells[date], "%Y-%m-%d")
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse exam date {} error", cells[date])))?;
+        let (start, end) = parse_exam_time(date, &cells[time]).unzip();
+        exams.push(Exam {
+            code: cells[code].clone(),
+            name: cells[name].clone(),
+            date,
+            time: cells[time].clone(),
+            start,
+            end,
+            room: cells[room].clone(),
+            seat: seat.map(|seat| cells[seat].clone()).filter(|seat| !seat.is_empty() && seat != "未安排"),
+        });
+    }
+    Ok(exams)
+}
+
+// An advisor of the student, as listed on the student info page. Only the role and name are always there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advisor {
+    // eg. 导师 (the academic advisor), 辅导员 (the counselor), 班主任
+    role: String,
+    // eg. 张三
+    name: String,
+    // eg. 副教授
+    title: Option<String>,
+    email: Option<String>,
+    phone: Option<String>,
+}
+
+// A file the teacher uploaded for a course (课件), eg. the slides of a lecture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    // eg. 第一章 绪论.pdf
+    name: String,
+    // absolute, eg. https://jwfw.fudan.edu.cn/eams/lessonMaterial!download.action?material.id=1001
+    url: String,
+    // in bytes, None if the page does not say
+    size: Option<u64>,
+}
+
+// The details of a course, as shown on its course info page (课程信息). Courses often leave some of them out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CourseDetail {
+    // eg. COMP130004
+    code: String,
+    name: String,
+    credits: Option<f32>,
+    description: Option<String>,
+    // eg. 程序设计, empty if there is none
+    prerequisites: Vec<String>,
+    // the parts of the final grade and their weights in percent, eg. (期末考试, 50.0)
+    assessment: Vec<(String, f32)>,
+}
+
+// Parse the course info page. It has a table of label/value rows and a table of assessment parts:
+// <table class="infoTable"><tr><th>课程代码</th><td>COMP130004</td></tr><tr><th>先修课程</th><td>程序设计，离散数学</td></tr></table>
+// <table id="assessment"><tr><th>考核方式</th><th>比例</th></tr><tr><td>期末考试</td><td>50%</td></tr></table>
+// Only the code and name are required. A missing or empty row, or one saying 无, is left as None or empty.
+fn parse_course_detail(html: &str) -> Result<CourseDetail> {
+    let document = Html::parse_document(html);
+
+    let mut fields = HashMap::new();
+    for tr in document.select(&COURSE_INFO_ROW_SELECTOR) {
+        let label = tr.select(&TH_SELECTOR).next().map(|th| th.text().collect::<String>().trim().to_string());
+        if let (Some(label), Some(value)) = (label, cell_texts(tr).into_iter().next()) {
+            if !value.is_empty() && value != "无" {
+                fields.insert(label, value);
+            }
+        }
+    }
+    let required = |label: &str| fields.get(label).cloned()
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("{} not found in course info", label)));
+
+    let mut assessment = Vec::new();
+    for tr in document.select(&ASSESSMENT_ROW_SELECTOR) {
+        let cells = cell_texts(tr);
+        // the header row
+        if cells.len() < 2 {
+            continue;
+        }
+        let weight = cells[1].trim_end_matches('%').trim();
+        let weight = weight.parse()
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse weight {} of {} error", cells[1], cells[0])))?;
+        assessment.push((cells[0].clone(), weight));
+    }
+
+    Ok(CourseDetail {
+        code: required("课程代码")?,
+        name: required("课程名称")?,
+        credits: fields.get("学分").map(|credits| parse_credits(credits)).transpose()?,
+        description: fields.get("课程简介").cloned(),
+        // separated like teachers, or by 、
+        prerequisites: fields.get("先修课程")
+            .map(|prerequisites| prerequisites.split('、').flat_map(split_teachers).collect())
+            .unwrap_or_default(),
+        assessment,
+    })
+}
+
+// Parse the syllabus list of the enrolled courses into course code (课程序号) -> absolute syllabus url.
+// The columns are found by their headers (课程序号, 教学大纲), and the syllabus cell holds a link like
+// <a href="syllabus!info.action?lesson.id=155165">查看</a>, or just 暂无 when the teacher has not uploaded one,
+// in which case the course is left out.
+fn parse_syllabus_links(html: &str) -> Result<HashMap<String, String>> {
+    let document = Html::parse_document(html);
+    let base = Url::parse(JWFW_SYLLABUS_URL).unwrap();
+
+    let headers: Vec<String> = document.select(&HEADER_SELECTOR).map(|th| th.text().collect::<String>().trim().to_string()).collect();
+    let column = |name: &str| headers.iter().position(|header| header == name)
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("column {} not found in syllabus table", name)));
+    let (code, syllabus) = (column("课程序号")?, column("教学大纲")?);
+
+    let mut links = HashMap::new();
+    for tr in document.select(&ROW_SELECTOR) {
+        let cells: Vec<ElementRef> = tr.select(&CELL_SELECTOR).collect();
+        if cells.len() < headers.len() {
+            continue;
+        }
+        let href = match cells[syllabus].select(&LINK_SELECTOR).find_map(|a| a.value().attr("href")) {
+            Some(href) => href,
+            None => continue,
+        };
+        let code = element_text(cells[code]);
+        let link = base.join(href)
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("invalid syllabus link of {}", code)))?;
+        links.insert(code, link.to_string());
+    }
+    Ok(links)
+}
+
+// Parse the attachments from the materials page of a course, a table like
+// <table id="materials"><tr><th>文件名</th><th>大小</th><th>上传时间</th></tr>
+// <tr><td><a href="lessonMaterial!download.action?material.id=1001">第一章 绪论.pdf</a></td><td>1.5MB</td><td>2023-02-20</td></tr></table>
+// A course without materials has no row.
+fn parse_attachments(html: &str) -> Result<Vec<Attachment>> {
+    if NOT_ENROLLED_MARKERS.iter().any(|marker| html.contains(marker)) {
+        return Err(SDKError::with_type(ErrorType::OtherError, "not enrolled in the course, whose materials are denied".to_string()));
+    }
+    let document = Html::parse_document(html);
+    let base = Url::parse(JWFW_COURSE_MATERIAL_URL).unwrap();
+
+    let headers: Vec<String> = document.select(&MATERIAL_HEADER_SELECTOR).map(element_text).collect();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let name = column("文件名")
+        .ok_or(SDKError::with_type(ErrorType::ParseError, "column 文件名 not found in material table".to_string()))?;
+    let size = column("大小");
+
+    let mut attachments = Vec::new();
+    for tr in document.select(&MATERIAL_ROW_SELECTOR) {
+        let cells: Vec<ElementRef> = tr.select(&CELL_SELECTOR).collect();
+        if cells.len() < headers.len() {
+            continue;
+        }
+        let href = cells[name].select(&LINK_SELECTOR).find_map(|a| a.value().attr("href"))
+            .ok_or(SDKError::with_type(ErrorType::ParseError, format!("no link to {}", element_text(cells[name]))))?;
+        let url = base.join(href)
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("invalid attachment link {}", href)))?;
+        attachments.push(Attachment {
+            name: element_text(cells[name]),
+            url: url.to_string(),
+            size: size.and_then(|size| parse_size(&element_text(cells[size]))),
+        });
+    }
+    Ok(attachments)
+}
+
+// A file size as shown, eg. 1.5MB, 512 KB or 300B, in bytes.
+fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim().to_ascii_uppercase();
+    let number_end = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let number: f64 = text[..number_end].parse().ok()?;
+    let unit: u64 = match text[number_end..].trim() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => return None,
+    };
+    Some((number * unit as f64).round() as u64)
+}
+
+// Parse the advisors from the student info page, a table like
+// <table id="advisors"><tr><th>类别</th><th>姓名</th><th>职称</th><th>电子邮箱</th><th>联系电话</th></tr>
+// <tr><td>导师</td><td>张三</td><td>教授</td><td>zhangsan@fudan.edu.cn</td><td>021-65640000</td></tr></table>
+// The role and name columns are required. Contact columns may be missing, and cells left empty or saying 无 are None.
+// An advisor not assigned yet has no row, so the list may be empty.
+fn parse_advisors(html: &str) -> Result<Vec<Advisor>> {
+    let document = Html::parse_document(html);
+
+    let headers: Vec<String> = document.select(&ADVISOR_HEADER_SELECTOR).map(element_text).collect();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let required = |name: &str| column(name)
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("column {} not found in advisor table", name)));
+    let (role, name) = (required("类别")?, required("姓名")?);
+    let (title, email, phone) = (column("职称"), column("电子邮箱"), column("联系电话"));
+
+    Ok(document.select(&ADVISOR_ROW_SELECTOR).filter_map(|tr| {
+        let cells = cell_texts(tr);
+        // the header row
+        if cells.len() < headers.len() {
+            return None;
+        }
+        let optional = |column: Option<usize>| column
+            .map(|column| cells[column].clone())
+            .filter(|value| !value.is_empty() && value != "无");
+        Some(Advisor {
+            role: cells[role].clone(),
+            name: cells[name].clone(),
+            title: optional(title),
+            email: optional(email),
+            phone: optional(phone),
+        })
+    }).collect())
 }
 
 #[derive(Debug)]
 pub struct CourseData {
     id: String,
-    teacher: String,
+    teachers: Vec<String>,
     name_with_course_id: String,
     classroom: String,
     weeks: Vec<i32>,
@@ -48,23 +654,13 @@ table0.activities[index][table0.activities[index].length]=activity;
  */
 // the number in "index =2*unitCount+0;", "index =1*unitCount+8;", etc. implies the day and time for the course in the current week.
 fn parse_course_data(html: &String) -> Vec<CourseData> {
-    let regex_course = Regex::new(r##"activity = new TaskActivity\("(\d+)","(\S+)","\d+\(\w+.\w+\)","(\S+\(\w+.\w+\))","\d+","(\S+)","([01]+)"\);((?:\s*index =\d+\*unitCount\+\d+;\s*table0.activities\[index]\[table0.activities\[index].length]=activity;)+)"##).unwrap();
     let mut ret = Vec::new();
-    for cap_course in regex_course.captures_iter(html.as_str()) {
+    for cap_course in COURSE_REGEX.captures_iter(html.as_str()) {
 
         // Get the week info for the course
         // e.g. "01111111111011111000000000000000000000000000000000000"
         // The position with value 1 means there's a lesson in the week of its index.
-        let course_week_info = cap_course[5].to_string();
-
-        // Convert the week info to vector.
-        // e.g. "01111111111011111000000000000000000000000000000000000" converts to vec![1,2,3,4,5,6,7,8,9,10,12,13,14,15,16]
-        let mut weeks: Vec<i32> = Vec::new();
-        for (i, c) in course_week_info.chars().enumerate() {
-            if c == '1' {
-                weeks.push(i as i32);
-            }
-        }
+        let weeks = parse_week_bitmap(&cap_course[5]);
 
 
         // Get the data for each group, which is like
@@ -80,15 +676,14 @@ fn parse_course_data(html: &String) -> Vec<CourseData> {
 
         let mut time: Vec<(i32, i32)> = Vec::new();
         let course_data = &cap_course[6];
-        let regex_lesson = Regex::new(r##"index =(\d+)\*unitCount\+(\d+);"##).unwrap();
-        for cap_lesson in regex_lesson.captures_iter(course_data) {
+        for cap_lesson in LESSON_REGEX.captures_iter(course_data) {
             let day_number: &i32 = &cap_lesson[1].parse().unwrap();
             let time_number: &i32 = &cap_lesson[2].parse().unwrap();
             time.push((*day_number, *time_number));
         }
         ret.push(CourseData {
             id: cap_course[1].to_string(),
-            teacher: cap_course[2].to_string(),
+            teachers: split_teachers(&cap_course[2]),
             name_with_course_id: cap_course[3].to_string(),
             classroom: cap_course[4].to_string(),
             weeks,
@@ -98,58 +693,907 @@ fn parse_course_data(html: &String) -> Vec<CourseData> {
     ret
 }
 
-pub trait JwfwClient: Account {
-    fn get_jwfw_homepage(&self) -> reqwest::Result<String> {
-        let client = self.get_client();
-        let mut html = client.get(JWFW_URL).send()?.text()?;
-        let document = Html::parse_document(html.as_str());
-        let selector = Selector::parse(r#"html > body > a"#).unwrap();
-        for element in document.select(&selector) {
-            if element.inner_html().as_str() == "点击此处" {
-                let href = element.value().attr("href");
-                if let Some(key) = href {
-                    html = client.get(key.to_string()).send()?.text()?
+// Tell the courses parsed from `html` by `parse_course_data` apart from a page they cannot be parsed from, as both give
+// no course: a page without the course table (eg. the login page) fails, and so does one whose activities are there
+// but did not parse, which means jwfw changed their format. A table without any activity is a term without courses.
+fn check_course_table(html: &str, courses: Vec<CourseData>) -> Result<Vec<CourseData>> {
+    if !courses.is_empty() {
+        return Ok(courses);
+    }
+    if !COURSE_TABLE_MARKERS.iter().any(|marker| html.contains(marker)) {
+        return Err(SDKError::with_type(ErrorType::ParseError, "course table not found".to_string()));
+    }
+    if html.contains("TaskActivity") {
+        return Err(SDKError::with_type(ErrorType::ParseError, "course table activities in an unknown format".to_string()));
+    }
+    Ok(courses)
+}
+
+// The slots of `courses` in `week`, in the order of weekdays and units.
+fn slots_in_week(courses: &[CourseData], week: i32) -> Vec<ScheduleSlot> {
+    let mut slots: Vec<ScheduleSlot> = courses.iter()
+        .flat_map(|course| course.schedule_slots())
+        .filter(|slot| slot.weeks.contains(&week))
+        .collect();
+    slots.sort_by_key(|slot| (slot.weekday, slot.start_unit));
+    slots
+}
+
+// The slots of `courses` on `date`, in the order of units. The week of `date` is counted from `term_start`, the Monday of week 1.
+fn slots_on(courses: &[CourseData], term_start: NaiveDate, date: NaiveDate) -> Vec<ScheduleSlot> {
+    let days = (date - term_start).num_days();
+    if days < 0 {
+        return Vec::new();
+    }
+    let weekday = date.weekday().number_from_monday() as i32;
+    slots_in_week(courses, (days / 7 + 1) as i32).into_iter()
+        .filter(|slot| slot.weekday == weekday)
+        .collect()
+}
+
+// The course table of the term, for views of a week rather than of the whole term.
+#[derive(Debug)]
+pub struct Timetable {
+    courses: Vec<CourseData>,
+}
+
+impl Timetable {
+    pub fn new(courses: Vec<CourseData>) -> Self {
+        Self { courses }
+    }
+
+    pub fn courses(&self) -> &[CourseData] {
+        &self.courses
+    }
+
+    // The classes in week `week` (1-based), in the order of weekdays and units, e.g. for a weekly widget.
+    // Empty if the term has no such week.
+    pub fn week(&self, week: u32) -> Vec<ScheduleSlot> {
+        match i32::try_from(week) {
+            Ok(week) => slots_in_week(&self.courses, week),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // The class on at `now`, and the next one to start after `now`, eg. for "your next class is 数据结构 at 15:25 in H3409".
+    // `term_start` is the Monday of week 1, see `JwfwClient::get_term_start_date`, from which the week of `now` is
+    // counted, and the classes are those of that week (see `week`) at the times of `UNIT_START_TIMES`. `now` is taken
+    // in Shanghai time, whatever its time zone. The next class may be on a later day, up to the end of term, and is None
+    // after the last one.
+    pub fn current_and_next<Tz: TimeZone>(&self, term_start: NaiveDate, now: DateTime<Tz>) -> (Option<ScheduleSlot>, Option<ScheduleSlot>) {
+        let now = now.with_timezone(&FixedOffset::east_opt(8 * 3600).unwrap()).naive_local();
+        let last_week = self.courses.iter().flat_map(|course| course.weeks.iter().copied()).max().unwrap_or(0);
+        let last_day = term_start + Duration::days(i64::from(last_week) * 7);
+
+        let mut current = None;
+        let mut date = now.date();
+        while date < last_day {
+            for slot in slots_on(&self.courses, term_start, date) {
+                let (start, end) = match (slot.start_time(), slot.end_time()) {
+                    (Some(start), Some(end)) => (date.and_time(start), date.and_time(end)),
+                    _ => continue,
+                };
+                if start > now {
+                    return (current, Some(slot));
+                }
+                if current.is_none() && now < end {
+                    current = Some(slot);
                 }
             }
+            date = date.succ_opt().unwrap();
+        }
+        (current, None)
+    }
+
+    // Draw week `week` as an SVG image, eg. to share it, which the frontends rasterize to PNG if they need to.
+    // The grid has the weekdays as columns and the units as rows, 14 of them unless a class goes later, and each
+    // class is a rect (`class="course"`) over its units with its name and classroom. Classes overlapping on a day
+    // are stacked side by side in the column, each in its own lane.
+    pub fn render_svg(&self, week: u32) -> String {
+        const UNITS: i32 = 14;
+        const WEEKDAYS: [&str; 7] = ["周一", "周二", "周三", "周四", "周五", "周六", "周日"];
+        const COLORS: [&str; 6] = ["#8ecae6", "#ffb703", "#90be6d", "#f4a261", "#cdb4db", "#e5989b"];
+        let (label_width, header_height, column_width, row_height) = (40.0, 30.0, 120.0, 40.0);
+
+        let slots = self.week(week);
+        let units = slots.iter().map(|slot| slot.end_unit).max().unwrap_or(0).max(UNITS);
+        let (width, height) = (label_width + column_width * 7.0, header_height + row_height * units as f64);
+        let mut svg = format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="12">"#,
+            width, height, width, height);
+        svg.push_str(&format!(r##"<rect width="{}" height="{}" fill="#ffffff"/>"##, width, height));
+
+        // the grid
+        for (i, weekday) in WEEKDAYS.iter().enumerate() {
+            let x = label_width + column_width * i as f64;
+            svg.push_str(&format!(r##"<text x="{}" y="{}" text-anchor="middle">{}</text>"##, x + column_width / 2.0, header_height / 2.0 + 4.0, weekday));
+            svg.push_str(&format!(r##"<line x1="{}" y1="0" x2="{}" y2="{}" stroke="#dddddd"/>"##, x, x, height));
+        }
+        for unit in 1..=units {
+            let y = header_height + row_height * (unit - 1) as f64;
+            svg.push_str(&format!(r##"<text x="{}" y="{}" text-anchor="middle">{}</text>"##, label_width / 2.0, y + row_height / 2.0 + 4.0, unit));
+            svg.push_str(&format!(r##"<line x1="0" y1="{}" x2="{}" y2="{}" stroke="#dddddd"/>"##, y, width, y));
+        }
+
+        // the classes, in lanes where they overlap
+        let lanes = assign_lanes(&slots);
+        for (slot, (lane, lane_count)) in slots.iter().zip(lanes) {
+            let lane_width = column_width / lane_count as f64;
+            let x = label_width + column_width * (slot.weekday - 1) as f64 + lane_width * lane as f64;
+            let y = header_height + row_height * (slot.start_unit - 1) as f64;
+            let block_height = row_height * (slot.end_unit - slot.start_unit + 1) as f64;
+            let color = COLORS[slot.name.chars().map(|c| c as usize).sum::<usize>() % COLORS.len()];
+            svg.push_str(&format!(r##"<rect class="course" x="{}" y="{}" width="{}" height="{}" rx="4" fill="{}" stroke="#ffffff"/>"##,
+                x, y, lane_width, block_height, color));
+            svg.push_str(&format!(r##"<text x="{}" y="{}" font-size="11"><tspan x="{}" dy="0">{}</tspan><tspan x="{}" dy="14">{}</tspan></text>"##,
+                x + 4.0, y + 16.0, x + 4.0, escape_xml(&slot.name), x + 4.0, escape_xml(&slot.classroom)));
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+// The lane of each slot in its weekday column, and the number of lanes it shares the column with, so that slots which
+// overlap are drawn side by side. A slot takes the first lane free over all its units. The slots are of one week.
+fn assign_lanes(slots: &[ScheduleSlot]) -> Vec<(usize, usize)> {
+    let overlap = |a: &ScheduleSlot, b: &ScheduleSlot| a.overlaps(b);
+    let mut lanes: Vec<usize> = Vec::new();
+    for (i, slot) in slots.iter().enumerate() {
+        let taken: Vec<usize> = (0..i).filter(|j| overlap(&slots[*j], slot)).map(|j| lanes[j]).collect();
+        lanes.push((0..).find(|lane| !taken.contains(lane)).unwrap());
+    }
+    slots.iter().enumerate().map(|(i, slot)| {
+        let count = slots.iter().enumerate()
+            .filter(|(_, other)| overlap(other, slot))
+            .map(|(j, _)| lanes[j] + 1)
+            .max()
+            .unwrap_or(1);
+        (lanes[i], count)
+    }).collect()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+impl CourseData {
+    // Group the lessons into blocks of consecutive units on the same day.
+    //
+    // jwfw counts days and units from 0, so they are shifted by one to match `ScheduleSlot`.
+    pub fn schedule_slots(&self) -> Vec<ScheduleSlot> {
+        let mut time = self.time.clone();
+        time.sort();
+        let mut slots: Vec<ScheduleSlot> = Vec::new();
+        for (day, unit) in time {
+            let (weekday, unit) = (day + 1, unit + 1);
+            match slots.last_mut() {
+                Some(slot) if slot.weekday == weekday && slot.end_unit + 1 == unit => slot.end_unit = unit,
+                _ => slots.push(ScheduleSlot::new(
+                    self.name_with_course_id.clone(), self.classroom.clone(), weekday, unit, unit, self.weeks.clone(),
+                )),
+            }
         }
-        Ok(html)
+        slots
+    }
+}
+
+pub trait JwfwClient: Account {
+    // Log in to jwfw, which the other jwfw methods need. The session is kept in the cookie store.
+    // Logging in lands on the homepage, so this is `get_jwfw_home` with the homepage left out.
+    fn login_jwfw(&self) -> Result<()> {
+        self.get_jwfw_home()?;
+        Ok(())
+    }
+
+    // Log in to jwfw and get the term and notices shown on its homepage.
+    fn get_jwfw_home(&self) -> Result<JwfwHome> {
+        // log in through CAS, then get the homepage like the other jwfw pages
+        self.follow_cas(JWFW_URL)?;
+        let html = check_session(self.send_and_get_html(self.get_client().get(self.resolve_url(JWFW_URL)))?)?;
+        Ok(parse_jwfw_home(&html))
     }
 
-    fn get_course_table(&self) -> reqwest::Result<Vec<CourseData>> {
+    // Get the latest `count` academic notices (教务通知), the latest first, eg. for a notice feed. The homepage shows
+    // only a few titles, see `get_jwfw_home`. Fewer are given if jwfw has fewer.
+    fn get_notices(&self, count: usize) -> Result<Vec<Notice>> {
+        let request = self.get_client().get(self.resolve_url(JWFW_NOTICE_LIST_URL)).query(&[("pageNo", 1), ("pageSize", count)]);
+        let mut notices = parse_notices(&check_session(self.send_and_get_html(request)?)?)?;
+        notices.truncate(count);
+        Ok(notices)
+    }
+
+    #[deprecated(note = "use `login_jwfw` to log in, or `get_jwfw_home` for the parsed homepage")]
+    fn get_jwfw_homepage(&self) -> Result<String> {
+        self.follow_cas(JWFW_URL)?;
+        check_session(self.send_and_get_html(self.get_client().get(self.resolve_url(JWFW_URL)))?)
+    }
+
+    fn get_course_table(&self) -> Result<Vec<CourseData>> {
+        Ok(self.get_course_table_with_raw()?.0)
+    }
+
+    // Same as `get_course_table`, but wrapped in a `Timetable` to look at one week at a time.
+    fn get_timetable(&self) -> Result<Timetable> {
+        Ok(Timetable::new(self.get_course_table()?))
+    }
+
+    // Same as `get_course_table`, but also return the raw html the course data is parsed from, to debug the parser with.
+    // The html is moved out rather than copied, but it is kept alive as long as you hold it, so only use this when needed.
+    fn get_course_table_with_raw(&self) -> Result<(Vec<CourseData>, String)> {
         let client = self.get_client();
 
         // First visit the courseTableForStd.action to get ids(a value related to student id)
-        let main_html = client.get(JWFW_COURSE_TABLE_MAIN_URL).send()?.text()?;
-        let ids = parse_ids(&main_html);
+        let main_html = check_session(self.send_and_get_text(client.get(self.resolve_url(JWFW_COURSE_TABLE_MAIN_URL)))?)?;
+        let ids = parse_ids(&main_html)?;
 
         let mut payload = HashMap::new();
         payload.insert("ignoreHead", "1");
         payload.insert("setting.kind", "std");
         payload.insert("startWeek", "1");
         payload.insert("project.id", "1");
-        payload.insert("semester.id", "385");
+        let semester_id = COURSE_TABLE_SEMESTER_ID.to_string();
+        payload.insert("semester.id", semester_id.as_str());
         payload.insert("ids", ids.as_str());
-        let query_html = client.post(JWFW_COURSE_TABLE_QUERY_URL).form(&payload).send()?.text()?;
-        let course_data = parse_course_data(&query_html);
+        let query_html = check_session(self.send_and_get_text(client.post(self.resolve_url(JWFW_COURSE_TABLE_QUERY_URL)).form(&payload))?)?;
+        let query_html = check_semester(query_html, COURSE_TABLE_SEMESTER_ID)?;
+        let course_data = check_course_table(&query_html, parse_course_data(&query_html))?;
         println!("{:#?}", course_data);
-        Ok(course_data)
+        Ok((course_data, query_html))
+    }
+
+    // Get the Monday of week 1 of the current term, e.g. for exporting the course table to ICS.
+    // The school calendar is public, so this works without `login`.
+    fn get_term_start_date(&self) -> Result<NaiveDate> {
+        let html = check_session(self.send_and_get_html(self.get_client().get(self.resolve_url(JWFW_CALENDAR_URL)))?)?;
+        parse_term_start_date(&html)
+    }
+
+    // Get the classes on `date`, e.g. today's for a home screen. Empty on a day off, or out of the term.
+    fn get_classes_on(&self, date: NaiveDate) -> Result<Vec<ScheduleSlot>> {
+        let term_start = self.get_term_start_date()?;
+        Ok(slots_on(&self.get_course_table()?, term_start, date))
+    }
+
+    // Get the progress towards the graduation requirements, None if the program has no audit data.
+    fn get_degree_audit(&self) -> Result<Option<DegreeAudit>> {
+        let html = check_session(self.send_and_get_html(self.get_client().get(self.resolve_url(JWFW_PLAN_COMPLETION_URL)))?)?;
+        parse_degree_audit(&html)
+    }
+
+    // Get the teaching evaluation status of every course this term. Grades are only released after they are evaluated.
+    fn get_evaluations(&self) -> Result<Vec<Evaluation>> {
+        let html = check_session(self.send_and_get_html(self.get_client().get(self.resolve_url(JWFW_EVALUATION_URL)))?)?;
+        parse_evaluations(&html)
+    }
+
+    // The courses not evaluated yet.
+    fn get_pending_evaluations(&self) -> Result<Vec<Evaluation>> {
+        Ok(self.get_evaluations()?.into_iter().filter(|evaluation| !evaluation.evaluated).collect())
+    }
+
+    // Get the syllabus (课程大纲) links of the courses enrolled in the semester, keyed by course code, e.g. COMP130004.03.
+    // Courses without a syllabus are not in the map. `semester_id` is jwfw's id of the semester, e.g. 385.
+    fn get_syllabus_links(&self, semester_id: i32) -> Result<HashMap<String, String>> {
+        let request = self.get_client().get(self.resolve_url(JWFW_SYLLABUS_URL)).query(&[("semester.id", semester_id)]);
+        parse_syllabus_links(&check_semester(check_session(self.send_and_get_html(request)?)?, semester_id)?)
+    }
+
+    // Get the details of a course, e.g. for a course detail view. `course_id` is jwfw's id of the course,
+    // as in `CourseData`, e.g. 155165.
+    fn get_course_detail(&self, course_id: &str) -> Result<CourseDetail> {
+        let request = self.get_client().get(self.resolve_url(JWFW_COURSE_INFO_URL)).query(&[("lesson.id", course_id)]);
+        parse_course_detail(&check_session(self.send_and_get_html(request)?)?)
+    }
+
+    // Download the official transcript (成绩单) PDF, generated on demand, e.g. to save or share it.
+    // It fails with an `OtherError` if jwfw cannot generate it now, and a `ParseError` if the response is not a PDF.
+    fn download_transcript(&self) -> Result<Vec<u8>> {
+        let (content_type, bytes) = self.send_and_get_bytes(self.get_client().get(self.resolve_url(JWFW_TRANSCRIPT_URL)))?;
+        check_transcript(&content_type, bytes)
+    }
+
+    // Get the files the teachers uploaded for a course (课件), to download them with `download_attachment`.
+    // `course_id` is jwfw's id of the course, as in `get_course_detail`. It fails with an `OtherError` for a course
+    // the student is not enrolled in.
+    fn get_attachments(&self, course_id: &str) -> Result<Vec<Attachment>> {
+        let request = self.get_client().get(self.resolve_url(JWFW_COURSE_MATERIAL_URL)).query(&[("lesson.id", course_id)]);
+        parse_attachments(&check_session(self.send_and_get_html(request)?)?)
+    }
+
+    // Download an attachment, and get its file name with its bytes. The name is the one the server gives,
+    // or the one listed if it gives none.
+    fn download_attachment(&self, attachment: &Attachment) -> Result<(String, Vec<u8>)> {
+        let (filename, bytes) = self.send_and_get_file(self.get_client().get(self.resolve_url(&attachment.url)))?;
+        Ok((filename.unwrap_or_else(|| attachment.name.clone()), bytes))
+    }
+
+    // Get the academic advisor (导师), counselor (辅导员) and others advising the student, with their contacts
+    // where the student info page gives them. Empty if none is assigned yet.
+    fn get_advisors(&self) -> Result<Vec<Advisor>> {
+        let html = check_session(self.send_and_get_html(self.get_client().get(self.resolve_url(JWFW_STD_DETAIL_URL)))?)?;
+        parse_advisors(&html)
+    }
+
+    // Get the exams of this term, with the seats if they are assigned.
+    fn get_exams(&self) -> Result<Vec<Exam>> {
+        let html = check_session(self.send_and_get_html(self.get_client().get(self.resolve_url(JWFW_EXAM_TABLE_URL)))?)?;
+        parse_exams(&html)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fdu::test_support::fixture;
+
+    // the Wednesday course runs in weeks 1-10 and 12-16, and moves to Tuesday in week 11
+    static COURSE_TABLE: Lazy<String> = Lazy::new(|| fixture("jwfw_course_table.html"));
+
+    const HOMEPAGE: &str = r#"<html><head><title>本科生教务系统</title></head><body>
+        <div id="semester">当前学期：2022-2023学年 1学期</div>
+        <div class="notice"><ul>
+            <li><a href="/eams/noticeView.action?id=1">关于2022-2023学年第二学期选课的通知</a></li>
+            <li><a href="/eams/noticeView.action?id=2"> 期末考试安排 </a></li>
+        </ul></div>
+    </body></html>"#;
+
+    #[test]
+    fn test_parse_jwfw_home() {
+        let home = parse_jwfw_home(HOMEPAGE);
+        assert_eq!(home.term.as_deref(), Some("2022-2023学年1学期"));
+        assert_eq!(home.notices, vec!["关于2022-2023学年第二学期选课的通知", "期末考试安排"]);
+        assert_eq!(parse_jwfw_home("<html>本科生教务系统</html>"), JwfwHome::default());
+    }
+
+    #[test]
+    fn test_login_jwfw() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/eams/home.action", vec![MockResponse::ok(HOMEPAGE)]);
+
+        let fd = logged_in_fdu(&server, &["https://jwfw.fudan.edu.cn"], &["/eams/home.action"]);
+        fd.login_jwfw().expect("jwfw login error");
+        assert!(server.request_lines().iter().any(|line| line.starts_with("GET /eams/home.action?ticket=ST-mock")));
+    }
+
+    #[test]
+    fn test_schedule_slots() {
+        let courses = parse_course_data(&COURSE_TABLE);
+        assert_eq!(courses.len(), 2);
+        assert_eq!(courses[0].weeks, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 13, 14, 15, 16]);
+
+        let slots = courses[0].schedule_slots();
+        assert_eq!(slots, vec![ScheduleSlot::new(
+            "数据结构(COMP130004.03)".to_string(), "HGX304".to_string(), 3, 1, 3,
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 12, 13, 14, 15, 16],
+        )]);
+        let slots = courses[1].schedule_slots();
+        assert_eq!((slots[0].weekday, slots[0].start_unit, slots[0].end_unit), (2, 8, 10));
+        assert_eq!(slots[0].weeks, vec![11]);
+    }
+
+    #[test]
+    fn test_slots_on() {
+        let courses = parse_course_data(&COURSE_TABLE);
+        let term_start = NaiveDate::from_ymd_opt(2022, 9, 5).unwrap();
+        let day = |d: u32| NaiveDate::from_ymd_opt(2022, 9, d).unwrap();
+
+        // Wednesday of week 1
+        let slots = slots_on(&courses, term_start, day(7));
+        assert_eq!(slots.len(), 1);
+        assert_eq!((slots[0].classroom.as_str(), slots[0].start_unit, slots[0].end_unit), ("HGX304", 1, 3));
+        // no class on Thursday, nor before the term starts
+        assert!(slots_on(&courses, term_start, day(8)).is_empty());
+        assert!(slots_on(&courses, term_start, day(1)).is_empty());
+        // week 11 has the Tuesday course only, and skips the Wednesday one
+        assert_eq!(slots_on(&courses, term_start, term_start + Duration::days(7 * 10 + 1))[0].classroom, "H3409");
+        assert!(slots_on(&courses, term_start, term_start + Duration::days(7 * 10 + 2)).is_empty());
+    }
+
+    #[test]
+    fn test_timetable_week() {
+        let timetable = Timetable::new(parse_course_data(&COURSE_TABLE));
+        // the Wednesday course runs in weeks 1-10 and 12-16, and the Tuesday one in week 11 only
+        let week_1 = timetable.week(1);
+        assert_eq!(week_1.len(), 1);
+        assert_eq!((week_1[0].classroom.as_str(), week_1[0].weekday), ("HGX304", 3));
+        assert_eq!(timetable.week(12), week_1);
+        let week_11 = timetable.week(11);
+        assert_eq!((week_11[0].classroom.as_str(), week_11[0].weekday, week_11[0].start_unit), ("H3409", 2, 8));
+        // out of the term
+        assert!(timetable.week(0).is_empty());
+        assert!(timetable.week(17).is_empty());
+        assert!(timetable.week(u32::MAX).is_empty());
+    }
+
+    #[test]
+    fn test_current_and_next() {
+        use chrono::Utc;
+
+        let timetable = Timetable::new(parse_course_data(&COURSE_TABLE));
+        let term_start = NaiveDate::from_ymd_opt(2022, 9, 5).unwrap();
+        let shanghai = FixedOffset::east_opt(8 * 3600).unwrap();
+        let at = |month, day, hour, minute| shanghai.with_ymd_and_hms(2022, month, day, hour, minute, 0).unwrap();
+        let classroom = |slot: Option<ScheduleSlot>| slot.map(|slot| slot.classroom);
+
+        // mid-class on Wednesday of week 1 (units 1-3, 08:00-10:40), with the next one a week later
+        let (current, next) = timetable.current_and_next(term_start, at(9, 7, 9, 30));
+        assert_eq!(current.as_ref().map(|slot| slot.name.as_str()), Some("数据结构(COMP130004.03)"));
+        assert_eq!(current.as_ref().and_then(ScheduleSlot::start_time), NaiveTime::from_hms_opt(8, 0, 0));
+        assert_eq!(current.as_ref().and_then(ScheduleSlot::end_time), NaiveTime::from_hms_opt(10, 40, 0));
+        assert_eq!(classroom(next), Some("HGX304".to_string()));
+        // the same moment in UTC
+        assert_eq!(classroom(timetable.current_and_next(term_start, Utc.with_ymd_and_hms(2022, 9, 7, 1, 30, 0).unwrap()).0), Some("HGX304".to_string()));
+
+        // between classes: after the Wednesday class of week 10 comes the Tuesday one of week 11, at 15:25
+        let (current, next) = timetable.current_and_next(term_start, at(11, 9, 12, 0));
+        assert!(current.is_none());
+        assert_eq!(next.as_ref().map(|slot| (slot.classroom.as_str(), slot.weekday)), Some(("H3409", 2)));
+        assert_eq!(next.and_then(|slot| slot.start_time()), NaiveTime::from_hms_opt(15, 25, 0));
+        // right before a class, and the moment it ends
+        assert_eq!(classroom(timetable.current_and_next(term_start, at(9, 7, 7, 59)).1), Some("HGX304".to_string()));
+        assert!(timetable.current_and_next(term_start, at(9, 7, 10, 40)).0.is_none());
+
+        // nothing after the last class of the term, nor before the first in an empty table
+        assert_eq!(timetable.current_and_next(term_start, at(12, 21, 12, 0)), (None, None));
+        assert_eq!(Timetable::new(vec![]).current_and_next(term_start, at(9, 7, 9, 30)), (None, None));
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let timetable = Timetable::new(parse_course_data(&COURSE_TABLE));
+        let svg = timetable.render_svg(1);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert_eq!(svg.matches(r#"<rect class="course""#).count(), timetable.week(1).len());
+        assert!(svg.contains("数据结构(COMP130004.03)") && svg.contains("HGX304"));
+        // no class out of the term, but the grid is still there
+        let empty = timetable.render_svg(17);
+        assert_eq!(empty.matches(r#"<rect class="course""#).count(), 0);
+        assert!(empty.contains("周日"));
+
+        // two classes at the same time on Monday share the column, and a later one there has it to itself
+        let course = |name: &str, time: Vec<(i32, i32)>| CourseData {
+            id: "1".to_string(),
+            teachers: vec![],
+            name_with_course_id: name.to_string(),
+            classroom: "H3108".to_string(),
+            weeks: vec![1],
+            time,
+        };
+        let timetable = Timetable::new(vec![
+            course("高等数学A&B", vec![(0, 0), (0, 1)]),
+            course("线性代数", vec![(0, 1), (0, 2)]),
+            course("大学英语", vec![(0, 5), (0, 6)]),
+        ]);
+        let svg = timetable.render_svg(1);
+        assert_eq!(svg.matches(r#"<rect class="course""#).count(), 3);
+        assert_eq!(svg.matches(r#"width="60" height="80""#).count(), 2);
+        assert_eq!(svg.matches(r#"width="120" height="80""#).count(), 1);
+        assert!(svg.contains("高等数学A&amp;B"));
+        assert_eq!(assign_lanes(&timetable.week(1)), vec![(0, 2), (1, 2), (0, 1)]);
+    }
+
+    #[test]
+    fn test_split_teachers() {
+        assert_eq!(split_teachers("陈钊,王永钦,张晏"), vec!["陈钊", "王永钦", "张晏"]);
+        assert_eq!(split_teachers("陈钊， 王永钦"), vec!["陈钊", "王永钦"]);
+        assert_eq!(split_teachers("陈彤兵"), vec!["陈彤兵"]);
+        assert!(split_teachers("").is_empty());
+
+        const CO_TAUGHT: &str = r#"activity = new TaskActivity("155170","陈钊,王永钦,张晏","42080(ECON130010.01)","当代中国经济(ECON130010.01)","301","H4305","011111");
+index =2*unitCount+2;
+table0.activities[index][table0.activities[index].length]=activity;"#;
+        assert_eq!(parse_course_data(&CO_TAUGHT.to_string())[0].teachers, vec!["陈钊", "王永钦", "张晏"]);
+    }
+
+    #[test]
+    fn test_parse_ids() {
+        const MAIN: &str = r#"bg.form.addInput(form,"ids","123456");"#;
+        assert_eq!(parse_ids(&MAIN.to_string()).unwrap(), "123456");
+        assert!(parse_ids(&"<html>课表</html>".to_string()).expect_err("expect error").to_string().contains("ids"));
+    }
+
+    #[test]
+    fn test_parse_course_data_repeatedly() {
+        // the regexes are shared between calls, make sure nothing is left over from the last call
+        let first = parse_course_data(&COURSE_TABLE);
+        let second = parse_course_data(&COURSE_TABLE);
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].time, second[0].time);
+        assert_eq!(first[1].time, vec![(1, 7), (1, 8), (1, 9)]);
+    }
+
+    #[test]
+    fn test_parse_term_start_date() {
+        const CALENDAR: &str = r#"<table>
+            <tr><th>周次</th><th>一</th><th>二</th><th>三</th><th>四</th><th>五</th><th>六</th><th>日</th></tr>
+            <tr><td>第1周</td><td>2022-09-05</td><td>2022-09-06</td><td>2022-09-07</td><td>2022-09-08</td><td>2022-09-09</td><td>2022-09-10</td><td>2022-09-11</td></tr>
+            <tr><td>第2周</td><td>2022-09-12</td><td>2022-09-13</td><td>2022-09-14</td><td>2022-09-15</td><td>2022-09-16</td><td>2022-09-17</td><td>2022-09-18</td></tr>
+        </table>"#;
+        assert_eq!(parse_term_start_date(CALENDAR).unwrap(), NaiveDate::from_ymd_opt(2022, 9, 5).unwrap());
+
+        // the term starts on a Wednesday
+        const CALENDAR_MIDWEEK: &str = "<tr><td>第 1 周</td><td>2023-02-15</td></tr>";
+        assert_eq!(parse_term_start_date(CALENDAR_MIDWEEK).unwrap(), NaiveDate::from_ymd_opt(2023, 2, 13).unwrap());
+
+        parse_term_start_date("<html>系统维护中</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_term_start_date_without_login() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/eams/schoolCalendar.action", vec![MockResponse::ok("<tr><td>第1周</td><td>2022-09-05</td></tr>")]);
+
+        let fd = mock_fdu(&server, &["https://jwfw.fudan.edu.cn"]);
+        assert_eq!(fd.get_term_start_date().expect("jwfw calendar error"), NaiveDate::from_ymd_opt(2022, 9, 5).unwrap());
+        // the calendar is fetched directly, without going through UIS
+        assert_eq!(server.request_lines(), vec!["GET /eams/schoolCalendar.action".to_string()]);
+    }
 
     #[test]
     fn test_jwfw() {
-        dotenv::dotenv().ok();  // load env from .env file
-        let uid = std::env::var("UID").expect("environment variable UID not set");
-        let pwd = std::env::var("PWD").expect("environment variable PWD not set");
-
-        let mut fd = Fdu::new();
-        fd.login(uid.as_str(), pwd.as_str()).expect("login error");
-        fd.get_jwfw_homepage().expect("jwfw error");
-        fd.get_course_table().expect("jwfw course table error");
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/eams/home.action", vec![MockResponse::ok(HOMEPAGE)]);
+        server.route("/eams/courseTableForStd.action", vec![MockResponse::ok(r#"bg.form.addInput(form,"ids","123456");"#)]);
+        server.route("/eams/courseTableForStd!courseTable.action", vec![MockResponse::ok(&COURSE_TABLE)]);
+        server.route("/eams/schoolCalendar.action", vec![MockResponse::ok("<tr><td>第1周</td><td>2022-09-05</td></tr>")]);
+
+        let fd = logged_in_fdu(&server, &["https://jwfw.fudan.edu.cn"], &["/eams/home.action"]);
+        fd.login_jwfw().expect("jwfw login error");
+        assert_eq!(fd.get_jwfw_home().expect("jwfw homepage error").term.as_deref(), Some("2022-2023学年1学期"));
+        #[allow(deprecated)]
+        let homepage = fd.get_jwfw_homepage().expect("jwfw error");
+        assert!(homepage.contains("本科生教务系统"));
+        assert_eq!(fd.get_course_table().expect("jwfw course table error").len(), 2);
+        let (courses, raw) = fd.get_course_table_with_raw().expect("jwfw course table error");
+        assert_eq!(courses.len(), 2);
+        assert_eq!(raw, *COURSE_TABLE);
+        assert_eq!(fd.get_term_start_date().expect("jwfw calendar error"), NaiveDate::from_ymd_opt(2022, 9, 5).unwrap());
         fd.logout().expect("logout error");
+
+        let requests = server.requests();
+        let query = requests.iter().find(|r| r.starts_with("POST /eams/courseTableForStd!courseTable.action")).unwrap();
+        assert!(query.contains("ids=123456"));
+    }
+
+    #[test]
+    fn test_parse_notices() {
+        let notices = parse_notices(&fixture("jwfw_notices.html")).unwrap();
+        assert_eq!(notices, vec![
+            Notice {
+                title: "2022-2023学年第一学期期末考试安排".to_string(),
+                date: NaiveDate::from_ymd_opt(2023, 2, 15).unwrap(),
+                url: "https://jwfw.fudan.edu.cn/eams/noticeView.action?id=1025".to_string(),
+            },
+            Notice {
+                title: "关于2022-2023学年第二学期选课的通知".to_string(),
+                date: NaiveDate::from_ymd_opt(2023, 2, 10).unwrap(),
+                url: "https://jwfw.fudan.edu.cn/eams/noticeView.action?id=1021".to_string(),
+            },
+            Notice {
+                title: "关于缓考申请&补考的说明".to_string(),
+                date: NaiveDate::from_ymd_opt(2023, 1, 5).unwrap(),
+                url: "https://jwfw.fudan.edu.cn/eams/noticeView.action?id=1003".to_string(),
+            },
+        ]);
+
+        const NO_NOTICE: &str = "<table id=\"notices\"><tr><th>标题</th><th>发布时间</th></tr></table>";
+        assert!(parse_notices(NO_NOTICE).unwrap().is_empty());
+        parse_notices("<html>通知公告</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_get_notices() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/eams/noticeView!search.action", vec![MockResponse::ok(&fixture("jwfw_notices.html"))]);
+        let fd = logged_in_fdu(&server, &["https://jwfw.fudan.edu.cn"], &[]);
+        let titles = |notices: Vec<Notice>| notices.into_iter().map(|notice| notice.title).collect::<Vec<_>>();
+        assert_eq!(titles(fd.get_notices(2).unwrap()), vec!["2022-2023学年第一学期期末考试安排", "关于2022-2023学年第二学期选课的通知"]);
+        // fewer than asked for
+        assert_eq!(fd.get_notices(10).unwrap().len(), 3);
+        assert!(server.request_lines().iter().any(|line| line.starts_with("GET /eams/noticeView!search.action?pageNo=1&pageSize=10")));
+
+        // a notice about maintenance is just a notice
+        server.route("/eams/noticeView!search.action", vec![MockResponse::ok(&fixture("jwfw_notices_maintenance.html"))]);
+        assert_eq!(fd.get_notices(10).unwrap()[0].title, "关于2月18日教务系统升级维护的通知（系统维护中暂停选课与成绩查询）");
+    }
+
+    #[test]
+    fn test_check_course_table() {
+        let check = |html: &str| check_course_table(html, parse_course_data(&html.to_string()));
+        assert_eq!(check(&COURSE_TABLE).unwrap().len(), 2);
+        assert!(check(&fixture("jwfw_course_table_empty.html")).unwrap().is_empty());
+        check(&fixture("jwfw_course_table_changed.html")).expect_err("expect format changed");
+        check("<html>统一身份认证</html>").expect_err("expect no course table");
+    }
+
+    #[test]
+    fn test_session_expired() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        const EXPIRED: &str = r#"<html><head><title>提示</title></head>
+            <body><div class="errorMessage">业务流程已过期，请重新登录</div></body></html>"#;
+
+        let expired = check_session(EXPIRED.to_string()).expect_err("expect session expired");
+        assert!(expired.is_session_expired());
+        assert!(check_session("<html>本科生教务系统</html>".to_string()).is_ok());
+
+        let server = MockServer::start();
+        server.route("/eams/home.action", vec![MockResponse::ok(EXPIRED)]);
+        server.route("/eams/courseTableForStd.action", vec![MockResponse::ok(EXPIRED)]);
+        server.route("/eams/schoolCalendar.action", vec![MockResponse::ok(EXPIRED)]);
+
+        let fd = logged_in_fdu(&server, &["https://jwfw.fudan.edu.cn"], &["/eams/home.action"]);
+        assert!(fd.login_jwfw().expect_err("expect session expired").is_session_expired());
+        assert!(fd.get_course_table().expect_err("expect session expired").is_session_expired());
+        assert!(fd.get_term_start_date().expect_err("expect session expired").is_session_expired());
+    }
+
+    #[test]
+    fn test_semester_out_of_range() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let out_of_range = fixture("jwfw_semester_out_of_range.html");
+        let e = check_semester(out_of_range.clone(), 999).expect_err("expect error");
+        assert!(e.to_string().contains("semester 999 is out of the range"));
+        assert!(check_semester("<html>本科生教务系统</html>".to_string(), 385).is_ok());
+
+        let server = MockServer::start();
+        server.route("/eams/stdSyllabus!search.action", vec![MockResponse::ok(&out_of_range)]);
+        let fd = logged_in_fdu(&server, &["https://jwfw.fudan.edu.cn"], &[]);
+        let e = fd.get_syllabus_links(999).expect_err("expect error");
+        assert!(e.to_string().contains("semester 999"));
+        assert!(!e.is_session_expired());
+    }
+
+    #[test]
+    fn test_parse_degree_audit() {
+        const PLAN: &str = r#"<html><body>
+            <table id="creditSummary">
+                <tr><th>课程类别</th><th>要求学分</th><th>已修学分</th></tr>
+                <tr><td>专业必修课程</td><td>40</td><td>32</td></tr>
+                <tr><td>通识教育核心课程</td><td>26</td><td>28.5</td></tr>
+            </table>
+            <table id="unfinishedCourses">
+                <tr><th>课程代码</th><th>课程名称</th><th>学分</th></tr>
+                <tr><td>COMP130004</td><td>数据结构</td><td>3</td></tr>
+            </table>
+        </body></html>"#;
+        let audit = parse_degree_audit(PLAN).unwrap().unwrap();
+        assert_eq!(audit.categories[0], CreditCategory { name: "专业必修课程".to_string(), required: 40.0, completed: 32.0 });
+        assert!(!audit.categories[0].is_completed());
+        assert!(audit.categories[1].is_completed());
+        assert_eq!(audit.outstanding, vec![OutstandingCourse { code: "COMP130004".to_string(), name: "数据结构".to_string(), credits: 3.0 }]);
+
+        assert_eq!(parse_degree_audit("<html>没有找到培养方案</html>").unwrap(), None);
+        parse_degree_audit("<html>本科生教务系统</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_parse_evaluations() {
+        const EVALUATIONS: &str = r#"<table class="gridtable">
+            <thead><tr><th>课程序号</th><th>课程名称</th><th>教师</th><th>操作</th></tr></thead>
+            <tbody>
+                <tr><td>COMP130004.03</td><td>数据结构</td><td>陈彤兵</td><td><a href="/eams/quality/stdEvaluate!answer.action?lessonId=1">进行评教</a></td></tr>
+                <tr><td>ECON130003.01</td><td>国际金融</td><td>郑辉</td><td>已评教</td></tr>
+            </tbody>
+        </table>"#;
+        let evaluations = parse_evaluations(EVALUATIONS).unwrap();
+        assert_eq!(evaluations[0], Evaluation {
+            code: "COMP130004.03".to_string(),
+            name: "数据结构".to_string(),
+            teachers: vec!["陈彤兵".to_string()],
+            evaluated: false,
+        });
+        assert!(evaluations[1].evaluated);
+
+        const EMPTY: &str = r#"<table class="gridtable"><tbody><tr><td colspan="4">没有需要评教的课程</td></tr></tbody></table>"#;
+        assert!(parse_evaluations(EMPTY).unwrap().is_empty());
+        parse_evaluations("<html>本科生教务系统</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_parse_exams() {
+        const EXAMS: &str = r#"<table class="gridtable">
+            <tr><th>课程序号</th><th>课程名称</th><th>考试日期</th><th>考试时间</th><th>考试地点</th><th>座位号</th></tr>
+            <tr><td>COMP130004.03</td><td>数据结构</td><td>2023-01-03</td><td>08:30-10:30</td><td>H3109</td><td>25</td></tr>
+            <tr><td>ECON130003.01</td><td>国际金融</td><td>2022-12-27</td><td>13:00-15:00</td><td>H2201</td><td>未安排</td></tr>
+            <tr><td>ECON130064.01</td><td>博弈论</td><td>2023-01-05</td><td>13:00-15:00</td><td>H6212</td><td></td></tr>
+        </table>"#;
+        let exams = parse_exams(EXAMS).unwrap();
+        assert_eq!(exams[0], Exam {
+            code: "COMP130004.03".to_string(),
+            name: "数据结构".to_string(),
+            date: NaiveDate::from_ymd_opt(2023, 1, 3).unwrap(),
+            time: "08:30-10:30".to_string(),
+            start: NaiveDate::from_ymd_opt(2023, 1, 3).unwrap().and_hms_opt(8, 30, 0),
+            end: NaiveDate::from_ymd_opt(2023, 1, 3).unwrap().and_hms_opt(10, 30, 0),
+            room: "H3109".to_string(),
+            seat: Some("25".to_string()),
+        });
+        assert_eq!((exams[1].seat.as_ref(), exams[2].seat.as_ref()), (None, None));
+
+        // no seat column before any seat is assigned
+        const UNSEATED: &str = r#"<table>
+            <tr><th>课程序号</th><th>课程名称</th><th>考试日期</th><th>考试时间</th><th>考试地点</th></tr>
+            <tr><td>COMP130004.03</td><td>数据结构</td><td>2023-01-03</td><td>08:30-10:30</td><td>H3109</td></tr>
+        </table>"#;
+        assert_eq!(parse_exams(UNSEATED).unwrap()[0].seat, None);
+        parse_exams("<html>本科生教务系统</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_parse_advisors() {
+        let advisors = parse_advisors(&fixture("jwfw_std_detail.html")).unwrap();
+        assert_eq!(advisors, vec![
+            Advisor {
+                role: "导师".to_string(),
+                name: "张三".to_string(),
+                title: Some("教授".to_string()),
+                email: Some("zhangsan@fudan.edu.cn".to_string()),
+                phone: Some("021-65640000".to_string()),
+            },
+            Advisor { role: "辅导员".to_string(), name: "李四".to_string(), title: None, email: Some("lisi@fudan.edu.cn".to_string()), phone: None },
+        ]);
+
+        // none assigned, and no contact columns
+        const NO_ADVISOR: &str = "<table id=\"advisors\"><tr><th>类别</th><th>姓名</th></tr></table>";
+        assert!(parse_advisors(NO_ADVISOR).unwrap().is_empty());
+        parse_advisors("<html>学籍信息</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_parse_attachments() {
+        let attachments = parse_attachments(&fixture("jwfw_lesson_material.html")).unwrap();
+        assert_eq!(attachments, vec![
+            Attachment {
+                name: "第一章 绪论.pdf".to_string(),
+                url: "https://jwfw.fudan.edu.cn/eams/lessonMaterial!download.action?material.id=1001".to_string(),
+                size: Some(1572864),
+            },
+            Attachment {
+                name: "实验一&实验二.zip".to_string(),
+                url: "https://jwfw.fudan.edu.cn/eams/lessonMaterial!download.action?material.id=1002".to_string(),
+                size: Some(524288),
+            },
+            Attachment {
+                name: "课程大纲.docx".to_string(),
+                url: "https://elearning.fudan.edu.cn/files/3003/download".to_string(),
+                size: None,
+            },
+        ]);
+
+        const NO_MATERIAL: &str = "<table id=\"materials\"><tr><th>文件名</th><th>大小</th></tr></table>";
+        assert!(parse_attachments(NO_MATERIAL).unwrap().is_empty());
+        parse_attachments("<html><div class=\"error\">您没有权限查看该课程的资料</div></html>").expect_err("expect error");
+        parse_attachments("<html>课程资料</html>").expect_err("expect error");
+        assert_eq!(parse_size("300B"), Some(300));
+        assert_eq!(parse_size("2G"), Some(2 << 30));
+    }
+
+    #[test]
+    fn test_download_attachment() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        let fd = logged_in_fdu(&server, &["https://jwfw.fudan.edu.cn"], &[]);
+        server.route("/eams/lessonMaterial!download.action", vec![
+            MockResponse::bytes(200, b"%PDF-1.4".to_vec()).header("Content-Type", "application/pdf")
+                .header("Content-Disposition", "attachment; filename*=UTF-8''%E7%AC%AC%E4%B8%80%E7%AB%A0.pdf"),
+            MockResponse::bytes(200, b"PK".to_vec()),
+            MockResponse::new(403, "<html>无权访问</html>"),
+        ]);
+        let attachment = |name: &str| Attachment {
+            name: name.to_string(),
+            url: "https://jwfw.fudan.edu.cn/eams/lessonMaterial!download.action?material.id=1001".to_string(),
+            size: None,
+        };
+
+        assert_eq!(fd.download_attachment(&attachment("第一章 绪论.pdf")).unwrap(), ("第一章.pdf".to_string(), b"%PDF-1.4".to_vec()));
+        // no name given by the server
+        assert_eq!(fd.download_attachment(&attachment("实验一.zip")).unwrap().0, "实验一.zip");
+        fd.download_attachment(&attachment("实验二.zip")).expect_err("expect access denied");
+    }
+
+    #[test]
+    fn test_parse_course_detail() {
+        let detail = parse_course_detail(&fixture("jwfw_course_detail.html")).unwrap();
+        assert_eq!(detail, CourseDetail {
+            code: "COMP130004".to_string(),
+            name: "数据结构".to_string(),
+            credits: Some(3.0),
+            description: Some("介绍线性表、树、图等基本数据结构，以及排序与查找算法。".to_string()),
+            prerequisites: vec!["程序设计".to_string(), "离散数学".to_string()],
+            assessment: vec![("平时作业".to_string(), 20.0), ("期中考试".to_string(), 30.0), ("期末考试".to_string(), 50.0)],
+        });
+
+        // sparse metadata
+        const SPARSE: &str = r#"<table class="infoTable">
+            <tr><th>课程代码</th><td>PEDU110001</td></tr><tr><th>课程名称</th><td>体育</td></tr>
+            <tr><th>学分</th><td></td></tr><tr><th>先修课程</th><td>无</td></tr>
+        </table>"#;
+        let detail = parse_course_detail(SPARSE).unwrap();
+        assert_eq!((detail.credits, detail.description, detail.prerequisites.len(), detail.assessment.len()), (None, None, 0, 0));
+
+        parse_course_detail("<html>本科生教务系统</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_parse_exam_time() {
+        let date = NaiveDate::from_ymd_opt(2022, 12, 27).unwrap();
+        let (start, end) = parse_exam_time(date, "13:00 - 15:00").unwrap();
+        assert_eq!((start, end), (date.and_hms_opt(13, 0, 0).unwrap(), date.and_hms_opt(15, 0, 0).unwrap()));
+        assert_eq!(end - start, Duration::hours(2));
+        assert_eq!(parse_exam_time(date, "待定"), None);
+        assert_eq!(parse_exam_time(date, "13:00-"), None);
+    }
+
+    #[test]
+    fn test_download_transcript() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        const PDF: &[u8] = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n1 0 obj\n<<>>\nendobj\n%%EOF\n";
+        let server = MockServer::start();
+        server.route("/eams/stdTranscript!download.action", vec![
+            MockResponse::bytes(200, PDF.to_vec()).header("Content-Type", "application/pdf"),
+            MockResponse::ok("<html><div class=\"errorMessage\">成绩单暂不可用，请稍后再试</div></html>"),
+            MockResponse::ok("<html>业务流程已过期</html>"),
+        ]);
+        let fd = logged_in_fdu(&server, &["https://jwfw.fudan.edu.cn"], &[]);
+
+        let transcript = fd.download_transcript().unwrap();
+        assert!(transcript.starts_with(b"%PDF-"));
+        assert_eq!(transcript, PDF);
+        let e = fd.download_transcript().expect_err("expect not available");
+        assert!(e.to_string().contains("not available"));
+        assert!(fd.download_transcript().expect_err("expect session expired").is_session_expired());
+
+        check_transcript("application/pdf", b"<html></html>".to_vec()).expect_err("expect not a PDF");
+    }
+
+    #[test]
+    fn test_find_exam_conflicts() {
+        let exam = |code: &str, day: u32, time: &str| {
+            let date = NaiveDate::from_ymd_opt(2022, 12, day).unwrap();
+            let (start, end) = parse_exam_time(date, time).unzip();
+            Exam { code: code.to_string(), name: code.to_string(), date, time: time.to_string(), start, end, room: "H3109".to_string(), seat: None }
+        };
+        let exams = vec![
+            exam("A.01", 27, "08:30-10:30"),
+            // the same day, but after A
+            exam("B.01", 27, "13:00-15:00"),
+            exam("C.01", 27, "10:00-12:00"),
+            // another day, at the same time as A
+            exam("D.01", 28, "08:30-10:30"),
+            // right after B ends
+            exam("E.01", 27, "15:00-17:00"),
+            exam("F.01", 27, "待定"),
+        ];
+        assert_eq!(find_exam_conflicts(&exams), vec![(0, 2)]);
+        assert!(find_exam_conflicts(&exams[3..]).is_empty());
+        assert!(find_exam_conflicts(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_syllabus_links() {
+        const SYLLABUS: &str = r#"<table class="gridtable">
+            <tr><th>课程序号</th><th>课程名称</th><th>教师</th><th>教学大纲</th></tr>
+            <tr><td>COMP130004.03</td><td>数据结构</td><td>陈彤兵</td><td><a href="syllabus!info.action?lesson.id=155165">查看</a></td></tr>
+            <tr><td>ECON130003.01</td><td>国际金融</td><td>郑辉</td><td>暂无</td></tr>
+            <tr><td>PEDU110018.01</td><td>篮球</td><td>王五</td><td><a href="https://www.fudan.edu.cn/syllabus/pedu.pdf">查看</a></td></tr>
+        </table>"#;
+        let links = parse_syllabus_links(SYLLABUS).unwrap();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links["COMP130004.03"], "https://jwfw.fudan.edu.cn/eams/syllabus!info.action?lesson.id=155165");
+        assert_eq!(links["PEDU110018.01"], "https://www.fudan.edu.cn/syllabus/pedu.pdf");
+        assert!(!links.contains_key("ECON130003.01"));
+
+        parse_syllabus_links("<html>本科生教务系统</html>").expect_err("expect error");
     }
 }
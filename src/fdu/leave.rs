@@ -0,0 +1,180 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use super::api::parse_api_data;
+use super::prelude::*;
+
+impl LeaveClient for Fdu {}
+
+// the leave (请假) requests of the student affairs system
+const LEAVE_INDEX_URL: &str = "https://xsgl.fudan.edu.cn/leave/index";
+const LEAVE_RECORDS_URL: &str = "https://xsgl.fudan.edu.cn/leave/api/student/records";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LeaveStatus {
+    // 待审批 or 审批中, waiting for the counselor (辅导员)
+    Pending,
+    // 已批准, or 已销假 once back
+    Approved,
+    // 已驳回 or 未批准
+    Rejected,
+    // a status we do not know of, eg. 已撤回, as shown
+    Other(String),
+}
+
+impl LeaveStatus {
+    fn parse(text: &str) -> Self {
+        match text.trim() {
+            "待审批" | "审批中" => LeaveStatus::Pending,
+            "已批准" | "已销假" => LeaveStatus::Approved,
+            "已驳回" | "未批准" => LeaveStatus::Rejected,
+            other => LeaveStatus::Other(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaveRequest {
+    // eg. 事假, 病假
+    kind: String,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    reason: String,
+    status: LeaveStatus,
+}
+
+impl LeaveRequest {
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn start(&self) -> NaiveDateTime {
+        self.start
+    }
+
+    pub fn end(&self) -> NaiveDateTime {
+        self.end
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    pub fn status(&self) -> &LeaveStatus {
+        &self.status
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.status == LeaveStatus::Pending
+    }
+}
+
+// eg. {"qjlx":"事假","kssj":"2023-03-01 08:00","jssj":"2023-03-03 18:00","qjsy":"回家办理证件","zt":"已批准"}
+// for 请假类型, 开始时间, 结束时间, 请假事由 and 状态.
+#[derive(Deserialize)]
+struct LeaveData {
+    #[serde(default)]
+    qjlx: String,
+    kssj: String,
+    jssj: String,
+    #[serde(default)]
+    qjsy: String,
+    #[serde(default)]
+    zt: String,
+}
+
+fn parse_time(time: &str) -> Result<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(time.trim(), "%Y-%m-%d %H:%M")
+        .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse leave time {} error", time)))
+}
+
+// Parse the requests. `data` is empty or null for students who never asked for a leave.
+fn parse_leave_requests(json: &str) -> Result<Vec<LeaveRequest>> {
+    let records: Vec<LeaveData> = parse_api_data(json, "get leave requests")?.unwrap_or_default();
+
+    records.into_iter().map(|leave| Ok(LeaveRequest {
+        start: parse_time(&leave.kssj)?,
+        end: parse_time(&leave.jssj)?,
+        status: LeaveStatus::parse(&leave.zt),
+        kind: leave.qjlx,
+        reason: leave.qjsy,
+    })).collect()
+}
+
+pub trait LeaveClient: Account {
+    // Get the leave (请假) requests the student submitted, pending, approved or rejected, as listed by the system,
+    // the latest first. Empty if there is no record.
+    fn get_leave_requests(&self) -> Result<Vec<LeaveRequest>> {
+        self.follow_cas(LEAVE_INDEX_URL)?;
+        let json = self.send_and_get_json(self.get_client().get(self.resolve_url(LEAVE_RECORDS_URL)))?;
+        parse_leave_requests(&json)
+    }
+
+    // Only the requests still waiting for approval.
+    fn get_pending_leave_requests(&self) -> Result<Vec<LeaveRequest>> {
+        Ok(self.get_leave_requests()?.into_iter().filter(LeaveRequest::is_pending).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::fdu::test_support::fixture;
+
+    #[test]
+    fn test_parse_leave_requests() {
+        let time = |day, hour| NaiveDate::from_ymd_opt(2023, 3, day).unwrap().and_hms_opt(hour, 0, 0).unwrap();
+        let requests = parse_leave_requests(&fixture("leave_records.json")).unwrap();
+        assert_eq!(requests, vec![
+            LeaveRequest {
+                kind: "病假".to_string(),
+                start: time(20, 8),
+                end: time(21, 18),
+                reason: "发烧就医".to_string(),
+                status: LeaveStatus::Pending,
+            },
+            LeaveRequest {
+                kind: "事假".to_string(),
+                start: time(1, 8),
+                end: time(3, 18),
+                reason: "回家办理证件".to_string(),
+                status: LeaveStatus::Approved,
+            },
+            LeaveRequest {
+                kind: "事假".to_string(),
+                start: time(10, 14),
+                end: time(10, 18),
+                reason: "参加比赛".to_string(),
+                status: LeaveStatus::Rejected,
+            },
+        ]);
+        assert_eq!(requests.iter().filter(|request| request.is_pending()).count(), 1);
+
+        parse_leave_requests(r#"{"code":0,"data":[{"qjlx":"事假","kssj":"3月1日","jssj":"2023-03-03 18:00"}]}"#)
+            .expect_err("expect error");
+    }
+
+    #[test]
+    fn test_get_pending_leave_requests() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/leave/index", vec![MockResponse::ok("<html>请假管理</html>")]);
+        server.route("/leave/api/student/records", vec![MockResponse::json(&fixture("leave_records.json"))]);
+
+        let fd = logged_in_fdu(&server, &["https://xsgl.fudan.edu.cn"], &["/leave/index"]);
+        let pending = fd.get_pending_leave_requests().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].kind(), pending[0].reason()), ("病假", "发烧就医"));
+        assert!(server.request_lines().iter().any(|line| line.starts_with("GET /leave/index?ticket=ST-mock")));
+    }
+
+    #[test]
+    fn test_parse_no_leave_request() {
+        assert!(parse_leave_requests(r#"{"code":0,"msg":"暂无记录","data":[]}"#).unwrap().is_empty());
+        assert!(parse_leave_requests(r#"{"code":0,"msg":"暂无记录","data":null}"#).unwrap().is_empty());
+        parse_leave_requests(r#"{"code":401,"msg":"未登录"}"#).expect_err("expect error");
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use reqwest::blocking::Client;
+use reqwest::cookie::Jar;
+use scraper::Html;
+
+use super::html::{element_text, CELL_SELECTOR, HEADER_SELECTOR, ROW_SELECTOR};
+use super::prelude::*;
+
+const LIBRARY_LOANS_URL: &str = "https://opac.fudan.edu.cn/reader/loans";
+const LIBRARY_RENEW_URL: &str = "https://opac.fudan.edu.cn/reader/renew";
+
+struct Library {
+    fdu: Fdu,
+}
+
+impl Library {
+    fn new() -> Self {
+        Self { fdu: Fdu::new() }
+    }
+
+    fn new_from_fdu(fdu: Fdu) -> Self {
+        Self { fdu }
+    }
+
+    // Log out and drop the client, see `Fdu::close`.
+    fn close(self) -> Result<()> {
+        self.fdu.close()
+    }
+}
+
+impl HttpClient for Library {
+    fn get_client(&self) -> &Client {
+        self.fdu.get_client()
+    }
+
+    fn get_client_following(&self, follow: bool) -> &Client {
+        self.fdu.get_client_following(follow)
+    }
+
+    fn get_cookie_store(&self) -> &Arc<Jar> {
+        self.fdu.get_cookie_store()
+    }
+
+    fn get_capture_dir(&self) -> Option<&Path> {
+        self.fdu.get_capture_dir()
+    }
+
+    fn get_endpoint_overrides(&self) -> &[(String, String)] {
+        self.fdu.get_endpoint_overrides()
+    }
+
+    fn get_max_body_size(&self) -> usize {
+        self.fdu.get_max_body_size()
+    }
+
+    fn get_request_delays(&self) -> &[(String, Duration)] {
+        self.fdu.get_request_delays()
+    }
+
+    fn get_extra_headers(&self) -> &[(String, String, String)] {
+        self.fdu.get_extra_headers()
+    }
+
+    fn get_strict_decoding(&self) -> bool {
+        self.fdu.get_strict_decoding()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Loan {
+    title: String,
+    barcode: String,
+    loan_date: NaiveDate,
+    due_date: NaiveDate,
+    renewals: i32,
+}
+
+impl Library {
+    fn get_loans(&self) -> Result<Vec<Loan>> {
+        // log in to the reader portal through CAS, then get the loans like any other page
+        self.follow_cas(LIBRARY_LOANS_URL)?;
+        let html = self.send_and_get_html(self.get_client().get(self.resolve_url(LIBRARY_LOANS_URL)))?;
+        parse_loans(&html)
+    }
+
+    // Renew the book with `barcode`, returning whether the library accepts it.
+    fn renew(&self, barcode: &str) -> Result<bool> {
+        let html = self.send_and_get_html(self.get_client().post(self.resolve_url(LIBRARY_RENEW_URL)).form(&[("barcode", barcode)]))?;
+        Ok(html.contains("续借成功"))
+    }
+}
+
+fn parse_date(text: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(text, "%Y%m%d"))
+        .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse loan date {} error", text)))
+}
+
+// Parse the loans table of the reader portal. The columns are found by their headers (题名, 条码, 借书日期, 应还日期, 续借次数),
+// so that a reordered or extra column does not break parsing. There is a table without data rows if nothing is borrowed.
+fn parse_loans(html: &str) -> Result<Vec<Loan>> {
+    let document = Html::parse_document(html);
+
+    let headers: Vec<String> = document.select(&HEADER_SELECTOR).map(element_text).collect();
+    let column = |name: &str| headers.iter().position(|header| header == name)
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("column {} not found in loans table", name)));
+    let (title, barcode, loan_date, due_date, renewals) =
+        (column("题名")?, column("条码")?, column("借书日期")?, column("应还日期")?, column("续借次数")?);
+
+    let mut loans = Vec::new();
+    for tr in document.select(&ROW_SELECTOR) {
+        let cells: Vec<String> = tr.select(&CELL_SELECTOR).map(element_text).collect();
+        if cells.len() < headers.len() {
+            continue;
+        }
+        loans.push(Loan {
+            title: cells[title].clone(),
+            barcode: cells[barcode].clone(),
+            loan_date: parse_date(&cells[loan_date])?,
+            due_date: parse_date(&cells[due_date])?,
+            renewals: cells[renewals].parse().unwrap_or_default(),
+        });
+    }
+    Ok(loans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdu::test_support::{fixture, logged_in_fdu, MockResponse, MockServer};
+
+    #[test]
+    fn test_parse_loans() {
+        let loans = parse_loans(&fixture("library_loans.html")).unwrap();
+        assert_eq!(loans.len(), 2);
+        assert_eq!(loans[0], Loan {
+            title: "数据结构与算法分析".to_string(),
+            barcode: "31130001234567".to_string(),
+            loan_date: NaiveDate::from_ymd_opt(2022, 9, 1).unwrap(),
+            due_date: NaiveDate::from_ymd_opt(2022, 10, 1).unwrap(),
+            renewals: 0,
+        });
+        assert_eq!(loans[1].due_date, NaiveDate::from_ymd_opt(2022, 10, 15).unwrap());
+        assert_eq!(loans[1].renewals, 1);
+    }
+
+    #[test]
+    fn test_parse_no_loans() {
+        const LOANS: &str = r#"<table>
+            <tr><th>序号</th><th>题名</th><th>条码</th><th>借书日期</th><th>应还日期</th><th>续借次数</th></tr>
+            <tr><td colspan="6">暂无借阅记录</td></tr>
+        </table>"#;
+        assert!(parse_loans(LOANS).unwrap().is_empty());
+        // not the loans page at all
+        parse_loans("<html>请登录</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_get_loans_and_renew() {
+        let server = MockServer::start();
+        server.route("GET /reader/loans", vec![MockResponse::ok(&fixture("library_loans.html"))]);
+        server.route("POST /reader/renew", vec![MockResponse::ok("<html>续借成功</html>"), MockResponse::ok("<html>已达最大续借次数</html>")]);
+
+        let library = Library::new_from_fdu(logged_in_fdu(&server, &["https://opac.fudan.edu.cn"], &["/reader/loans"]));
+        let loans = library.get_loans().unwrap();
+        assert_eq!(loans.len(), 2);
+        assert!(library.renew(&loans[0].barcode).unwrap());
+        assert!(!library.renew(&loans[1].barcode).unwrap());
+        assert!(server.requests().last().unwrap().ends_with("barcode=31130007654321"));
+    }
+
+    #[test]
+    #[ignore = "needs the real UIS and UID/PWD"]
+    fn test_get_loans() {
+        dotenv::dotenv().ok();  // load env from .env file
+        let uid = std::env::var("UID").expect("environment variable UID not set");
+        let pwd = std::env::var("PWD").expect("environment variable PWD not set");
+
+        let mut library = Library::new();
+        library.fdu.login(uid.as_str(), pwd.as_str()).expect("login error");
+        library.get_loans().expect("get loans error");
+        library.fdu.logout().expect("logout error");
+    }
+}
@@ -0,0 +1,236 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::api::parse_api_data;
+use super::number::parse_number;
+use super::prelude::*;
+
+impl MinorClient for Fdu {}
+
+// the minor (辅修) and double degree (双学位) application system
+const MINOR_INDEX_URL: &str = "https://fxsq.fudan.edu.cn/minor/index";
+const MINOR_STATUS_URL: &str = "https://fxsq.fudan.edu.cn/minor/api/student/applications";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgramKind {
+    // 辅修
+    Minor,
+    // 双学位
+    DoubleDegree,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApprovalStatus {
+    // 审核中 or 待审核
+    Pending,
+    // 已通过
+    Approved,
+    // 未通过
+    Rejected,
+    // a status we do not know of, as shown
+    Other(String),
+}
+
+impl ApprovalStatus {
+    fn parse(text: &str) -> Self {
+        match text.trim() {
+            "审核中" | "待审核" => ApprovalStatus::Pending,
+            "已通过" => ApprovalStatus::Approved,
+            "未通过" => ApprovalStatus::Rejected,
+            other => ApprovalStatus::Other(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinorApplication {
+    // the program applied for, eg. 经济学
+    program: String,
+    kind: ProgramKind,
+    status: ApprovalStatus,
+    // None if the system does not say
+    applied_on: Option<NaiveDate>,
+}
+
+impl MinorApplication {
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn kind(&self) -> ProgramKind {
+        self.kind
+    }
+
+    pub fn status(&self) -> &ApprovalStatus {
+        &self.status
+    }
+
+    pub fn applied_on(&self) -> Option<NaiveDate> {
+        self.applied_on
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinorCourse {
+    // eg. ECON130003.01
+    code: String,
+    name: String,
+    credits: f64,
+}
+
+impl MinorCourse {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn credits(&self) -> f64 {
+        self.credits
+    }
+}
+
+// The applications and the courses enrolled in for the programs. Both are empty for students who never applied.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MinorStatus {
+    applications: Vec<MinorApplication>,
+    courses: Vec<MinorCourse>,
+}
+
+impl MinorStatus {
+    pub fn applications(&self) -> &[MinorApplication] {
+        &self.applications
+    }
+
+    pub fn courses(&self) -> &[MinorCourse] {
+        &self.courses
+    }
+}
+
+// eg. {"sqxm":[{"xmmc":"经济学","lx":"辅修","zt":"已通过","sqrq":"2022-03-01"}],"kc":[{"kcdm":"ECON130003.01","kcmc":"国际金融","xf":"3"}]}
+// for 申请项目 (with 项目名称, 类型, 状态 and 申请日期) and 课程 (with 课程代码, 课程名称 and 学分).
+// The applications and the courses are missing or null for students who never applied.
+#[derive(Deserialize)]
+struct MinorData {
+    #[serde(default)]
+    sqxm: Option<Vec<ApplicationData>>,
+    #[serde(default)]
+    kc: Option<Vec<CourseData>>,
+}
+
+#[derive(Deserialize)]
+struct ApplicationData {
+    xmmc: String,
+    lx: String,
+    #[serde(default)]
+    zt: String,
+    #[serde(default)]
+    sqrq: String,
+}
+
+// The credits are a string.
+#[derive(Deserialize)]
+struct CourseData {
+    kcdm: String,
+    kcmc: String,
+    #[serde(default)]
+    xf: String,
+}
+
+fn parse_minor_status(json: &str) -> Result<MinorStatus> {
+    let data = match parse_api_data::<MinorData>(json, "get minor status")? {
+        Some(data) => data,
+        None => return Ok(MinorStatus::default()),
+    };
+
+    let applications = data.sqxm.unwrap_or_default().into_iter().map(|application| {
+        let kind = match application.lx.trim() {
+            "辅修" => ProgramKind::Minor,
+            "双学位" | "双学士学位" => ProgramKind::DoubleDegree,
+            kind => return Err(SDKError::with_type(ErrorType::ParseError, format!("unknown program kind {} of {}", kind, application.xmmc))),
+        };
+        let applied_on = match application.sqrq.trim() {
+            "" => None,
+            date => Some(NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse application date {} error", date)))?),
+        };
+        Ok(MinorApplication { program: application.xmmc, kind, status: ApprovalStatus::parse(&application.zt), applied_on })
+    }).collect::<Result<Vec<_>>>()?;
+
+    let courses = data.kc.unwrap_or_default().into_iter().map(|course| {
+        let credits = parse_number(&course.xf)
+            .ok_or_else(|| SDKError::with_type(ErrorType::ParseError, format!("parse credits {} of {} error", course.xf, course.kcmc)))?;
+        Ok(MinorCourse { code: course.kcdm, name: course.kcmc, credits })
+    }).collect::<Result<Vec<_>>>()?;
+
+    Ok(MinorStatus { applications, courses })
+}
+
+pub trait MinorClient: Account {
+    // Get the minor (辅修) and double degree (双学位) applications and their approval, with the courses enrolled in for them.
+    // Everything is empty for students who never applied.
+    fn get_minor_status(&self) -> Result<MinorStatus> {
+        self.follow_cas(MINOR_INDEX_URL)?;
+        let json = self.send_and_get_json(self.get_client().get(self.resolve_url(MINOR_STATUS_URL)))?;
+        parse_minor_status(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdu::test_support::fixture;
+
+    #[test]
+    fn test_parse_minor_status() {
+        let status = parse_minor_status(&fixture("minor_status.json")).unwrap();
+        assert_eq!(status.applications, vec![
+            MinorApplication {
+                program: "经济学".to_string(),
+                kind: ProgramKind::Minor,
+                status: ApprovalStatus::Approved,
+                applied_on: NaiveDate::from_ymd_opt(2022, 3, 1),
+            },
+            MinorApplication {
+                program: "数学与应用数学".to_string(),
+                kind: ProgramKind::DoubleDegree,
+                status: ApprovalStatus::Pending,
+                applied_on: NaiveDate::from_ymd_opt(2023, 2, 20),
+            },
+            MinorApplication {
+                program: "哲学".to_string(),
+                kind: ProgramKind::Minor,
+                status: ApprovalStatus::Rejected,
+                applied_on: None,
+            },
+        ]);
+        assert_eq!(status.courses[1], MinorCourse { code: "ECON130064.01".to_string(), name: "博弈论".to_string(), credits: 2.5 });
+
+        parse_minor_status(r#"{"code":0,"data":{"sqxm":[{"xmmc":"经济学","lx":"第二专业"}]}}"#).expect_err("expect error");
+    }
+
+    #[test]
+    fn test_get_minor_status() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/minor/index", vec![MockResponse::ok("<html>辅修及双学位申请</html>")]);
+        server.route("/minor/api/student/applications", vec![MockResponse::json(&fixture("minor_status.json"))]);
+
+        let fd = logged_in_fdu(&server, &["https://fxsq.fudan.edu.cn"], &["/minor/index"]);
+        let status = fd.get_minor_status().unwrap();
+        assert_eq!(status.applications().len(), 3);
+        assert_eq!((status.applications()[0].program(), status.applications()[0].kind()), ("经济学", ProgramKind::Minor));
+        assert_eq!(status.courses()[0].credits(), 3.0);
+        assert!(server.request_lines().iter().any(|line| line.starts_with("GET /minor/index?ticket=ST-mock")));
+    }
+
+    #[test]
+    fn test_parse_no_application() {
+        assert_eq!(parse_minor_status(r#"{"code":0,"msg":"无申请记录","data":null}"#).unwrap(), MinorStatus::default());
+        assert_eq!(parse_minor_status(r#"{"code":0,"data":{"sqxm":[],"kc":null}}"#).unwrap(), MinorStatus::default());
+        parse_minor_status(r#"{"code":401,"msg":"未登录"}"#).expect_err("expect error");
+    }
+}
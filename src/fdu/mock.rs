@@ -0,0 +1,202 @@
+// A tiny HTTP server for tests, so that the request flows can be tested without credentials or network.
+//
+// Every connection serves exactly one request and is closed afterwards, which is enough for reqwest's blocking client.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::fdu::fdu::{Fdu, FduBuilder};
+
+const UIS_ORIGIN: &str = "https://uis.fudan.edu.cn";
+
+#[derive(Clone)]
+pub(crate) struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    // how long to wait before responding
+    delay: Duration,
+}
+
+impl MockResponse {
+    pub(crate) fn new(status: u16, body: &str) -> Self {
+        Self::bytes(status, body.as_bytes().to_vec())
+    }
+
+    pub(crate) fn ok(body: &str) -> Self {
+        Self::new(200, body)
+    }
+
+    pub(crate) fn bytes(status: u16, body: Vec<u8>) -> Self {
+        Self { status, headers: vec![("Content-Type".to_string(), "text/html;charset=UTF-8".to_string())], body, delay: Duration::ZERO }
+    }
+
+    pub(crate) fn json(body: &str) -> Self {
+        Self::ok(body).header("Content-Type", "application/json;charset=UTF-8")
+    }
+
+    // A 302 to `location`.
+    pub(crate) fn redirect(location: &str) -> Self {
+        Self::new(302, "").header("Location", location)
+    }
+
+    // Add a header, replacing the default Content-Type if `name` is Content-Type.
+    pub(crate) fn header(mut self, name: &str, value: &str) -> Self {
+        if name.eq_ignore_ascii_case("content-type") {
+            self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case("content-type"));
+        }
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // Wait for `delay` before responding, e.g. to make a request time out.
+    pub(crate) fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+#[derive(Default)]
+struct State {
+    // path -> responses to give in turn. The last one is repeated once the others are used up.
+    routes: HashMap<String, Vec<MockResponse>>,
+    // the raw requests received (head and body), in order
+    requests: Vec<String>,
+}
+
+pub(crate) struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+    pub(crate) fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+        let shared = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = Arc::clone(&shared);
+                thread::spawn(move || handle(stream, &state));
+            }
+        });
+        Self { addr, state }
+    }
+
+    // The url of `path` on this server, e.g. `url("/login")` gives `http://127.0.0.1:12345/login`.
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    // Respond `responses` in turn to the requests to `path` (query is ignored when matching).
+    // `path` may start with a method, e.g. `POST /login`, to only match requests of that method,
+    // which takes precedence over a route without method.
+    pub(crate) fn route(&self, path: &str, responses: Vec<MockResponse>) {
+        self.state.lock().unwrap().routes.insert(path.to_string(), responses);
+    }
+
+    pub(crate) fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    // The request lines received, e.g. `GET /login?service=xxx`.
+    pub(crate) fn request_lines(&self) -> Vec<String> {
+        self.requests().iter()
+            .map(|request| request.lines().next().unwrap_or_default().trim_end_matches(" HTTP/1.1").to_string())
+            .collect()
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<State>) {
+    let request = match read_request(&mut stream) {
+        Some(request) => request,
+        None => return,
+    };
+    let mut words = request.split_whitespace();
+    let method = words.next().unwrap_or("GET").to_string();
+    let path = words.next().unwrap_or("/").split('?').next().unwrap_or("/").to_string();
+
+    let response = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request);
+        let with_method = format!("{} {}", method, path);
+        let key = if state.routes.contains_key(&with_method) { with_method } else { path };
+        match state.routes.get_mut(&key) {
+            Some(responses) if responses.len() > 1 => responses.remove(0),
+            Some(responses) if responses.len() == 1 => responses[0].clone(),
+            _ => MockResponse::new(404, "not found"),
+        }
+    };
+
+    thread::sleep(response.delay);
+    let mut head = format!("HTTP/1.1 {} MOCK\r\n", response.status);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", response.body.len()));
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(&response.body);
+}
+
+// Read the head and, according to Content-Length, the body of a request.
+fn read_request(stream: &mut TcpStream) -> Option<String> {
+    let mut data = Vec::new();
+    let mut buf = [0; 4096];
+    loop {
+        let n = stream.read(&mut buf).ok()?;
+        if n == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(end) = find(&data, b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&data[..end]).to_lowercase();
+            let length = head.lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .unwrap_or_default();
+            if data.len() >= end + 4 + length {
+                return Some(String::from_utf8_lossy(&data).to_string());
+            }
+        }
+    }
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|window| window == needle)
+}
+
+// An `Fdu` sending the requests to UIS and to all the `origins` (e.g. "https://jwfw.fudan.edu.cn") to `server`.
+pub(crate) fn mock_fdu(server: &MockServer, origins: &[&str]) -> Fdu {
+    mock_fdu_builder(server, origins).build().unwrap()
+}
+
+// The builder of `mock_fdu`, to set more options before building.
+pub(crate) fn mock_fdu_builder(server: &MockServer, origins: &[&str]) -> FduBuilder {
+    let mut builder = Fdu::builder().endpoint(UIS_ORIGIN, &server.url(""));
+    for origin in origins {
+        builder = builder.endpoint(origin, &server.url(""));
+    }
+    builder
+}
+
+// Script a successful UIS login: the login page with its hidden tokens, the login POST redirecting to the success page,
+// and logout. Then every url in `services` is logged in through CAS in turn, i.e. the n-th `follow_cas` is redirected
+// back to `services[n]` with a ticket.
+pub(crate) fn script_uis_login(server: &MockServer, services: &[&str]) {
+    const LOGIN_PAGE: &str = r#"<form id="casLoginForm" method="post">
+        <input type="hidden" name="lt" value="LT-mock"/>
+        <input type="hidden" name="execution" value="e1s1"/>
+    </form>"#;
+    let mut login = vec![MockResponse::ok(LOGIN_PAGE)];
+    login.extend(services.iter().map(|service| MockResponse::redirect(&format!("{}?ticket=ST-mock", service))));
+    server.route("GET /authserver/login", login);
+    server.route("POST /authserver/login", vec![MockResponse::redirect(&server.url("/authserver/index.do"))
+        .header("Set-Cookie", "CASTGC=TGT-mock; Path=/authserver")]);
+    server.route("/authserver/index.do", vec![MockResponse::ok("<html>个人中心</html>")]);
+    server.route("/authserver/logout", vec![MockResponse::ok("<html>注销成功</html>")]);
+}
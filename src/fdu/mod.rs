@@ -1,4 +1,11 @@
 pub mod fdu;
+pub mod html;
+pub mod number;
+pub mod api;
+#[cfg(test)]
+pub mod mock;
+#[cfg(test)]
+pub mod test_support;
 pub mod fdu_daily;
 pub mod prelude;
 pub mod jwfw;
@@ -6,3 +13,19 @@ pub mod ecard;
 pub mod grade;
 pub mod myfdu;
 pub mod xk;
+pub mod bus;
+pub mod library;
+pub mod tuition;
+pub mod pe;
+pub mod onestop;
+pub mod student_id;
+pub mod electricity;
+pub mod dashboard;
+pub mod thesis;
+pub mod canteen;
+pub mod minor;
+pub mod campus;
+pub mod scholarship;
+pub mod dorm;
+pub mod leave;
+pub mod ids;
@@ -1,13 +1,21 @@
-use std::collections::HashMap;
-use std::panic::catch_unwind;
-
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use reqwest::Url;
 use scraper::{Html, Selector};
 
+use crate::error::*;
 use crate::fdu::fdu::{Account, Fdu};
+use crate::fdu::html::{element_text, select_one, CELL_SELECTOR};
+use crate::fdu::number::parse_number;
 
 const MYFDU_URL: &str = "https://my.fudan.edu.cn/";
 const COURSE_GRADE_URL: &str = "https://my.fudan.edu.cn/list/bks_xx_cj";
 
+static GRADE_ROW_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#dataTable_BksXxCj>tbody>tr").unwrap());
+static ANNOUNCEMENT_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("li").unwrap());
+static ANNOUNCEMENT_LINK_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("a[href]").unwrap());
+static ANNOUNCEMENT_TIME_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(".time").unwrap());
+
 #[derive(Debug)]
 pub struct GradeData {
     id: String,
@@ -18,31 +26,80 @@ pub struct GradeData {
     grade: String,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Announcement {
+    title: String,
+    date: NaiveDate,
+    // absolute url of the announcement, eg. https://my.fudan.edu.cn/notice/view?id=1024
+    link: String,
+}
+
 impl MyFduClient for Fdu {}
 
+// Parse the announcements on the homepage, which are listed like
+// <div id="portal_notice"><ul><li><a href="/notice/view?id=1024" title="...">...</a><span class="time">2022-10-08</span></li></ul></div>
+// The list has no item with a link when there is nothing to announce (only a "暂无公告" placeholder), which gives an empty vector.
+fn parse_announcements(html: &str) -> Result<Vec<Announcement>> {
+    let document = Html::parse_document(html);
+    let notice = select_one(&document, "#portal_notice")?;
+    let base = Url::parse(MYFDU_URL).unwrap();
+
+    let mut announcements = Vec::new();
+    for item in notice.select(&ANNOUNCEMENT_SELECTOR) {
+        let a = match item.select(&ANNOUNCEMENT_LINK_SELECTOR).next() {
+            Some(a) => a,
+            None => continue,
+        };
+        // long titles are cut short in the text, but kept in full in the title attribute
+        let title = a.value().attr("title")
+            .map(|title| title.to_string())
+            .unwrap_or_else(|| a.text().collect::<String>())
+            .trim().to_string();
+        let link = base.join(a.value().attr("href").unwrap())
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("invalid announcement link of {}", title)))?;
+        let date_text = item.select(&ANNOUNCEMENT_TIME_SELECTOR).next()
+            .map(|time| time.text().collect::<String>())
+            .unwrap_or_default();
+        let date = NaiveDate::parse_from_str(date_text.trim(), "%Y-%m-%d")
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse announcement date {} error", date_text.trim())))?;
+        announcements.push(Announcement { title, date, link: link.to_string() });
+    }
+    Ok(announcements)
+}
+
 pub trait MyFduClient: Account {
-    fn get_myfdu_course_grade(&self) -> reqwest::Result<Vec<GradeData>> {
+    fn get_myfdu_course_grade(&self) -> Result<Vec<GradeData>> {
         let client = self.get_client();
-        let html = client.get(COURSE_GRADE_URL).send()?.text()?;
+        let html = self.send_and_get_text(client.get(self.resolve_url(COURSE_GRADE_URL)))?;
         let document = Html::parse_document(html.as_str());
-        let selector = Selector::parse("#dataTable_BksXxCj>tbody>tr").unwrap();
         let mut grade_data: Vec<GradeData> = Vec::new();
-        for element in document.select(&selector) {
-            let sub_selector = Selector::parse("td").unwrap();
-            let mut sub_element = element.select(&sub_selector);
+        for element in document.select(&GRADE_ROW_SELECTOR) {
+            // id, academic year, semester, name, credits and grade
+            let cells: Vec<String> = element.select(&CELL_SELECTOR).map(element_text).collect();
+            if cells.len() < 6 {
+                return Err(SDKError::with_type(ErrorType::ParseError, format!("unexpected grade row with {} cells", cells.len())));
+            }
             let course_info: GradeData = GradeData {
-                id: sub_element.next().unwrap().inner_html(),
-                academic_year: sub_element.next().unwrap().inner_html(),
-                semester: sub_element.next().unwrap().inner_html(),
-                name: sub_element.next().unwrap().inner_html(),
-                credits: sub_element.next().unwrap().inner_html().parse().unwrap(),
-                grade: sub_element.next().unwrap().inner_html(),
+                credits: parse_number(&cells[4])
+                    .ok_or_else(|| SDKError::with_type(ErrorType::ParseError, format!("parse credits {} of {} error", cells[4], cells[3])))?,
+                id: cells[0].clone(),
+                academic_year: cells[1].clone(),
+                semester: cells[2].clone(),
+                name: cells[3].clone(),
+                grade: cells[5].clone(),
             };
             // println!("{:?}", course_info);
             grade_data.push(course_info);
         }
         Ok(grade_data)
     }
+
+    // Get the announcements shown on the my.fudan.edu.cn homepage, newest first as the page lists them.
+    // The homepage is public, so this works without `login`.
+    fn get_announcements(&self) -> Result<Vec<Announcement>> {
+        let html = self.send_and_get_html(self.get_client().get(self.resolve_url(MYFDU_URL)))?;
+        parse_announcements(&html)
+    }
 }
 
 
@@ -61,4 +118,70 @@ mod tests {
         fd.get_myfdu_course_grade().expect("my fdu error");
         fd.logout().expect("logout error");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_myfdu_course_grade() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        const GRADES: &str = r#"<table id="dataTable_BksXxCj"><tbody>
+            <tr><td>COMP130004.03</td><td>2022-2023</td><td>1</td><td>数据结构</td><td>3</td><td>A</td></tr>
+        </tbody></table>"#;
+        let server = MockServer::start();
+        server.route("/list/bks_xx_cj", vec![
+            MockResponse::ok(GRADES),
+            MockResponse::ok(&GRADES.replace("<td>3</td>", "<td>-</td>")),
+            // a row cut short
+            MockResponse::ok(&GRADES.replace("<td>3</td><td>A</td>", "")),
+        ]);
+
+        let fd = mock_fdu(&server, &["https://my.fudan.edu.cn"]);
+        let grades = fd.get_myfdu_course_grade().expect("my fdu error");
+        assert_eq!((grades[0].name.as_str(), grades[0].credits, grades[0].grade.as_str()), ("数据结构", 3.0, "A"));
+        // the errors are `SDKError` now, a bad page does not panic
+        fd.get_myfdu_course_grade().expect_err("expect error");
+        assert!(fd.get_myfdu_course_grade().expect_err("expect error").to_string().contains("4 cells"));
+    }
+
+    #[test]
+    fn test_announcements_anonymously() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/", vec![MockResponse::ok(r#"<div id="portal_notice"><ul>
+            <li><a href="/notice/view?id=1024">校园网维护公告</a><span class="time">2022-11-20</span></li>
+        </ul></div>"#)]);
+
+        // never logged in
+        let fd = mock_fdu(&server, &["https://my.fudan.edu.cn"]);
+        assert_eq!(fd.get_announcements().expect("announcements error")[0].title, "校园网维护公告");
+        // nothing went to UIS, and no cookie was needed
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(!requests[0].to_lowercase().contains("cookie:"));
+    }
+
+    #[test]
+    fn test_parse_announcements() {
+        const HOMEPAGE: &str = r#"<html><body>
+            <div id="portal_notice"><ul>
+                <li><a href="/notice/view?id=1024" title="关于2022-2023学年第一学期期末考试安排的通知">关于2022-2023学年第一学期期末考试...</a><span class="time">2022-12-01</span></li>
+                <li><a href="https://www.fudan.edu.cn/2022/1120/c1a2.htm">校园网维护公告</a><span class="time"> 2022-11-20 </span></li>
+            </ul></div>
+        </body></html>"#;
+        let announcements = parse_announcements(HOMEPAGE).unwrap();
+        assert_eq!(announcements.len(), 2);
+        assert_eq!(announcements[0], Announcement {
+            title: "关于2022-2023学年第一学期期末考试安排的通知".to_string(),
+            date: NaiveDate::from_ymd_opt(2022, 12, 1).unwrap(),
+            link: "https://my.fudan.edu.cn/notice/view?id=1024".to_string(),
+        });
+        assert_eq!(announcements[1].title, "校园网维护公告");
+        assert_eq!(announcements[1].link, "https://www.fudan.edu.cn/2022/1120/c1a2.htm");
+
+        const EMPTY: &str = r#"<div id="portal_notice"><ul><li>暂无公告</li></ul></div>"#;
+        assert!(parse_announcements(EMPTY).unwrap().is_empty());
+        assert!(parse_announcements(r#"<div id="portal_notice"></div>"#).unwrap().is_empty());
+
+        parse_announcements("<html>统一身份认证</html>").expect_err("expect error");
+    }
+}
@@ -0,0 +1,55 @@
+use std::str::FromStr;
+
+// Parse a number as the pages show it, which is not always what `str::parse` takes: with a unit after it
+// (3.0学分, 54 学时), in full-width digits (３.０, typed in with a Chinese IME), or with spaces around it.
+// The unit is anything after the number without a digit in it, so 3学分2 is not a number, and neither is 1 2. Any type
+// `str::parse` can give will do, eg. f64 for credits or i32 for hours.
+pub(crate) fn parse_number<T: FromStr>(text: &str) -> Option<T> {
+    let normalized = normalize_number(text);
+    let end = normalized.char_indices()
+        .find(|(i, c)| !(c.is_ascii_digit() || *c == '.' || (*i == 0 && matches!(c, '-' | '+'))))
+        .map_or(normalized.len(), |(i, _)| i);
+    let (number, unit) = normalized.split_at(end);
+    if number.is_empty() || unit.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    number.parse().ok()
+}
+
+// Turn the full-width digits, point and signs into half-width ones, and trim the whitespace around, the full-width space included.
+fn normalize_number(text: &str) -> String {
+    text.trim().chars()
+        .map(|c| match c {
+            '０'..='９' => char::from_u32(c as u32 - '０' as u32 + '0' as u32).unwrap(),
+            '．' | '。' => '.',
+            '－' => '-',
+            '＋' => '+',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number::<f64>("3.0"), Some(3.0));
+        assert_eq!(parse_number::<f64>("3.0学分"), Some(3.0));
+        assert_eq!(parse_number::<f64>(" 3.0 学分 "), Some(3.0));
+        assert_eq!(parse_number::<f64>("３"), Some(3.0));
+        assert_eq!(parse_number::<f64>("２．５"), Some(2.5));
+        assert_eq!(parse_number::<f32>("\u{3000}15\u{3000}学分"), Some(15.0));
+        assert_eq!(parse_number::<i32>("54学时"), Some(54));
+        assert_eq!(parse_number::<f64>("-1.5"), Some(-1.5));
+
+        assert_eq!(parse_number::<f64>(""), None);
+        assert_eq!(parse_number::<f64>("学分"), None);
+        assert_eq!(parse_number::<f64>("3学分2"), None);
+        assert_eq!(parse_number::<f64>("3.0.1"), None);
+        assert_eq!(parse_number::<i32>("1 2"), None);
+        assert_eq!(parse_number::<f64>("- 1.5"), None);
+        assert_eq!(parse_number::<i32>("3.5"), None);
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use super::prelude::*;
+
+impl OnestopClient for Fdu {}
+
+const ONESTOP_INDEX_URL: &str = "https://ehall.fudan.edu.cn/portal/index";
+const ONESTOP_TODO_URL: &str = "https://ehall.fudan.edu.cn/portal/api/todo/list";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TodoItem {
+    // eg. 2022年秋季学期返校申请
+    title: String,
+    // eg. 审批, 表单填报
+    category: String,
+    // None if the item has no deadline
+    deadline: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+struct TodoResponse {
+    // "0" means ok
+    code: String,
+    #[serde(default)]
+    message: String,
+    data: Option<TodoData>,
+}
+
+#[derive(Deserialize)]
+struct TodoData {
+    #[serde(default)]
+    rows: Vec<TodoRow>,
+}
+
+// A pending item, eg. {"title":"2022年秋季学期返校申请","type":"审批","deadline":"2022-08-25 23:59"}
+// The deadline is an empty string or missing when there is none.
+#[derive(Deserialize)]
+struct TodoRow {
+    title: String,
+    #[serde(default, rename = "type")]
+    category: String,
+    #[serde(default)]
+    deadline: String,
+}
+
+// Parse the todo list. `rows` is empty when there is nothing to do.
+fn parse_todo_list(json: &str) -> Result<Vec<TodoItem>> {
+    let response: TodoResponse = serde_json::from_str(json)?;
+    if response.code != "0" {
+        return Err(SDKError::with_type(ErrorType::OtherError, format!("get todo list error: {}", response.message)));
+    }
+
+    let rows = response.data.map(|data| data.rows).unwrap_or_default();
+    rows.into_iter().map(|row| {
+        let deadline = match row.deadline.trim() {
+            "" => None,
+            deadline => Some(NaiveDateTime::parse_from_str(deadline, "%Y-%m-%d %H:%M")
+                .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse todo deadline {} error", deadline)))?),
+        };
+        Ok(TodoItem { title: row.title, category: row.category, deadline })
+    }).collect()
+}
+
+pub trait OnestopClient: Account {
+    // Get the pending items (approvals, forms to fill in, ...) on the onestop (一网通办) portal.
+    fn get_todo_list(&self) -> Result<Vec<TodoItem>> {
+        self.follow_cas(ONESTOP_INDEX_URL)?;
+        let json = self.send_and_get_json(self.get_client().get(self.resolve_url(ONESTOP_TODO_URL)))?;
+        parse_todo_list(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_todo_list() {
+        const TODO: &str = r#"{"code":"0","message":"success","data":{"total":2,"rows":[
+            {"id":"1","title":"2022年秋季学期返校申请","type":"审批","deadline":"2022-08-25 23:59"},
+            {"id":"2","title":"本科生学籍信息核对","type":"表单填报","deadline":""}
+        ]}}"#;
+        let items = parse_todo_list(TODO).unwrap();
+        assert_eq!(items, vec![
+            TodoItem {
+                title: "2022年秋季学期返校申请".to_string(),
+                category: "审批".to_string(),
+                deadline: Some(NaiveDate::from_ymd_opt(2022, 8, 25).unwrap().and_hms_opt(23, 59, 0).unwrap()),
+            },
+            TodoItem {
+                title: "本科生学籍信息核对".to_string(),
+                category: "表单填报".to_string(),
+                deadline: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_empty_todo_list() {
+        assert!(parse_todo_list(r#"{"code":"0","data":{"total":0,"rows":[]}}"#).unwrap().is_empty());
+        assert!(parse_todo_list(r#"{"code":"0","data":null}"#).unwrap().is_empty());
+        parse_todo_list(r#"{"code":"401","message":"未登录"}"#).expect_err("expect error");
+    }
+}
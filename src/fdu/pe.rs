@@ -0,0 +1,225 @@
+use scraper::Html;
+use serde::Deserialize;
+
+use super::api::parse_api_data;
+use super::html::{element_text, CELL_SELECTOR, FITNESS_TEST_ROW_SELECTOR, PE_SUMMARY_ROW_SELECTOR};
+use super::prelude::*;
+
+impl PeClient for Fdu {}
+
+const PE_INDEX_URL: &str = "https://fdtyb.fudan.edu.cn/sport/index";
+const PE_SUMMARY_URL: &str = "https://fdtyb.fudan.edu.cn/sport/student/summary";
+// the runs recorded by the running app (阳光长跑) this term
+const PE_RUNNING_URL: &str = "https://fdtyb.fudan.edu.cn/sport/api/student/running";
+
+// Shown instead of the summary when the student takes no PE course this term.
+const NOT_ENROLLED_MARKER: &str = "未选体育课";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FitnessTest {
+    // eg. 50米跑, 立定跳远
+    item: String,
+    // the measured result as shown, eg. 7.2, 2'05"
+    result: String,
+    // the score for the item, eg. 80
+    score: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeSummary {
+    // eg. 篮球(PEDU110018.01)
+    course: String,
+    // times present in PE classes
+    attendance: i32,
+    // times of extracurricular exercise (跑步) recorded
+    running: i32,
+    // results of the physical fitness test (体测), empty if not tested yet
+    tests: Vec<FitnessTest>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunningRecord {
+    // runs counted toward the requirement, ie. long and fast enough and in the allowed time
+    valid: i32,
+    // runs recorded in total, including the invalid ones
+    total: i32,
+    // runs required this term, eg. 20
+    required: i32,
+}
+
+impl RunningRecord {
+    pub fn valid(&self) -> i32 {
+        self.valid
+    }
+
+    pub fn total(&self) -> i32 {
+        self.total
+    }
+
+    pub fn required(&self) -> i32 {
+        self.required
+    }
+
+    // The runs still to go, 0 once the requirement is met.
+    pub fn remaining(&self) -> i32 {
+        (self.required - self.valid).max(0)
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.valid >= self.required
+    }
+}
+
+// eg. {"yxcs":12,"zcs":15,"yqcs":20,"sfxp":"1"} for 有效次数, 总次数, 要求次数 and 是否需跑.
+// sfxp is "0" for students exempted from running, eg. graduates or those excused for health, who have no requirement.
+#[derive(Deserialize)]
+struct RunningData {
+    #[serde(default)]
+    yxcs: i32,
+    #[serde(default)]
+    zcs: i32,
+    #[serde(default)]
+    yqcs: i32,
+    #[serde(default)]
+    sfxp: String,
+}
+
+// Parse the running record. None if the student is not subject to the requirement.
+fn parse_running_record(json: &str) -> Result<Option<RunningRecord>> {
+    match parse_api_data::<RunningData>(json, "get running record")? {
+        Some(data) if data.sfxp.trim() != "0" && data.yqcs > 0 => Ok(Some(RunningRecord {
+            valid: data.yxcs,
+            total: data.zcs,
+            required: data.yqcs,
+        })),
+        _ => Ok(None),
+    }
+}
+
+// Parse the summary page, which is like
+// <table class="summary"><tr><td>体育课程</td><td>篮球(PEDU110018.01)</td></tr><tr><td>上课出勤</td><td>12</td></tr>...</table>
+// <table id="tzcs"><tr><th>项目</th><th>成绩</th><th>得分</th></tr><tr><td>50米跑</td><td>7.2</td><td>80</td></tr></table>
+// The summary rows are found by their labels. None if the student is not enrolled in any PE course.
+fn parse_pe_summary(html: &str) -> Result<Option<PeSummary>> {
+    if html.contains(NOT_ENROLLED_MARKER) {
+        return Ok(None);
+    }
+
+    let document = Html::parse_document(html);
+    let rows: Vec<Vec<String>> = document.select(&PE_SUMMARY_ROW_SELECTOR)
+        .map(|tr| tr.select(&CELL_SELECTOR).map(element_text).collect())
+        .collect();
+    let field = |label: &str| rows.iter()
+        .find(|cells| cells.len() >= 2 && cells[0] == label)
+        .map(|cells| cells[1].clone())
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("{} not found in pe summary", label)));
+    let count = |label: &str| field(label)?.parse::<i32>()
+        .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse {} in pe summary error", label)));
+
+    let mut tests = Vec::new();
+    for tr in document.select(&FITNESS_TEST_ROW_SELECTOR) {
+        let cells: Vec<String> = tr.select(&CELL_SELECTOR).map(element_text).collect();
+        // the header row
+        if cells.len() < 3 {
+            continue;
+        }
+        let score = cells[2].parse()
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse score of {} error", cells[0])))?;
+        tests.push(FitnessTest { item: cells[0].clone(), result: cells[1].clone(), score });
+    }
+
+    Ok(Some(PeSummary {
+        course: field("体育课程")?,
+        attendance: count("上课出勤")?,
+        running: count("课外锻炼")?,
+        tests,
+    }))
+}
+
+pub trait PeClient: Account {
+    // Get the PE attendance, running counts and fitness test results of the current term.
+    // None if the student takes no PE course.
+    fn get_pe_summary(&self) -> Result<Option<PeSummary>> {
+        self.follow_cas(PE_INDEX_URL)?;
+        let html = self.send_and_get_html(self.get_client().get(self.resolve_url(PE_SUMMARY_URL)))?;
+        parse_pe_summary(&html)
+    }
+
+    // Get the count of valid runs completed vs required this term, from the running app.
+    // None if the student is not required to run.
+    fn get_running_record(&self) -> Result<Option<RunningRecord>> {
+        self.follow_cas(PE_INDEX_URL)?;
+        let json = self.send_and_get_json(self.get_client().get(self.resolve_url(PE_RUNNING_URL)))?;
+        parse_running_record(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pe_summary() {
+        const SUMMARY: &str = r#"<html><body>
+            <table class="summary">
+                <tr><td>体育课程</td><td>篮球(PEDU110018.01)</td></tr>
+                <tr><td>上课出勤</td><td> 12 </td></tr>
+                <tr><td>课外锻炼</td><td>8</td></tr>
+            </table>
+            <table id="tzcs">
+                <tr><th>项目</th><th>成绩</th><th>得分</th></tr>
+                <tr><td>50米跑</td><td>7.2</td><td>80</td></tr>
+                <tr><td>1000米跑</td><td>3'45"</td><td>72.5</td></tr>
+            </table>
+        </body></html>"#;
+        let summary = parse_pe_summary(SUMMARY).unwrap().unwrap();
+        assert_eq!(summary.course, "篮球(PEDU110018.01)");
+        assert_eq!((summary.attendance, summary.running), (12, 8));
+        assert_eq!(summary.tests.len(), 2);
+        assert_eq!(summary.tests[1], FitnessTest { item: "1000米跑".to_string(), result: "3'45\"".to_string(), score: 72.5 });
+
+        // not tested yet
+        const UNTESTED: &str = r#"<table class="summary"><tr><td>体育课程</td><td>游泳</td></tr><tr><td>上课出勤</td><td>0</td></tr><tr><td>课外锻炼</td><td>0</td></tr></table>"#;
+        assert!(parse_pe_summary(UNTESTED).unwrap().unwrap().tests.is_empty());
+    }
+
+    #[test]
+    fn test_parse_not_enrolled() {
+        assert_eq!(parse_pe_summary("<html><div class=\"tip\">本学期未选体育课</div></html>").unwrap(), None);
+        parse_pe_summary("<html>统一身份认证</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_parse_running_record() {
+        use crate::fdu::test_support::fixture;
+
+        let record = parse_running_record(&fixture("pe_running.json")).unwrap().unwrap();
+        assert_eq!(record, RunningRecord { valid: 12, total: 15, required: 20 });
+        assert_eq!(record.remaining(), 8);
+        assert!(!record.is_completed());
+
+        let record = parse_running_record(r#"{"code":0,"data":{"yxcs":23,"zcs":23,"yqcs":20,"sfxp":"1"}}"#).unwrap().unwrap();
+        assert_eq!((record.remaining(), record.is_completed()), (0, true));
+    }
+
+    #[test]
+    fn test_parse_running_exempted() {
+        assert_eq!(parse_running_record(r#"{"code":0,"data":{"yxcs":0,"zcs":0,"yqcs":20,"sfxp":"0"}}"#).unwrap(), None);
+        assert_eq!(parse_running_record(r#"{"code":0,"msg":"无需参加","data":null}"#).unwrap(), None);
+        parse_running_record(r#"{"code":401,"msg":"未登录"}"#).expect_err("expect error");
+    }
+
+    #[test]
+    fn test_get_running_record() {
+        use crate::fdu::test_support::{fixture, logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/sport/index", vec![MockResponse::ok("<html>体育教学管理</html>")]);
+        server.route("/sport/api/student/running", vec![MockResponse::json(&fixture("pe_running.json"))]);
+
+        let fd = logged_in_fdu(&server, &["https://fdtyb.fudan.edu.cn"], &["/sport/index"]);
+        let record = fd.get_running_record().unwrap().unwrap();
+        assert_eq!((record.valid(), record.total(), record.required()), (12, 15, 20));
+        assert!(server.request_lines().iter().any(|line| line.starts_with("GET /sport/index?ticket=ST-mock")));
+    }
+}
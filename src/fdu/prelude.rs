@@ -1,7 +1,4 @@
 pub use super::fdu_daily;
 pub use super::fdu::*;
-pub use super::jwfw;
-pub use super::ecard;
-pub use super::grade;
-pub use super::myfdu;
+pub use super::ids;
 pub use crate::error::*;
\ No newline at end of file
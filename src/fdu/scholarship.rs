@@ -0,0 +1,160 @@
+use serde::Deserialize;
+
+use super::api::parse_api_data;
+use super::prelude::*;
+
+impl ScholarshipClient for Fdu {}
+
+// the scholarship (奖学金) evaluation of the student affairs system
+const SCHOLARSHIP_INDEX_URL: &str = "https://xsgl.fudan.edu.cn/scholarship/index";
+const SCHOLARSHIP_RECORDS_URL: &str = "https://xsgl.fudan.edu.cn/scholarship/api/student/records";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScholarshipStatus {
+    // 已申请 or 审核中, still being evaluated
+    Applied,
+    // 已获奖 or 已评定
+    Awarded,
+    // 未获奖 or 未通过
+    NotAwarded,
+    // a status we do not know of, as shown
+    Other(String),
+}
+
+impl ScholarshipStatus {
+    fn parse(text: &str) -> Self {
+        match text.trim() {
+            "已申请" | "审核中" => ScholarshipStatus::Applied,
+            "已获奖" | "已评定" => ScholarshipStatus::Awarded,
+            "未获奖" | "未通过" => ScholarshipStatus::NotAwarded,
+            other => ScholarshipStatus::Other(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scholarship {
+    // eg. 国家奖学金, 复旦大学优秀学生奖学金一等奖
+    name: String,
+    // the academic year evaluated, eg. 2021-2022
+    year: String,
+    // in yuan, None until awarded or if the system does not say
+    amount: Option<f64>,
+    status: ScholarshipStatus,
+}
+
+impl Scholarship {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn year(&self) -> &str {
+        &self.year
+    }
+
+    pub fn amount(&self) -> Option<f64> {
+        self.amount
+    }
+
+    pub fn status(&self) -> &ScholarshipStatus {
+        &self.status
+    }
+}
+
+// eg. {"jxjmc":"国家奖学金","pdxn":"2021-2022","je":"8000","zt":"已获奖"} for 奖学金名称, 评定学年, 金额 and 状态.
+// The amount is a string, and empty when not decided yet.
+#[derive(Deserialize)]
+struct ScholarshipData {
+    jxjmc: String,
+    #[serde(default)]
+    pdxn: String,
+    #[serde(default)]
+    je: String,
+    #[serde(default)]
+    zt: String,
+}
+
+// Parse the records. `data` is empty or null for students who never applied for or got any scholarship.
+fn parse_scholarships(json: &str) -> Result<Vec<Scholarship>> {
+    let records: Vec<ScholarshipData> = parse_api_data(json, "get scholarships")?.unwrap_or_default();
+
+    records.into_iter().map(|scholarship| {
+        let amount = match scholarship.je.trim() {
+            "" => None,
+            amount => Some(amount.parse()
+                .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse amount {} of {} error", amount, scholarship.jxjmc)))?),
+        };
+        Ok(Scholarship {
+            status: ScholarshipStatus::parse(&scholarship.zt),
+            name: scholarship.jxjmc,
+            year: scholarship.pdxn,
+            amount,
+        })
+    }).collect()
+}
+
+pub trait ScholarshipClient: Account {
+    // Get the scholarships (奖学金) the student applied for or was awarded, with their status, as published by the
+    // evaluation (评奖). Empty if there is no record.
+    fn get_scholarships(&self) -> Result<Vec<Scholarship>> {
+        self.follow_cas(SCHOLARSHIP_INDEX_URL)?;
+        let json = self.send_and_get_json(self.get_client().get(self.resolve_url(SCHOLARSHIP_RECORDS_URL)))?;
+        parse_scholarships(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdu::test_support::fixture;
+
+    #[test]
+    fn test_parse_scholarships() {
+        let scholarships = parse_scholarships(&fixture("scholarship_records.json")).unwrap();
+        assert_eq!(scholarships, vec![
+            Scholarship {
+                name: "国家奖学金".to_string(),
+                year: "2021-2022".to_string(),
+                amount: Some(8000.0),
+                status: ScholarshipStatus::Awarded,
+            },
+            Scholarship {
+                name: "复旦大学优秀学生奖学金一等奖".to_string(),
+                year: "2022-2023".to_string(),
+                amount: None,
+                status: ScholarshipStatus::Applied,
+            },
+            Scholarship {
+                name: "专项奖学金".to_string(),
+                year: "2022-2023".to_string(),
+                amount: None,
+                status: ScholarshipStatus::Other("已撤销".to_string()),
+            },
+        ]);
+
+        parse_scholarships(r#"{"code":0,"data":[{"jxjmc":"国家奖学金","je":"八千"}]}"#).expect_err("expect error");
+    }
+
+    #[test]
+    fn test_get_scholarships() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/scholarship/index", vec![MockResponse::ok("<html>奖学金评定</html>")]);
+        server.route("/scholarship/api/student/records", vec![MockResponse::json(&fixture("scholarship_records.json"))]);
+
+        let fd = logged_in_fdu(&server, &["https://xsgl.fudan.edu.cn"], &["/scholarship/index"]);
+        let scholarships = fd.get_scholarships().unwrap();
+        assert_eq!(scholarships.len(), 3);
+        assert_eq!((scholarships[0].name(), scholarships[0].amount()), ("国家奖学金", Some(8000.0)));
+        assert_eq!(scholarships[1].status(), &ScholarshipStatus::Applied);
+        assert!(server.request_lines().iter().any(|line| line.starts_with("GET /scholarship/index?ticket=ST-mock")));
+    }
+
+    #[test]
+    fn test_parse_no_scholarship() {
+        assert!(parse_scholarships(r#"{"code":0,"msg":"暂无记录","data":[]}"#).unwrap().is_empty());
+        assert!(parse_scholarships(r#"{"code":0,"msg":"暂无记录","data":null}"#).unwrap().is_empty());
+        parse_scholarships(r#"{"code":401,"msg":"未登录"}"#).expect_err("expect error");
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::{Local, NaiveDate};
+use scraper::{ElementRef, Html};
+
+use super::html::{CARD_ITEM_SELECTOR, CARD_LABEL_SELECTOR, CARD_VALUE_SELECTOR};
+use super::prelude::*;
+
+impl StudentIdClient for Fdu {}
+
+const STUDENT_ID_URL: &str = "https://zlapp.fudan.edu.cn/site/student/card";
+
+// The electronic student ID card (学生证), not to be mixed up with the student number on it, see `ids::StudentId`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StudentCard {
+    name: String,
+    // eg. 20300180001
+    student_number: ids::StudentId,
+    enrollment_date: NaiveDate,
+    expiry_date: NaiveDate,
+    // the status as shown, eg. 有效, 已过期, 已注销
+    status: String,
+}
+
+impl StudentCard {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn student_number(&self) -> &ids::StudentId {
+        &self.student_number
+    }
+
+    pub fn enrollment_date(&self) -> NaiveDate {
+        self.enrollment_date
+    }
+
+    pub fn expiry_date(&self) -> NaiveDate {
+        self.expiry_date
+    }
+
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    // Whether the card is valid on `date`: the portal says so, and it has not expired by then.
+    // The status lags behind when the card expires, so the expiry date is checked as well.
+    pub fn is_valid_on(&self, date: NaiveDate) -> bool {
+        self.status == "有效" && date <= self.expiry_date
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.is_valid_on(Local::now().date_naive())
+    }
+}
+
+// Parse the card page, whose fields are listed like
+// <div class="card-item"><span class="label">姓名</span><span class="value">张三</span></div>
+// and are found by their labels (姓名, 学号, 入学日期, 有效期至, 状态).
+fn parse_student_card(html: &str) -> Result<StudentCard> {
+    let document = Html::parse_document(html);
+    let text = |element: ElementRef| element.text().collect::<String>().trim().to_string();
+
+    let fields: Vec<(String, String)> = document.select(&CARD_ITEM_SELECTOR)
+        .filter_map(|item| Some((text(item.select(&CARD_LABEL_SELECTOR).next()?), text(item.select(&CARD_VALUE_SELECTOR).next()?))))
+        .collect();
+    let field = |label: &str| fields.iter()
+        .find(|(l, _)| l == label)
+        .map(|(_, value)| value.clone())
+        .ok_or(SDKError::with_type(ErrorType::ParseError, format!("{} not found on student id card", label)));
+    let date = |label: &str| {
+        let value = field(label)?;
+        NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse {} {} error", label, value)))
+    };
+
+    Ok(StudentCard {
+        name: field("姓名")?,
+        student_number: ids::StudentId::parse(&field("学号")?)?,
+        enrollment_date: date("入学日期")?,
+        expiry_date: date("有效期至")?,
+        status: field("状态")?,
+    })
+}
+
+pub trait StudentIdClient: Account {
+    // Get the electronic student ID card. Expired cards are returned as well, see `StudentCard::is_valid`.
+    fn get_student_card(&self) -> Result<StudentCard> {
+        let html = self.send_and_get_html(self.get_client().get(self.resolve_url(STUDENT_ID_URL)))?;
+        parse_student_card(&html)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARD: &str = r#"<html><body><div class="card">
+        <div class="card-item"><span class="label">姓名</span><span class="value">张三</span></div>
+        <div class="card-item"><span class="label">学号</span><span class="value">20300180001</span></div>
+        <div class="card-item"><span class="label">入学日期</span><span class="value">2020-09-01</span></div>
+        <div class="card-item"><span class="label">有效期至</span><span class="value">2024-07-31</span></div>
+        <div class="card-item"><span class="label">状态</span><span class="value"> 有效 </span></div>
+    </div></body></html>"#;
+
+    #[test]
+    fn test_parse_student_card() {
+        let card = parse_student_card(CARD).unwrap();
+        assert_eq!(card, StudentCard {
+            name: "张三".to_string(),
+            student_number: ids::StudentId::parse("20300180001").unwrap(),
+            enrollment_date: NaiveDate::from_ymd_opt(2020, 9, 1).unwrap(),
+            expiry_date: NaiveDate::from_ymd_opt(2024, 7, 31).unwrap(),
+            status: "有效".to_string(),
+        });
+        assert!(card.is_valid_on(NaiveDate::from_ymd_opt(2022, 10, 1).unwrap()));
+        assert_eq!((card.name(), card.student_number().enrollment_year()), ("张三", 2020));
+        assert_eq!(card.enrollment_date(), NaiveDate::from_ymd_opt(2020, 9, 1).unwrap());
+
+        parse_student_card("<html>统一身份认证</html>").expect_err("expect error");
+        parse_student_card(&CARD.replace("20300180001", "2030018")).expect_err("expect error");
+    }
+
+    #[test]
+    fn test_expired_student_card() {
+        // past the expiry date, although the status is not updated yet
+        let card = parse_student_card(CARD).unwrap();
+        assert!(!card.is_valid_on(NaiveDate::from_ymd_opt(2024, 8, 1).unwrap()));
+
+        let card = parse_student_card(&CARD.replace(" 有效 ", "已过期")).unwrap();
+        assert_eq!(card.status(), "已过期");
+        assert!(!card.is_valid_on(NaiveDate::from_ymd_opt(2022, 10, 1).unwrap()));
+    }
+}
@@ -0,0 +1,28 @@
+// Shared helpers for the tests that need a logged-in client, built on the mock server in `mock`,
+// and a loader for the pages checked in under tests/fixtures.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::fdu::fdu::{Account, Fdu};
+use crate::fdu::mock::{mock_fdu_builder, script_uis_login};
+pub(crate) use crate::fdu::mock::{MockResponse, MockServer};
+
+// A `mock_fdu` logged in to UIS on `server`, with no delay between requests.
+// CAS redirects back to the paths in `services` in turn, see `script_uis_login`.
+pub(crate) fn logged_in_fdu(server: &MockServer, origins: &[&str], services: &[&str]) -> Fdu {
+    let services: Vec<String> = services.iter().map(|path| server.url(path)).collect();
+    script_uis_login(server, &services.iter().map(String::as_str).collect::<Vec<_>>());
+
+    let mut fd = mock_fdu_builder(server, origins).request_delay(&server.url(""), Duration::ZERO).build().unwrap();
+    fd.login("uid", "pwd").expect("mock login error");
+    fd
+}
+
+// The fixture `name` under tests/fixtures, e.g. "jwfw_course_table.html".
+pub(crate) fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("read fixture {} error: {}", path.display(), e))
+}
+
@@ -0,0 +1,114 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::api::parse_api_data;
+use super::prelude::*;
+
+impl ThesisClient for Fdu {}
+
+const THESIS_INDEX_URL: &str = "https://bysj.fudan.edu.cn/thesis/index";
+const THESIS_STATUS_URL: &str = "https://bysj.fudan.edu.cn/thesis/api/student/status";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThesisDeadline {
+    // eg. 开题报告, 中期检查, 论文定稿
+    name: String,
+    date: NaiveDate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThesisStatus {
+    // None until a topic is chosen
+    topic: Option<String>,
+    // None until an advisor is assigned
+    advisor: Option<String>,
+    // the current stage as shown, eg. 选题, 开题, 中期, 答辩
+    stage: String,
+    deadlines: Vec<ThesisDeadline>,
+}
+
+// eg. {"tmmc":"基于深度学习的代码补全","zdjs":"张三","jd":"开题","jdsj":[{"mc":"开题报告","jzrq":"2023-03-01"}]}
+// The topic (题目名称) and advisor (指导教师) are empty strings before they are decided.
+#[derive(Deserialize)]
+struct ThesisData {
+    #[serde(default)]
+    tmmc: String,
+    #[serde(default)]
+    zdjs: String,
+    #[serde(default)]
+    jd: String,
+    #[serde(default)]
+    jdsj: Vec<DeadlineData>,
+}
+
+#[derive(Deserialize)]
+struct DeadlineData {
+    mc: String,
+    jzrq: String,
+}
+
+fn non_empty(text: String) -> Option<String> {
+    let text = text.trim();
+    if text.is_empty() { None } else { Some(text.to_string()) }
+}
+
+// Parse the status. `data` is null for students without a thesis record, e.g. not in their final year yet, which gives None.
+fn parse_thesis_status(json: &str) -> Result<Option<ThesisStatus>> {
+    let data = match parse_api_data::<ThesisData>(json, "get thesis status")? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+
+    let deadlines = data.jdsj.into_iter().map(|deadline| {
+        let date = NaiveDate::parse_from_str(deadline.jzrq.trim(), "%Y-%m-%d")
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse deadline {} of {} error", deadline.jzrq, deadline.mc)))?;
+        Ok(ThesisDeadline { name: deadline.mc, date })
+    }).collect::<Result<Vec<_>>>()?;
+    Ok(Some(ThesisStatus {
+        topic: non_empty(data.tmmc),
+        advisor: non_empty(data.zdjs),
+        stage: data.jd,
+        deadlines,
+    }))
+}
+
+pub trait ThesisClient: Account {
+    // Get the topic selection and advisor assignment status of the graduation thesis (毕业论文).
+    // None if the student has no thesis record.
+    fn get_thesis_status(&self) -> Result<Option<ThesisStatus>> {
+        self.follow_cas(THESIS_INDEX_URL)?;
+        let json = self.send_and_get_json(self.get_client().get(self.resolve_url(THESIS_STATUS_URL)))?;
+        parse_thesis_status(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_thesis_status() {
+        const STATUS: &str = r#"{"code":0,"msg":"success","data":{"tmmc":"基于深度学习的代码补全","zdjs":"张三","jd":"开题",
+            "jdsj":[{"mc":"开题报告","jzrq":"2023-03-01"},{"mc":"中期检查","jzrq":"2023-04-15"}]}}"#;
+        let status = parse_thesis_status(STATUS).unwrap().unwrap();
+        assert_eq!(status, ThesisStatus {
+            topic: Some("基于深度学习的代码补全".to_string()),
+            advisor: Some("张三".to_string()),
+            stage: "开题".to_string(),
+            deadlines: vec![
+                ThesisDeadline { name: "开题报告".to_string(), date: NaiveDate::from_ymd_opt(2023, 3, 1).unwrap() },
+                ThesisDeadline { name: "中期检查".to_string(), date: NaiveDate::from_ymd_opt(2023, 4, 15).unwrap() },
+            ],
+        });
+
+        // still choosing a topic
+        let status = parse_thesis_status(r#"{"code":0,"data":{"tmmc":"","zdjs":" ","jd":"选题"}}"#).unwrap().unwrap();
+        assert_eq!((status.topic, status.advisor, status.deadlines.len()), (None, None, 0));
+    }
+
+    #[test]
+    fn test_parse_no_thesis() {
+        assert_eq!(parse_thesis_status(r#"{"code":0,"msg":"无论文记录","data":null}"#).unwrap(), None);
+        parse_thesis_status(r#"{"code":401,"msg":"未登录"}"#).expect_err("expect error");
+    }
+}
@@ -0,0 +1,132 @@
+use serde::Deserialize;
+
+use super::api::parse_api_data;
+use super::prelude::*;
+
+impl TuitionClient for Fdu {}
+
+const TUITION_INDEX_URL: &str = "https://stupay.fudan.edu.cn/payment/index";
+const TUITION_FEE_QUERY_URL: &str = "https://stupay.fudan.edu.cn/payment/fee/queryUnpaid";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeItem {
+    // eg. 学费, 住宿费
+    name: String,
+    // the year the fee belongs to, eg. 2022
+    year: String,
+    // the amounts in yuan
+    receivable: f64,
+    paid: f64,
+    due: f64,
+}
+
+impl FeeItem {
+    pub fn is_paid(&self) -> bool {
+        self.due <= 0.0
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeeBalance {
+    // the total amount still to pay, 0 if everything is paid
+    amount_due: f64,
+    items: Vec<FeeItem>,
+}
+
+impl FeeBalance {
+    pub fn is_settled(&self) -> bool {
+        self.amount_due <= 0.0
+    }
+}
+
+// A fee record, eg. {"xmmc":"学费","nd":"2022","ysje":"5000.00","sjje":"0.00","qfje":"5000.00"}
+// for 项目名称, 年度, 应收金额, 实缴金额 and 欠费金额. The amounts are strings.
+#[derive(Deserialize)]
+struct FeeRecord {
+    xmmc: String,
+    #[serde(default)]
+    nd: String,
+    ysje: String,
+    sjje: String,
+    qfje: String,
+}
+
+fn parse_amount(text: &str) -> Result<f64> {
+    text.trim().replace(',', "").parse()
+        .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse fee amount {} error", text)))
+}
+
+// Parse the unpaid fees. `data` is empty (or missing) when nothing is owed.
+fn parse_fee_balance(json: &str) -> Result<FeeBalance> {
+    let records: Vec<FeeRecord> = parse_api_data(json, "query fee")?.unwrap_or_default();
+
+    let mut balance = FeeBalance::default();
+    for record in records {
+        let item = FeeItem {
+            name: record.xmmc,
+            year: record.nd,
+            receivable: parse_amount(&record.ysje)?,
+            paid: parse_amount(&record.sjje)?,
+            due: parse_amount(&record.qfje)?,
+        };
+        balance.amount_due += item.due.max(0.0);
+        balance.items.push(item);
+    }
+    Ok(balance)
+}
+
+pub trait TuitionClient: Account {
+    // Get the outstanding tuition and other fees from the finance payment portal.
+    fn get_fee_balance(&self) -> Result<FeeBalance> {
+        // the payment portal is a separate CAS service, so log in to it first
+        self.follow_cas(TUITION_INDEX_URL)?;
+        let json = self.send_and_get_json(self.get_client().get(self.resolve_url(TUITION_FEE_QUERY_URL)))?;
+        parse_fee_balance(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fee_balance() {
+        const FEES: &str = r#"{"code":0,"msg":"成功","data":[
+            {"xmmc":"学费","nd":"2022","ysje":"5,000.00","sjje":"0.00","qfje":"5,000.00"},
+            {"xmmc":"住宿费","nd":"2022","ysje":"1200.00","sjje":"1200.00","qfje":"0.00"}
+        ]}"#;
+        let balance = parse_fee_balance(FEES).unwrap();
+        assert_eq!(balance.amount_due, 5000.0);
+        assert!(!balance.is_settled());
+        assert_eq!(balance.items[0], FeeItem {
+            name: "学费".to_string(),
+            year: "2022".to_string(),
+            receivable: 5000.0,
+            paid: 0.0,
+            due: 5000.0,
+        });
+        assert!(balance.items[1].is_paid());
+    }
+
+    #[test]
+    fn test_parse_no_outstanding_fee() {
+        assert!(parse_fee_balance(r#"{"code":0,"msg":"成功","data":[]}"#).unwrap().is_settled());
+        assert_eq!(parse_fee_balance(r#"{"code":0}"#).unwrap(), FeeBalance::default());
+        parse_fee_balance(r#"{"code":401,"msg":"未登录"}"#).expect_err("expect error");
+    }
+
+    #[test]
+    fn test_fee_balance() {
+        use crate::fdu::mock::{mock_fdu, script_uis_login, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        script_uis_login(&server, &[&server.url("/payment/index")]);
+        server.route("/payment/index", vec![MockResponse::ok("<html>学生缴费平台</html>")]);
+        server.route("/payment/fee/queryUnpaid", vec![MockResponse::json(r#"{"code":0,"msg":"成功","data":[]}"#)]);
+
+        let mut fd = mock_fdu(&server, &["https://stupay.fudan.edu.cn"]);
+        fd.login("uid", "pwd").expect("login error");
+        assert!(fd.get_fee_balance().expect("fee balance error").is_settled());
+        assert!(server.request_lines().iter().any(|line| line.starts_with("GET /payment/index?ticket=ST-mock")));
+    }
+}
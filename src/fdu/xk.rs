@@ -1,38 +1,150 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use chrono::NaiveDateTime;
+use once_cell::sync::Lazy;
 use regex::Regex;
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header;
 use reqwest::cookie::Jar;
-use scraper::{Html, Selector};
-use serde::{Deserialize, Serialize};
+use scraper::Html;
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::error::{ErrorType, Result, SDKError};
 
 use super::fdu::*;
+use super::html::{decode_entities, select_one, CATEGORY_OPTION_SELECTOR, FORM_SELECTOR, PROFILE_ID_INPUT_SELECTOR};
+use super::ids::CourseId;
+use super::jwfw::{parse_week_bitmap, split_teachers, ScheduleSlot};
 
-struct XK {
+static QUERY_RESULT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\[.+])[\s\S]*?(\{.+})").unwrap());
+// eg. var electedLessons = [{id:698266,no:'ECON130064.01',...}];
+static SELECTED_RESULT_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"=\s*(\[[\s\S]*])").unwrap());
+const XK_DEFAULT_PAGE_URL: &str = "https://xk.fudan.edu.cn/xk/stdElectCourse!defaultPage.action";
+const QUERY_COURSE_URL: &str = "https://xk.fudan.edu.cn/xk/stdElectCourse!queryLesson.action";
+const SELECTED_COURSES_URL: &str = "https://xk.fudan.edu.cn/xk/stdElectCourse!electedLessons.action";
+// Once the firewall blocks a grab loop, it waits this many intervals before the next poll (but no less than
+// `RATE_LIMIT_MIN_BACKOFF`, as the interval may well be zero), twice as long for each poll blocked in a row,
+// up to `RATE_LIMIT_MAX_BACKOFF` times that, see `XK::grab_many`.
+const RATE_LIMIT_BACKOFF: u32 = 8;
+const RATE_LIMIT_MIN_BACKOFF: Duration = Duration::from_secs(5);
+const RATE_LIMIT_MAX_BACKOFF: u32 = 8;
+// the courses on a page of `XK::download_catalog`
+const CATALOG_PAGE_SIZE: usize = 200;
+// No term has near 20000 courses, so more pages than this means xk keeps giving full pages whatever the page number.
+const CATALOG_MAX_PAGES: usize = 100;
+
+// eg. 选课时间：2023-02-20 12:00:00 至 2023-03-03 17:00
+static ELECTION_WINDOW_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d{4}-\d{2}-\d{2} \d{2}:\d{2}(?::\d{2})?)\s*(?:至|~|-{1,2})\s*(\d{4}-\d{2}-\d{2} \d{2}:\d{2}(?::\d{2})?)").unwrap());
+// The results of the batch operator for a course the student has already.
+const ALREADY_SELECTED_MARKERS: [&str; 3] = ["已经选过", "该课程已选", "已选过该课程"];
+
+static JSON_KEY_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"([a-zA-Z]+?):").unwrap());
+
+// The rounds of course selection. Each round has its own election profile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Round {
+    // 预选
+    PreSelection,
+    // 正选
+    Formal,
+    // 补退选
+    AddDrop,
+    // a profile whose name tells no round we know of
+    Other,
+}
+
+impl Round {
+    // Guess the round from the name of a profile, eg. 2022-2023学年2学期 本科生补退选
+    fn from_profile_name(name: &str) -> Self {
+        // check 补退选 first, as it also contains 选
+        if name.contains("补退选") {
+            Round::AddDrop
+        } else if name.contains("预选") {
+            Round::PreSelection
+        } else if name.contains("正选") {
+            Round::Formal
+        } else {
+            Round::Other
+        }
+    }
+
+    // Courses cannot be dropped during 正选.
+    fn allows_drop(&self) -> bool {
+        !matches!(self, Round::Formal)
+    }
+
+    // The oversubscribed courses of 预选 and 正选 are drawn by lot from the wish lists, see `XK::submit_wishlist`,
+    // while 补退选 is first come, first served.
+    fn accepts_wishlist(&self) -> bool {
+        matches!(self, Round::PreSelection | Round::Formal)
+    }
+
+    // The later a round, the more it is preferred when several profiles are open at once.
+    fn priority(&self) -> i32 {
+        match self {
+            Round::Other => 0,
+            Round::PreSelection => 1,
+            Round::Formal => 2,
+            Round::AddDrop => 3,
+        }
+    }
+}
+
+// The id of an election profile, which every xk request is made under. Ids are positive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ProfileId(i32);
+
+impl ProfileId {
+    fn parse(value: &str) -> Result<Self> {
+        match value.trim().parse::<i32>() {
+            Ok(id) if id > 0 => Ok(ProfileId(id)),
+            _ => Err(SDKError::with_type(ErrorType::ParseError, format!("get profile id error: {}", value))),
+        }
+    }
+}
+
+pub(crate) struct XK {
     fdu: Fdu,
-    profile_id: i32,
+    // None until a profile is entered on login
+    profile_id: Option<ProfileId>,
+    round: Option<Round>,
+    // the open profiles found on login, as (profile id, round)
+    profiles: Vec<(ProfileId, Round)>,
     courses: Vec<Course>,
+    // the course categories of the profile in use, empty until `list_categories`
+    categories: Vec<Category>,
+    // when the time budget of the running operation runs out, see `grab_many`
+    deadline: Option<Instant>,
 }
 
 impl XK {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             fdu: Fdu::new(),
-            profile_id: 0,
+            profile_id: None,
+            round: None,
+            profiles: Vec::new(),
             courses: Vec::new(),
+            categories: Vec::new(),
+            deadline: None,
         }
     }
 
     fn new_from_fdu(fdu: Fdu) -> Self {
         Self {
             fdu,
-            profile_id: 0,
+            profile_id: None,
+            round: None,
+            profiles: Vec::new(),
             courses: Vec::new(),
+            categories: Vec::new(),
+            deadline: None,
         }
     }
 }
@@ -42,9 +154,37 @@ impl HttpClient for XK {
         &self.fdu.get_client()
     }
 
+    fn get_client_following(&self, follow: bool) -> &Client {
+        self.fdu.get_client_following(follow)
+    }
+
     fn get_cookie_store(&self) -> &Arc<Jar> {
         &self.fdu.get_cookie_store()
     }
+
+    fn get_capture_dir(&self) -> Option<&Path> {
+        self.fdu.get_capture_dir()
+    }
+
+    fn get_endpoint_overrides(&self) -> &[(String, String)] {
+        self.fdu.get_endpoint_overrides()
+    }
+
+    fn get_max_body_size(&self) -> usize {
+        self.fdu.get_max_body_size()
+    }
+
+    fn get_request_delays(&self) -> &[(String, Duration)] {
+        self.fdu.get_request_delays()
+    }
+
+    fn get_extra_headers(&self) -> &[(String, String, String)] {
+        self.fdu.get_extra_headers()
+    }
+
+    fn get_strict_decoding(&self) -> bool {
+        self.fdu.get_strict_decoding()
+    }
 }
 
 impl Account for XK {
@@ -52,6 +192,10 @@ impl Account for XK {
         &self.fdu.set_credentials(uid, pwd);
     }
 
+    fn set_password_expiring(&mut self, expiring: bool) {
+        self.fdu.set_password_expiring(expiring);
+    }
+
     fn login(&mut self, uid: &str, pwd: &str) -> Result<()> {
         self.set_credentials(uid, pwd);
 
@@ -62,44 +206,25 @@ impl Account for XK {
         let mut payload = HashMap::new();
         payload.insert("username", uid);
         payload.insert("password", pwd);
-        let res = self.get_client().post(LOGIN_URL).form(&payload).send()?;
-        if !res.url().as_str().starts_with(LOGIN_SUCCESS_URL) {
-            return Err(SDKError::with_type(ErrorType::LoginError, "login error".to_string()));
+        let res = self.get_client().post(self.resolve_url(LOGIN_URL)).form(&payload).send()?;
+        if !is_same_page(res.url(), &self.resolve_url(LOGIN_SUCCESS_URL)) {
+            return Err(SDKError::login_failed("login error", res.url().as_str(), res.status().as_u16()));
         }
 
-        // sleep
-        thread::sleep(Duration::from_millis(1500));
-
-        // get profile id
-        const XK_URL: &str = "https://xk.fudan.edu.cn/xk/stdElectCourse!defaultPage.action";
-        let html = self.get_client().get(XK_URL).send()?.text()?;
-        let document = Html::parse_document(html.as_str());
-        let selector = Selector::parse(r#"input[type="hidden"]"#).unwrap();
-        if let Some(element) = document.select(&selector).next() {
-            self.profile_id = element.value().attr("value").unwrap_or_default().parse::<i32>().unwrap_or_default();
-            if self.profile_id == 0 {
-                return Err(SDKError::with_type(ErrorType::ParseError, "get profile id error".to_string()));
-            }
-        } else {
-            return Err(SDKError::with_type(ErrorType::ParseError, "get profile id error".to_string()));
-        }
+        // wait as between any requests to xk, see `FduBuilder::request_delay`
+        thread::sleep(self.request_delay(LOGIN_SUCCESS_URL));
 
-        // sleep
-        thread::sleep(Duration::from_millis(1500));
+        // get the open profiles, and enter the one of the latest round
+        let html = self.send_and_get_text(self.get_client().get(self.resolve_url(XK_DEFAULT_PAGE_URL)))?;
+        self.profiles = parse_profiles(&html)?;
+        let round = self.profiles.iter().map(|(_, round)| *round).max_by_key(Round::priority).unwrap();
 
-        // access XK_URL otherwise we couldn't get courses
-        let mut payload = HashMap::new();
-        payload.insert("electionProfile.id", self.profile_id);
-        let res = self.get_client().post(XK_URL).form(&payload).send()?;
-        if res.status() != 200 {
-            return Err(SDKError::with_type(ErrorType::LoginError, "access xk page error".to_string()));
-        }
-        Ok(())
+        self.set_profile(round)
     }
 
     fn logout(&self) -> Result<()> {
         const LOGOUT_URL: &str = "https://xk.fudan.edu.cn/xk/logout.action";
-        let res = self.get_client().get(LOGOUT_URL).send()?;
+        let res = self.get_client().get(self.resolve_url(LOGOUT_URL)).send()?;
         if res.status() != 200 {
             return Err(SDKError::with_type(ErrorType::LoginError, "logout failed".to_string()));
         }
@@ -107,7 +232,7 @@ impl Account for XK {
     }
 }
 
-#[derive(Serialize, Default)]
+#[derive(Serialize, Default, Debug, Clone)]
 struct CourseQuery {
     #[serde(rename = "lessonNo")]
     no: String,
@@ -117,20 +242,163 @@ struct CourseQuery {
     // eg. ECON130213
     #[serde(rename = "courseName")]
     name: String, // eg. 计量经济学
+    // Part of a teacher's name, eg. 张. The server is not guaranteed to filter by it,
+    // so `query_course` always filters the results by `Course::teachers` on our side as well.
+    #[serde(rename = "teacherName")]
+    teacher: String,
+    // The id of a course category, see `XK::list_categories`. Like `teacher`, it is filtered on our side as well.
+    #[serde(rename = "courseTypeId", skip_serializing_if = "Option::is_none")]
+    category: Option<i32>,
 }
 
-#[derive(Deserialize, Default, Debug, Clone)]
-struct Course {
+// The orders `XK::get_courses_sorted` can sort the courses in. Ties keep the order xk lists them in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CourseSort {
+    // the most seats left first, see `Course::remaining`
+    Remaining,
+    // the least full first, by the part of the seats taken. Courses without seats go last.
+    Fullness,
+    Name,
+    // by the lesson no, eg. ECON130003.01 before ECON130003.02
+    Code,
+}
+
+// A course category (课程类别) to browse the courses by, eg. 专业必修课程.
+#[derive(Debug, Clone, PartialEq)]
+struct Category {
+    // eg. 7
     id: i32,
+    name: String,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+pub(crate) struct Course {
+    pub(crate) id: CourseId,
     // eg. 123456
-    no: String,
+    pub(crate) no: String,
     // eg. ECON130213.01
-    code: String,
+    pub(crate) code: String,
     // eg. ECON130213
-    name: String,
+    pub(crate) name: String,
     // eg. 计量经济学
+    #[serde(default, deserialize_with = "deserialize_teachers")]
+    pub(crate) teachers: Vec<String>,
+    // eg. 陈钊,王永钦,张晏
     #[serde(default)]
     amount: AmountInfo,
+    #[serde(default, rename = "arrangeInfo")]
+    arrange_info: Vec<ArrangeInfo>,
+    // eg. 3.0, None if the server does not say
+    #[serde(default)]
+    pub(crate) credits: Option<f64>,
+    // the total hours (学时), eg. 54
+    #[serde(default)]
+    period: Option<i32>,
+    // the hours a week (周学时), eg. 3.0
+    #[serde(default, rename = "weekHour")]
+    week_hour: Option<f64>,
+    // eg. 7, the id of 专业必修课程, see `Category`
+    #[serde(default, rename = "courseTypeId")]
+    category_id: Option<i32>,
+    // whether the course may be dropped, None if the server does not say
+    #[serde(default)]
+    withdrawable: Option<bool>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+struct ArrangeInfo {
+    // 1 = Monday, ..., 7 = Sunday
+    #[serde(rename = "weekDay")]
+    week_day: i32,
+    // eg. 01111111111111111000000000000000000000000000000000000
+    #[serde(rename = "weekState")]
+    week_state: String,
+    #[serde(rename = "startUnit")]
+    start_unit: i32,
+    #[serde(rename = "endUnit")]
+    end_unit: i32,
+    // eg. 1-16
+    #[serde(rename = "weekStateDigest")]
+    week_state_digest: String,
+    // eg. H3208
+    rooms: String,
+}
+
+impl CourseQuery {
+    // Whether `course` is the one looked for. A lesson no picks that very teaching class, and the code and name are
+    // only looked at without one, in which case every section of the course matches.
+    fn matches(&self, course: &Course) -> bool {
+        if !self.no.is_empty() {
+            return course.no == self.no;
+        }
+        course.code == self.code || course.name == self.name
+    }
+}
+
+// A course is identified by its id alone: the amounts change between queries,
+// and the same course may come back on more than one page of results.
+impl PartialEq for Course {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Course {}
+
+impl Hash for Course {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl Course {
+    // The schedule of the course, in the same form as jwfw's course table.
+    fn schedule_slots(&self) -> Vec<ScheduleSlot> {
+        self.arrange_info.iter().map(|arrange| ScheduleSlot::new(
+            self.name.clone(),
+            arrange.rooms.clone(),
+            arrange.week_day,
+            arrange.start_unit,
+            arrange.end_unit,
+            parse_week_bitmap(&arrange.week_state),
+        )).collect()
+    }
+
+    // The credits and hours as shown to students, eg. 3 学分 / 54 学时 / 3 周学时, leaving out those the server does not say.
+    fn credit_hours(&self) -> String {
+        [
+            self.credits.map(|credits| format!("{} 学分", credits)),
+            self.period.map(|period| format!("{} 学时", period)),
+            self.week_hour.map(|week_hour| format!("{} 周学时", week_hour)),
+        ].into_iter().flatten().collect::<Vec<_>>().join(" / ")
+    }
+
+    // Whether the course can still be dropped. A course the server says nothing about is taken as droppable.
+    fn is_withdrawable(&self) -> bool {
+        self.withdrawable != Some(false)
+    }
+
+    // The seats left, 0 when the course is full.
+    pub(crate) fn remaining(&self) -> i32 {
+        (self.amount.total - self.amount.selected).max(0)
+    }
+
+    // Whether the two courses have a lesson at the same unit of the same weekday in some week.
+    // Courses in the same time slot but in disjoint weeks, or in adjacent units, do not conflict.
+    fn conflicts_with(&self, other: &Course) -> bool {
+        let slots = self.schedule_slots();
+        other.schedule_slots().iter().any(|other_slot| slots.iter().any(|slot| slot.overlaps(other_slot)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum OperationResult {
+    Success,
+    // The student has the course already, with the message from the server, eg. 选课失败：该课程已经选过.
+    // A grab needs not go on for it.
+    AlreadySelected(String),
+    // the message from the server, eg. 选课失败：人数已满
+    Failure(String),
 }
 
 #[derive(Deserialize, Default, Debug, Clone)]
@@ -142,21 +410,103 @@ struct AmountInfo {
 }
 
 impl XK {
+    // Log out of xk (if it was logged in to) and then UIS, see `Fdu::close`. UIS is logged out of even if xk fails.
+    fn close(self) -> Result<()> {
+        let xk = if self.profile_id.is_some() { self.logout() } else { Ok(()) };
+        let uis = self.fdu.close();
+        xk.and(uis)
+    }
+
+    // The round of the profile in use, None before login.
+    fn round(&self) -> Option<Round> {
+        self.round
+    }
+
+    // Enter the profile of `round`, which must be one of the profiles found on login.
+    // The courses cached from another profile are cleared.
+    fn set_profile(&mut self, round: Round) -> Result<()> {
+        let profile_id = self.profiles.iter().find(|(_, r)| *r == round).map(|(id, _)| *id)
+            .ok_or(SDKError::with_type(ErrorType::OtherError, format!("no profile open for round {:?}", round)))?;
+
+        // access XK_DEFAULT_PAGE_URL otherwise we couldn't get courses
+        let mut payload = HashMap::new();
+        payload.insert("electionProfile.id", profile_id.0);
+        let res = self.get_client().post(self.resolve_url(XK_DEFAULT_PAGE_URL)).form(&payload).send()?;
+        if res.status() != 200 {
+            return Err(SDKError::with_type(ErrorType::LoginError, "access xk page error".to_string()));
+        }
+        self.profile_id = Some(profile_id);
+        self.round = Some(round);
+        self.courses.clear();
+        self.categories.clear();
+        Ok(())
+    }
+
+    // The course categories the courses can be browsed by, from the category filter of the election page.
+    // Pass the id of one as `CourseQuery::category` to query its courses only.
+    fn list_categories(&mut self) -> Result<Vec<Category>> {
+        if self.categories.is_empty() {
+            let payload = [("electionProfile.id", self.profile_id()?.0)];
+            let html = self.send_and_get_text(self.get_client().post(self.resolve_url(XK_DEFAULT_PAGE_URL)).form(&payload))?;
+            self.categories = parse_categories(&html)?;
+        }
+        Ok(self.categories.clone())
+    }
+
+    // The open and close times of the round in use, eg. to tell the user "补退选 open until 2023-03-03 17:00" or to gray
+    // out selection when it is closed. None if the xk homepage does not publish them. Compare with `Local::now()`
+    // to tell if selection is open.
+    fn election_window(&mut self) -> Result<Option<(NaiveDateTime, NaiveDateTime)>> {
+        let profile_id = self.profile_id()?;
+        let html = self.send_and_get_text(self.get_client().get(self.resolve_url(XK_DEFAULT_PAGE_URL)))?;
+        parse_election_window(&html, profile_id)
+    }
+
+    // The profile in use, or a `LoginError` if none is entered yet.
+    fn profile_id(&self) -> Result<ProfileId> {
+        self.profile_id.ok_or(SDKError::with_type(ErrorType::LoginError, "no election profile entered, log in first".to_string()))
+    }
+
+    // Make the request time out when the budget of the running operation runs out, if there is one.
+    fn within_deadline(&self, builder: RequestBuilder) -> RequestBuilder {
+        match self.deadline {
+            // with no time left, the request times out at once
+            Some(deadline) => builder.timeout(deadline.saturating_duration_since(Instant::now())),
+            None => builder,
+        }
+    }
+
+    fn timed_out(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
     fn query_course(&self, query: &CourseQuery) -> Result<Vec<Course>> {
-        const QUERY_COURSE_URL: &str = "https://xk.fudan.edu.cn/xk/stdElectCourse!queryLesson.action";
-        let res = self.get_client().
-            post(QUERY_COURSE_URL).
-            query(&[("profileId", self.profile_id)]).
-            form(query).
-            send()?;
-        let status_code = res.status();
-        let html = res.text()?;
+        let mut courses = self.query_course_page(query, None)?;
+        if let Some(category) = query.category {
+            courses.retain(|course| course.category_id == Some(category));
+        }
+        Ok(filter_by_teacher(courses, &query.teacher))
+    }
+
+    // The courses matching `query` on the `page`-th page counting from 1, of `CATALOG_PAGE_SIZE` courses each,
+    // or all of them at once when `page` is None. Their amounts are joined, but nothing is filtered on our side.
+    fn query_course_page(&self, query: &CourseQuery, page: Option<usize>) -> Result<Vec<Course>> {
+        // without these, xk may answer with the whole election page instead of the js of the courses
+        let mut builder = self.get_client().
+            post(self.resolve_url(QUERY_COURSE_URL)).
+            header("X-Requested-With", "XMLHttpRequest").
+            header(header::REFERER, self.resolve_url(XK_DEFAULT_PAGE_URL)).
+            query(&[("profileId", self.profile_id()?.0)]);
+        if let Some(page) = page {
+            builder = builder.query(&[("pageNo", page), ("pageSize", CATALOG_PAGE_SIZE)]);
+        }
+        let (status_code, html) = self.send_and_get_status_text(self.within_deadline(builder.form(query)))?;
+        check_blocked(status_code, &html)?;
         if status_code != 200 {
             return Err(SDKError::with_type(ErrorType::NetworkError, format!("status code: {}\ntext: {}", status_code, html)));
         }
 
-        let r = Regex::new(r"(\[.+])[\s\S]*?(\{.+})").unwrap();
-        let cap = r.captures(html.as_str()).ok_or(SDKError::with_type(ErrorType::ParseError, "parse course error".to_string()))?;
+        let cap = QUERY_RESULT_REGEX.captures(html.as_str()).ok_or(SDKError::with_type(ErrorType::ParseError, "parse course error".to_string()))?;
         let courses_str = normalize_json(
             cap.get(1).ok_or(SDKError::with_type(ErrorType::ParseError, "course_str does not exist".to_string()))?.as_str()
         );
@@ -165,15 +515,45 @@ impl XK {
         );
 
         let mut courses: Vec<Course> = serde_json::from_str(courses_str.as_str())?;
-        let amounts: HashMap<&str, AmountInfo> = serde_json::from_str(amounts_str.as_str())?;
+        join_amounts(&mut courses, parse_amounts(&amounts_str)?);
+        // the strings in the js are not decoded, eg. 经济&amp;管理
         for course in &mut courses {
-            if let Some(amount) = amounts.get(course.id.to_string().as_str()) {
-                course.amount = amount.clone();
-            }
+            course.name = decode_entities(&course.name);
+            course.teachers = course.teachers.iter().map(|teacher| decode_entities(teacher)).collect();
         }
         Ok(courses)
     }
 
+    // Download the whole catalog of the profile, page by page, waiting as between any requests to xk in between.
+    // This is the heavy version of `get_courses` for offline browsing, and replaces its cache.
+    fn download_catalog(&mut self) -> Result<Vec<Course>> {
+        self.download_catalog_with_progress(|_, _| {})
+    }
+
+    // `download_catalog`, calling `progress` with the count of pages and of courses downloaded so far after each page.
+    // The paging stops at a page short of `CATALOG_PAGE_SIZE`, or one giving no new course (xk ignoring the page number),
+    // and fails after `CATALOG_MAX_PAGES` pages instead of looping on forever.
+    fn download_catalog_with_progress(&mut self, mut progress: impl FnMut(usize, usize)) -> Result<Vec<Course>> {
+        let mut catalog: Vec<Course> = Vec::new();
+        let mut seen = HashSet::new();
+        for page in 1..=CATALOG_MAX_PAGES {
+            if page > 1 {
+                thread::sleep(self.request_delay(QUERY_COURSE_URL));
+            }
+            let courses = self.query_course_page(&CourseQuery::default(), Some(page))?;
+            let last = courses.len() < CATALOG_PAGE_SIZE;
+            let before = catalog.len();
+            // a course may show up on two pages if the list changes while paging
+            catalog.extend(courses.into_iter().filter(|course| seen.insert(course.id)));
+            progress(page, catalog.len());
+            if last || catalog.len() == before {
+                self.courses = catalog;
+                return Ok(self.courses.clone());
+            }
+        }
+        Err(SDKError::with_type(ErrorType::OtherError, format!("course catalog has more than {} pages", CATALOG_MAX_PAGES)))
+    }
+
     fn get_courses(&mut self) -> Result<Vec<Course>> {
         if self.courses.len() > 0 {
             return Ok(self.courses.clone());
@@ -183,45 +563,148 @@ impl XK {
         Ok(self.courses.clone())
     }
 
-    fn get_id(&mut self, query: &CourseQuery, courses: Vec<Course>) -> Result<i32> {
-        for course in courses {
-            if course.no == query.no || course.code == query.code || course.name == query.name {
-                return Ok(course.id);
+    // Search the course list (fetched first if it is not cached yet) for `keyword` in the name, code or teachers,
+    // eg. 计量 for 计量经济学 or econ1302 for ECON130213, ignoring case. An empty keyword gives all the courses.
+    pub(crate) fn search_courses(&mut self, keyword: &str) -> Result<Vec<Course>> {
+        Ok(search_in(self.get_courses()?, keyword))
+    }
+
+    // Look up a course by id in the course list, fetching the list first if it is not cached yet.
+    fn get_course_by_id(&mut self, id: CourseId) -> Result<Option<Course>> {
+        Ok(self.get_courses()?.into_iter().find(|course| course.id == id))
+    }
+
+    // The courses that still have seats, see `Course::remaining`.
+    fn get_available_courses(&mut self) -> Result<Vec<Course>> {
+        Ok(split_by_availability(self.get_courses()?).0)
+    }
+
+    // The course list sorted `by`, eg. by the seats left for a course grabbing view. `get_courses` keeps xk's order.
+    fn get_courses_sorted(&mut self, by: CourseSort) -> Result<Vec<Course>> {
+        let mut courses = self.get_courses()?;
+        sort_courses(&mut courses, by);
+        Ok(courses)
+    }
+
+    // The courses that are full.
+    fn get_full_courses(&mut self) -> Result<Vec<Course>> {
+        Ok(split_by_availability(self.get_courses()?).1)
+    }
+
+    // Find the id of the one course matching `query`. If several sections match, e.g. only the code is given,
+    // it fails with an `AmbiguousSelection` error listing them, rather than picking one at random.
+    fn get_id(&mut self, query: &CourseQuery, courses: Vec<Course>) -> Result<CourseId> {
+        // the same course may come back more than once, not necessarily in a row
+        let mut seen = HashSet::new();
+        let matched: Vec<Course> = courses.into_iter().filter(|course| query.matches(course) && seen.insert(course.id)).collect();
+        match matched.as_slice() {
+            [] => Err(SDKError::with_type(ErrorType::OtherError, "id not found".to_string())),
+            [course] => Ok(course.id),
+            sections => {
+                let sections: Vec<String> = sections.iter()
+                    .map(|course| format!("{}({})", course.no, course.teachers.join(",")))
+                    .collect();
+                Err(SDKError::with_type(ErrorType::AmbiguousSelection,
+                    format!("{} matches several sections, pick one by lesson no: {}", query.code, sections.join(", "))))
             }
         }
-        Err(SDKError::with_type(ErrorType::OtherError, "id not found".to_string()))
     }
 
-    fn operate_course(&self, id: i32, select: bool) -> Result<bool> {
+    // Check whether the course found by `query` conflicts with the courses the student has, and return the first
+    // conflicting one in `current`.
+    fn would_conflict(&mut self, query: &CourseQuery, current: &[Course]) -> Result<Option<Course>> {
+        let courses = self.query_course(query)?;
+        let id = self.get_id(query, courses.clone())?;
+        let target = courses.iter().find(|course| course.id == id).unwrap();
+        Ok(find_conflict(target, current).cloned())
+    }
+
+    fn operate_course(&self, id: CourseId, select: bool) -> Result<bool> {
         // select: true -> select, false -> drop
+        // dropping a course known to be not withdrawable is doomed, so it is not even sent
+        if let Some(course) = self.courses.iter().find(|course| course.id == id && !select && !course.is_withdrawable()) {
+            return Err(SDKError::with_type(ErrorType::OtherError, format!("{}({}) cannot be dropped", course.name, course.no)));
+        }
+        // having the course already counts as selected
+        let results = self.batch_operate(&[id], select)?;
+        Ok(matches!(results.first(), Some((_, OperationResult::Success | OperationResult::AlreadySelected(_)))))
+    }
+
+    // The courses the student has in the profile, as xk lists them now. Their amounts are not given, and are left 0/0.
+    fn get_selected_courses(&self) -> Result<Vec<Course>> {
+        let html = self.send_and_get_text(self.within_deadline(self.get_client().
+            get(self.resolve_url(SELECTED_COURSES_URL)).
+            header("X-Requested-With", "XMLHttpRequest").
+            query(&[("profileId", self.profile_id()?.0)])))?;
+        parse_selected_courses(&html)
+    }
+
+    // The number of courses the student has in the profile and their total credits, see `credit_summary`.
+    pub(crate) fn get_selected_credits(&self) -> Result<(usize, f64)> {
+        Ok(credit_summary(&self.get_selected_courses()?))
+    }
+
+    // Check that a course selected (or dropped, if false) by `operate_course` really is (or is no more) in the
+    // selected courses: a select may come back 成功 and still not be saved, eg. when the connection drops halfway.
+    fn verify_selected(&mut self, id: CourseId) -> Result<bool> {
+        Ok(self.get_selected_courses()?.iter().any(|course| course.id == id))
+    }
 
+    // Select or drop several courses in one request, and get the result of each course.
+    fn batch_operate(&self, ids: &[CourseId], select: bool) -> Result<Vec<(CourseId, OperationResult)>> {
         const OPERATE_COURSE_URL: &str = "https://xk.fudan.edu.cn/xk/stdElectCourse!batchOperator.action";
-        let mut payload = HashMap::new();
-        let mut operator0 = String::new();
-        if select {
-            payload.insert("optype", "true");
-            operator0 = format!("{}:true:0", id);
-        } else {
-            payload.insert("optype", "false");
-            operator0 = format!("{}:false", id);
+        if !select && self.round.is_some_and(|round| !round.allows_drop()) {
+            return Ok(ids.iter().map(|id| (*id, OperationResult::Failure("当前轮次不允许退课".to_string()))).collect());
+        }
+        let mut payload = vec![("optype".to_string(), select.to_string())];
+        for (i, id) in ids.iter().enumerate() {
+            let operator = if select { format!("{}:true:0", id) } else { format!("{}:false", id) };
+            payload.push((format!("operator{}", i), operator));
         }
-        payload.insert("operator0", operator0.as_str());
 
-        let mut html = self.get_client().
-            post(OPERATE_COURSE_URL).
-            query(&[("profileId", self.profile_id)]).
-            form(&payload).
-            send()?.text()?;
+        let (status, html) = self.send_and_get_status_text(self.within_deadline(self.get_client().
+            post(self.resolve_url(OPERATE_COURSE_URL)).
+            query(&[("profileId", self.profile_id()?.0)]).
+            form(&payload)))?;
+        check_blocked(status, &html)?;
 
-        let document = Html::parse_document(html.as_str());
-        let selector = Selector::parse("div").unwrap();
-        html = document.select(&selector).next().
-            ok_or(SDKError::with_type(ErrorType::ParseError, "operate course result".to_string()))?.
-            text().collect();
-        html.retain(|c| !c.is_whitespace());
+        parse_operation_results(&html, ids)
+    }
 
-        println!("{}", html);
-        Ok(html.contains("成功"))
+    // Submit a ranked wish list (志愿) for the courses drawn by lot, the first wish first. Each query must find exactly
+    // one course, see `get_id`, and a later submission replaces the whole list. It fails if the round takes no wish list,
+    // or if the server rejects any of the wishes, with its messages.
+    fn submit_wishlist(&mut self, ranked: &[CourseQuery]) -> Result<()> {
+        const WISHLIST_URL: &str = "https://xk.fudan.edu.cn/xk/stdElectCourse!saveWishList.action";
+        let profile_id = self.profile_id()?;
+        if let Some(round) = self.round.filter(|round| !round.accepts_wishlist()) {
+            return Err(SDKError::with_type(ErrorType::OtherError, format!("round {:?} does not accept wish lists", round)));
+        }
+
+        let mut ids = Vec::new();
+        for query in ranked {
+            let courses = self.query_course(query)?;
+            let id = self.get_id(query, courses)?;
+            if ids.contains(&id) {
+                return Err(SDKError::with_type(ErrorType::OtherError, format!("course {} is wished more than once", id)));
+            }
+            ids.push(id);
+        }
+
+        let html = self.send_and_get_text(self.get_client().
+            post(self.resolve_url(WISHLIST_URL)).
+            query(&[("profileId", profile_id.0)]).
+            form(&wishlist_payload(&ids)))?;
+        let failures: Vec<String> = parse_operation_results(&html, &ids)?.into_iter()
+            .filter_map(|(_, result)| match result {
+                OperationResult::Failure(message) | OperationResult::AlreadySelected(message) => Some(message),
+                OperationResult::Success => None,
+            })
+            .collect();
+        if !failures.is_empty() {
+            return Err(SDKError::with_type(ErrorType::OtherError, format!("submit wish list error: {}", failures.join("; "))));
+        }
+        Ok(())
     }
 
     fn single_select(&mut self, query: &CourseQuery, select: bool) -> Result<bool> {
@@ -229,11 +712,309 @@ impl XK {
         let id = self.get_id(query, courses)?;
         self.operate_course(id, select)
     }
+
+    // Wait for a seat of the course to open and select it, see `grab_many`.
+    fn grab(&mut self, query: &CourseQuery, max_polls: usize, interval: Duration, cancel: Option<Arc<AtomicBool>>,
+            max_total_duration: Option<Duration>) -> Result<bool> {
+        Ok(self.grab_many(std::slice::from_ref(query), max_polls, interval, cancel, max_total_duration)?[0].1)
+    }
+
+    // `grab` the teaching class of lesson no `no`, eg. ECON130064.01, for `fdu_xk_grab` which has no `CourseQuery`.
+    pub(crate) fn grab_by_no(&mut self, no: &str, max_polls: usize, interval: Duration, cancel: Option<Arc<AtomicBool>>) -> Result<bool> {
+        self.grab(&CourseQuery { no: no.to_string(), ..Default::default() }, max_polls, interval, cancel, None)
+    }
+
+    // Poll the availability of several courses, and select each of them as soon as it has a seat.
+    //
+    // Every poll takes one query for the whole course list and at most one batch select, however many courses
+    // there are, so it stays within the same throttle budget as grabbing a single course. Polls are `interval` apart,
+    // which should be no shorter than 1.5s to avoid being throttled. Stops when every course is selected or after
+    // `max_polls` polls, and returns whether each course is selected, in the order of `queries`.
+    //
+    // Setting `cancel` (e.g. from a UI thread) stops the loop with a `Cancelled` error before the next poll,
+    // even in the middle of waiting. The courses selected by then stay selected.
+    //
+    // `max_total_duration` bounds the whole loop, waiting and requests included: once it is over, the loop stops
+    // with a `Timeout` error, even in the middle of a slow request, however many polls are left.
+    //
+    // A poll blocked by the firewall (a `RateLimited` error) does not stop the loop, which backs off instead: the next
+    // poll waits `RATE_LIMIT_BACKOFF` intervals, at least `RATE_LIMIT_MIN_BACKOFF`, and twice as long after each poll
+    // blocked in a row. The blocked polls count towards `max_polls`, and if the last one is blocked, the loop fails with
+    // the `RateLimited` error.
+    fn grab_many(&mut self, queries: &[CourseQuery], max_polls: usize, interval: Duration, cancel: Option<Arc<AtomicBool>>,
+                 max_total_duration: Option<Duration>) -> Result<Vec<(CourseQuery, bool)>> {
+        self.deadline = max_total_duration.map(|duration| Instant::now() + duration);
+        let result = self.poll_and_grab(queries, max_polls, interval, cancel.as_deref());
+        self.deadline = None;
+        result
+    }
+
+    // The loop of `grab_many`, within `self.deadline`.
+    fn poll_and_grab(&mut self, queries: &[CourseQuery], max_polls: usize, interval: Duration, cancel: Option<&AtomicBool>) -> Result<Vec<(CourseQuery, bool)>> {
+        let mut selected = vec![false; queries.len()];
+        let stopped = |r#type: ErrorType, reason: &str, poll: usize, selected: &[bool]| {
+            let count = selected.iter().filter(|selected| **selected).count();
+            SDKError::with_type(r#type, format!("grab {} after {} polls, {} of {} courses selected", reason, poll, count, queries.len()))
+        };
+        // the polls blocked in a row, and the error of the last one
+        let mut blocked: (u32, Option<SDKError>) = (0, None);
+        for poll in 0..max_polls {
+            if poll > 0 {
+                let wait = match blocked.0 {
+                    0 => interval,
+                    n => (interval * RATE_LIMIT_BACKOFF).max(RATE_LIMIT_MIN_BACKOFF) * 2u32.saturating_pow(n - 1).min(RATE_LIMIT_MAX_BACKOFF),
+                };
+                let left = self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                sleep_unless_cancelled(left.map_or(wait, |left| wait.min(left)), cancel);
+            }
+            if cancel.is_some_and(|cancel| cancel.load(Ordering::SeqCst)) {
+                return Err(stopped(ErrorType::Cancelled, "cancelled", poll, &selected));
+            }
+            if self.timed_out() {
+                return Err(stopped(ErrorType::Timeout, "timed out", poll, &selected));
+            }
+
+            let courses = match self.query_course(&CourseQuery::default()) {
+                Ok(courses) => courses,
+                Err(e) if e.is_rate_limited() && !self.timed_out() => {
+                    blocked = (blocked.0 + 1, Some(e));
+                    continue;
+                }
+                Err(e) => return Err(if self.timed_out() { stopped(ErrorType::Timeout, "timed out", poll, &selected) } else { e }),
+            };
+            blocked = (0, None);
+            let mut targets = Vec::new();
+            for (i, query) in queries.iter().enumerate() {
+                if selected[i] {
+                    continue;
+                }
+                if let Some(course) = courses.iter().find(|course| query.matches(course) && course.remaining() > 0) {
+                    targets.push((i, course.id));
+                }
+            }
+            if !targets.is_empty() {
+                let ids: Vec<CourseId> = targets.iter().map(|(_, id)| *id).collect();
+                let results = match self.batch_operate(&ids, true) {
+                    Ok(results) => results,
+                    Err(e) if e.is_rate_limited() && !self.timed_out() => {
+                        blocked = (blocked.0 + 1, Some(e));
+                        continue;
+                    }
+                    Err(e) => return Err(if self.timed_out() { stopped(ErrorType::Timeout, "timed out", poll, &selected) } else { e }),
+                };
+                for ((i, _), (_, result)) in targets.iter().zip(results) {
+                    // someone else may have taken the seat first, so keep polling on failure,
+                    // but not for a course the student has already
+                    selected[*i] = matches!(result, OperationResult::Success | OperationResult::AlreadySelected(_));
+                }
+            }
+
+            if selected.iter().all(|selected| *selected) {
+                break;
+            }
+        }
+        if let (_, Some(e)) = blocked {
+            return Err(e);
+        }
+        Ok(queries.iter().cloned().zip(selected).collect())
+    }
+}
+
+// Sleep for `duration`, but wake up early once `cancel` is set, so that cancelling does not wait out a long interval.
+fn sleep_unless_cancelled(duration: Duration, cancel: Option<&AtomicBool>) {
+    const STEP: Duration = Duration::from_millis(50);
+    let cancel = match cancel {
+        Some(cancel) => cancel,
+        None => return thread::sleep(duration),
+    };
+    let mut left = duration;
+    while !left.is_zero() && !cancel.load(Ordering::SeqCst) {
+        let step = left.min(STEP);
+        thread::sleep(step);
+        left -= step;
+    }
+}
+
+// Parse the response of the batch operator. The result is in the first div, one line for each operator, eg.
+// <div>国际金融(ECON130003.01) 选课成功<br/>博弈论(ECON130064.01) 选课失败：人数已满<br/></div>
+// and the n-th line is the result of `operatorN`, i.e. `ids[n]`.
+fn parse_operation_results(html: &str, ids: &[CourseId]) -> Result<Vec<(CourseId, OperationResult)>> {
+    let document = Html::parse_document(html);
+    let div = select_one(&document, "div")?;
+    let lines: Vec<String> = div.text()
+        .map(|line| line.split_whitespace().collect::<String>())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.len() < ids.len() {
+        return Err(SDKError::with_type(ErrorType::ParseError, format!("expect {} results, found {}", ids.len(), lines.len())));
+    }
+
+    Ok(ids.iter().zip(lines).map(|(id, line)| {
+        let result = if ALREADY_SELECTED_MARKERS.iter().any(|marker| line.contains(marker)) {
+            OperationResult::AlreadySelected(line)
+        } else if line.contains("成功") {
+            OperationResult::Success
+        } else {
+            OperationResult::Failure(line)
+        };
+        (*id, result)
+    }).collect())
+}
+
+fn parse_selected_courses(html: &str) -> Result<Vec<Course>> {
+    let cap = SELECTED_RESULT_REGEX.captures(html)
+        .ok_or(SDKError::with_type(ErrorType::ParseError, "parse selected courses error".to_string()))?;
+    let mut courses: Vec<Course> = serde_json::from_str(&normalize_json(&cap[1]))?;
+    for course in &mut courses {
+        course.name = decode_entities(&course.name);
+    }
+    Ok(courses)
+}
+
+// The form of a wish list, one `wishN` for each course in order, with its rank from 1, eg. wish0=698266:1
+fn wishlist_payload(ids: &[CourseId]) -> Vec<(String, String)> {
+    ids.iter().enumerate().map(|(i, id)| (format!("wish{}", i), format!("{}:{}", id, i + 1))).collect()
+}
+
+// The teachers come as one string, eg. 陈钊,王永钦,张晏
+fn deserialize_teachers<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<String>, D::Error> {
+    Ok(split_teachers(&String::deserialize(deserializer)?))
+}
+
+// Keep the courses taught by a teacher whose name contains `teacher`. An empty `teacher` keeps all.
+fn filter_by_teacher(mut courses: Vec<Course>, teacher: &str) -> Vec<Course> {
+    if !teacher.is_empty() {
+        courses.retain(|course| course.teachers.iter().any(|name| name.contains(teacher)));
+    }
+    courses
+}
+
+// Keep the courses with `keyword` in the name, code or a teacher's name, ignoring case, see `XK::search_courses`.
+fn search_in(mut courses: Vec<Course>, keyword: &str) -> Vec<Course> {
+    let keyword = keyword.trim().to_lowercase();
+    courses.retain(|course| [&course.name, &course.code].into_iter().chain(&course.teachers)
+        .any(|text| text.to_lowercase().contains(&keyword)));
+    courses
+}
+
+// Parse the open election profiles on the xk default page, one form for each, eg.
+// <form><h3>2022-2023学年2学期 本科生正选</h3><input type="hidden" name="electionProfile.id" value="1234"/></form>
+// The round is told by the text of the form.
+fn parse_profiles(html: &str) -> Result<Vec<(ProfileId, Round)>> {
+    let document = Html::parse_document(html);
+
+    let mut profiles = Vec::new();
+    for form in document.select(&FORM_SELECTOR) {
+        if let Some(input) = form.select(&PROFILE_ID_INPUT_SELECTOR).next() {
+            let id = ProfileId::parse(input.value().attr("value").unwrap_or_default())?;
+            profiles.push((id, Round::from_profile_name(&form.text().collect::<String>())));
+        }
+    }
+    if profiles.is_empty() {
+        return Err(SDKError::with_type(ErrorType::ParseError, "no election profile is open".to_string()));
+    }
+    Ok(profiles)
+}
+
+// Parse the election window of the profile from its form on the xk default page, eg.
+// <form><h3>2022-2023学年2学期 本科生补退选</h3><p>选课时间：2023-02-20 12:00:00 至 2023-03-03 17:00:00</p>
+// <input type="hidden" name="electionProfile.id" value="1234"/></form>
+// None if the form states no window, or there is no form of the profile.
+fn parse_election_window(html: &str, profile_id: ProfileId) -> Result<Option<(NaiveDateTime, NaiveDateTime)>> {
+    let document = Html::parse_document(html);
+    let form = document.select(&FORM_SELECTOR).find(|form| form.select(&PROFILE_ID_INPUT_SELECTOR).next()
+        .and_then(|input| input.value().attr("value"))
+        .and_then(|value| ProfileId::parse(value).ok()) == Some(profile_id));
+    let text = match form {
+        Some(form) => form.text().collect::<String>(),
+        None => return Ok(None),
+    };
+    let captures = match ELECTION_WINDOW_REGEX.captures(&text) {
+        Some(captures) => captures,
+        None => return Ok(None),
+    };
+
+    let parse_time = |time: &str| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M"))
+        .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse election time {} error", time)));
+    let (open, close) = (parse_time(&captures[1])?, parse_time(&captures[2])?);
+    if close < open {
+        return Err(SDKError::with_type(ErrorType::ParseError, format!("election window {} ends before it opens", &captures[0])));
+    }
+    Ok(Some((open, close)))
+}
+
+// Parse the course categories from the options of the category filter, eg. <option value="7">专业必修课程</option>.
+// The option for all categories has no value, and is left out.
+fn parse_categories(html: &str) -> Result<Vec<Category>> {
+    let document = Html::parse_document(html);
+
+    let mut categories = Vec::new();
+    for option in document.select(&CATEGORY_OPTION_SELECTOR) {
+        let value = option.value().attr("value").unwrap_or_default().trim();
+        if value.is_empty() {
+            continue;
+        }
+        let id = value.parse()
+            .map_err(|_| SDKError::with_type(ErrorType::ParseError, format!("parse course category id {} error", value)))?;
+        categories.push(Category { id, name: option.text().collect::<String>().trim().to_string() });
+    }
+    if categories.is_empty() {
+        return Err(SDKError::with_type(ErrorType::ParseError, "no course category found".to_string()));
+    }
+    Ok(categories)
+}
+
+// Find the first course in `current` having a lesson at the same time as `target`, see `Course::conflicts_with`.
+fn find_conflict<'a>(target: &Course, current: &'a [Course]) -> Option<&'a Course> {
+    current.iter().find(|course| course.id != target.id && target.conflicts_with(course))
+}
+
+// Parse the (normalized) amounts of the query result, which are keyed by course id, eg. {"698241":{"sc":70,"lc":100}}
+fn parse_amounts(json: &str) -> Result<HashMap<CourseId, AmountInfo>> {
+    Ok(serde_json::from_str(json)?)
+}
+
+// The number of courses and their total credits, e.g. of the selected courses for a home screen stat.
+// A course without credits is counted with 0, and a course listed more than once is counted once.
+fn credit_summary(courses: &[Course]) -> (usize, f64) {
+    let mut seen = HashSet::new();
+    courses.iter()
+        .filter(|course| seen.insert(course.id))
+        .fold((0, 0.0), |(count, credits), course| (count + 1, credits + course.credits.unwrap_or_default()))
+}
+
+// Split the courses into (available, full).
+fn split_by_availability(courses: Vec<Course>) -> (Vec<Course>, Vec<Course>) {
+    courses.into_iter().partition(|course| course.remaining() > 0)
+}
+
+fn sort_courses(courses: &mut [Course], by: CourseSort) {
+    match by {
+        CourseSort::Remaining => courses.sort_by_key(|course| std::cmp::Reverse(course.remaining())),
+        CourseSort::Fullness => {
+            let fullness = |course: &Course| match course.amount.total {
+                total if total > 0 => course.amount.selected as f64 / total as f64,
+                _ => f64::INFINITY,
+            };
+            courses.sort_by(|a, b| fullness(a).total_cmp(&fullness(b)));
+        }
+        CourseSort::Name => courses.sort_by(|a, b| a.name.cmp(&b.name)),
+        CourseSort::Code => courses.sort_by(|a, b| a.no.cmp(&b.no)),
+    }
+}
+
+// Fill in the amount of each course. A course without an amount keeps the default of 0/0.
+fn join_amounts(courses: &mut [Course], mut amounts: HashMap<CourseId, AmountInfo>) {
+    for course in courses {
+        if let Some(amount) = amounts.remove(&course.id) {
+            course.amount = amount;
+        }
+    }
 }
 
 fn normalize_json(json: &str) -> String {
-    let r1 = Regex::new(r"([a-zA-Z]+?):").unwrap();
-    let mut result = r1.replace_all(json, "\"${1}\":").to_string();
+    let mut result = JSON_KEY_REGEX.replace_all(json, "\"${1}\":").to_string();
     result = result.replace("'", "\"");
     result
 }
@@ -299,8 +1080,673 @@ mod tests {
         println!("{}\n{}", course_str, amount_str);
 
         let course: Vec<Course> = serde_json::from_str(&course_str).unwrap();
-        let amount: HashMap<String, AmountInfo> = serde_json::from_str(&amount_str).unwrap();
+        let amount = parse_amounts(&amount_str).unwrap();
+        assert!(course.iter().all(|course| course.credits == Some(3.0) && course.period == Some(54) && course.week_hour == Some(3.0)));
         println!("{:?}", course);
         println!("{:?}", amount);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_arrange_info() {
+        const COURSE: &str = "[{id:698251,no:'ECON130010.01',name:'当代中国经济',code:'ECON130010',teachers:'陈钊,王永钦,张晏',arrangeInfo:[{weekDay:3,weekState:'00000011111000000000000000000000000000000000000000000',startUnit:3,endUnit:5,weekStateDigest:'6-10',rooms:'H4305'},{weekDay:3,weekState:'01111100000000000000000000000000000000000000000000000',startUnit:3,endUnit:5,weekStateDigest:'1-5',rooms:'H4305'}]},{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003'}]";
+        let courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+
+        let arrange = &courses[0].arrange_info;
+        assert_eq!(arrange.len(), 2);
+        assert_eq!((arrange[0].week_day, arrange[0].start_unit, arrange[0].end_unit), (3, 3, 5));
+        assert_eq!(arrange[0].week_state_digest, "6-10");
+        assert_eq!(arrange[1].rooms, "H4305");
+        // arrangeInfo is optional
+        assert!(courses[1].arrange_info.is_empty());
+
+        let slots = courses[0].schedule_slots();
+        assert_eq!(slots[0], ScheduleSlot::new("当代中国经济".to_string(), "H4305".to_string(), 3, 3, 5, vec![6, 7, 8, 9, 10]));
+        assert_eq!(slots[1], ScheduleSlot::new("当代中国经济".to_string(), "H4305".to_string(), 3, 3, 5, vec![1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn test_parse_categories() {
+        let categories = parse_categories(&crate::fdu::test_support::fixture("xk_elect_page.html")).unwrap();
+        assert_eq!(categories.len(), 4);
+        assert_eq!(categories[0], Category { id: 7, name: "专业必修课程".to_string() });
+        assert_eq!(categories[3], Category { id: 15, name: "体育".to_string() });
+        parse_categories("<html>选课</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_query_by_category() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("POST /xk/stdElectCourse!defaultPage.action", vec![MockResponse::ok(&crate::fdu::test_support::fixture("xk_elect_page.html"))]);
+        // the server does not filter, so both come back
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![MockResponse::ok(
+            "var lessonJSONs = [{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003',courseTypeId:7},{id:698275,no:'ECON130128.01',name:'制度经济学',code:'ECON130128',courseTypeId:12}];\nvar lessonId2Counts = {'698241':{sc:70,lc:100},'698275':{sc:32,lc:32}};",
+        )]);
+
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.profile_id = Some(ProfileId(1234));
+        let elective = xk.list_categories().unwrap().into_iter().find(|category| category.name == "专业选修课程").unwrap();
+        // cached
+        xk.list_categories().unwrap();
+        assert_eq!(server.requests().len(), 1);
+
+        let courses = xk.query_course(&CourseQuery { category: Some(elective.id), ..Default::default() }).unwrap();
+        assert_eq!(courses.iter().map(|course| course.name.as_str()).collect::<Vec<_>>(), vec!["制度经济学"]);
+        assert!(server.requests()[1].ends_with("courseTypeId=12"));
+        // no category, no filter
+        assert_eq!(xk.query_course(&CourseQuery::default()).unwrap().len(), 2);
+        assert!(!server.requests()[2].contains("courseTypeId"));
+    }
+
+    #[test]
+    fn test_credit_hours() {
+        const COURSE: &str = "[{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003',credits:3.0,courseId:38081,period:54,weekHour:3.0,teachers:'郑辉'},{id:698275,no:'ECON130128.01',name:'制度经济学',code:'ECON130128',credits:2.5,period:36},{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064'}]";
+        let courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+        assert_eq!((courses[0].credits, courses[0].period, courses[0].week_hour), (Some(3.0), Some(54), Some(3.0)));
+        assert_eq!(courses[0].credit_hours(), "3 学分 / 54 学时 / 3 周学时");
+        assert_eq!(courses[1].credit_hours(), "2.5 学分 / 36 学时");
+        assert_eq!((courses[2].period, courses[2].week_hour), (None, None));
+        assert_eq!(courses[2].credit_hours(), "");
+    }
+
+    #[test]
+    fn test_credit_summary() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        const COURSE: &str = "[{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003',credits:3.0},{id:698251,no:'ECON130010.01',name:'当代中国经济',code:'ECON130010',credits:2.5},{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064'},{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003',credits:3.0}]";
+        let courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+        assert_eq!(courses[2].credits, None);
+        // 博弈论 has no credits, and 国际金融 comes twice
+        assert_eq!(credit_summary(&courses), (3, 5.5));
+        assert_eq!(credit_summary(&[]), (0, 0.0));
+
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!electedLessons.action", vec![MockResponse::ok(&format!("var electedLessons = {};", COURSE))]);
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.get_selected_credits().expect_err("expect no profile");
+        xk.profile_id = Some(ProfileId(1234));
+        assert_eq!(xk.get_selected_credits().unwrap(), (3, 5.5));
+    }
+
+    #[test]
+    fn test_parse_operation_results() {
+        const RESULT: &str = r#"<html><body><div style="width:85%;">
+            国际金融(ECON130003.01) 选课成功<br/>
+            博弈论(ECON130064.01) 选课失败：人数已满<br/>
+            税收学(ECON130042.01) 选课失败：与已选课程时间冲突<br/>
+        </div></body></html>"#;
+        let results = parse_operation_results(RESULT, &[CourseId(698241), CourseId(698266), CourseId(698260)]).unwrap();
+        assert_eq!(results[0], (CourseId(698241), OperationResult::Success));
+        assert_eq!(results[1], (CourseId(698266), OperationResult::Failure("博弈论(ECON130064.01)选课失败：人数已满".to_string())));
+        assert!(matches!(&results[2], (CourseId(698260), OperationResult::Failure(message)) if message.contains("冲突")));
+
+        parse_operation_results(RESULT, &[CourseId(1), CourseId(2), CourseId(3), CourseId(4)]).expect_err("expect error");
+    }
+
+    #[test]
+    fn test_filter_by_teacher() {
+        const COURSE: &str = "[{id:698251,no:'ECON130010.01',name:'当代中国经济',code:'ECON130010',teachers:'陈钊,王永钦,张晏'},{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064',teachers:'朱弘鑫'},{id:698260,no:'ECON130042.01',name:'税收学',code:'ECON130042',teachers:'余显财'}]";
+        let courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+        assert_eq!(courses[0].teachers, vec!["陈钊", "王永钦", "张晏"]);
+        assert_eq!(courses[1].teachers, vec!["朱弘鑫"]);
+
+        let filtered = filter_by_teacher(courses.clone(), "王永");
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name, "当代中国经济");
+        assert!(filter_by_teacher(courses.clone(), "不存在").is_empty());
+        assert_eq!(filter_by_teacher(courses, "").len(), 3);
+
+        let query = CourseQuery { teacher: "张晏".to_string(), ..Default::default() };
+        assert!(serde_json::to_string(&query).unwrap().contains(r#""teacherName":"张晏""#));
+    }
+
+    #[test]
+    fn test_search_courses() {
+        const COURSE: &str = "[{id:698301,no:'ECON130213.01',name:'计量经济学',code:'ECON130213',teachers:'张三'},{id:698302,no:'ECON130213.02',name:'计量经济学',code:'ECON130213',teachers:'李四'},{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064',teachers:'朱弘鑫'},{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003'}]";
+        let mut xk = XK::new();
+        // cached, so nothing is fetched
+        xk.courses = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+
+        let ids = |courses: Vec<Course>| courses.iter().map(|course| course.id.value()).collect::<Vec<_>>();
+        assert_eq!(ids(xk.search_courses("计量").unwrap()), vec![698301, 698302]);
+        assert_eq!(ids(xk.search_courses("econ130064").unwrap()), vec![698266]);
+        assert_eq!(ids(xk.search_courses(" 弘鑫 ").unwrap()), vec![698266]);
+        assert_eq!(ids(xk.search_courses("金融").unwrap()), vec![698241]);
+        assert!(xk.search_courses("线性代数").unwrap().is_empty());
+        assert_eq!(xk.search_courses("").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_parse_profiles() {
+        assert_eq!(parse_profiles(r#"<form><input type="hidden" name="electionProfile.id" value="1234"/></form>"#).unwrap(), vec![(ProfileId(1234), Round::Other)]);
+        parse_profiles("<form>当前没有开放的选课</form>").expect_err("expect error");
+        parse_profiles(r#"<input type="hidden" value=""/>"#).expect_err("expect error");
+        parse_profiles(r#"<form><input type="hidden" name="electionProfile.id" value=""/></form>"#).expect_err("expect error");
+
+        const PROFILES: &str = r#"<html><body>
+            <form method="post"><h3>2022-2023学年2学期 本科生预选</h3><input type="hidden" name="electionProfile.id" value="1233"/></form>
+            <form method="post"><h3>2022-2023学年2学期 本科生补退选</h3><input type="hidden" name="electionProfile.id" value="1235"/></form>
+            <form method="post"><h3>2022-2023学年2学期 本科生正选</h3><input type="hidden" name="electionProfile.id" value="1234"/></form>
+        </body></html>"#;
+        assert_eq!(parse_profiles(PROFILES).unwrap(), vec![(ProfileId(1233), Round::PreSelection), (ProfileId(1235), Round::AddDrop), (ProfileId(1234), Round::Formal)]);
+    }
+
+    #[test]
+    fn test_find_conflict() {
+        const COURSE: &str = "[{id:1,no:'A.01',name:'A',code:'A',arrangeInfo:[{weekDay:3,weekState:'01111100000000000000000000000000000000000000000000000',startUnit:3,endUnit:5,weekStateDigest:'1-5',rooms:'H4305'}]},{id:2,no:'B.01',name:'B',code:'B',arrangeInfo:[{weekDay:3,weekState:'00000011111000000000000000000000000000000000000000000',startUnit:4,endUnit:5,weekStateDigest:'6-10',rooms:'H4305'}]},{id:3,no:'C.01',name:'C',code:'C',arrangeInfo:[{weekDay:3,weekState:'01000000000000000000000000000000000000000000000000000',startUnit:5,endUnit:6,weekStateDigest:'1',rooms:'H4305'}]}]";
+        let courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+
+        // same units, but in disjoint weeks
+        assert!(find_conflict(&courses[1], &courses[..1]).is_none());
+        // unit 5 of week 1 is taken by both
+        assert_eq!(find_conflict(&courses[2], &courses[..2]).unwrap().id, CourseId(1));
+    }
+
+    #[test]
+    fn test_course_dedup() {
+        use std::collections::HashSet;
+
+        const COURSE: &str = "[{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064',amount:{sc:93,lc:93}},{id:698260,no:'ECON130042.01',name:'税收学',code:'ECON130042'},{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064',amount:{sc:92,lc:93}}]";
+        let courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+        assert_eq!(courses[0], courses[2]);
+        assert_ne!(courses[0], courses[1]);
+
+        let set: HashSet<Course> = courses.into_iter().collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.iter().any(|course| course.id == CourseId(698260)));
+    }
+
+    #[test]
+    fn test_conflicts_with() {
+        const COURSE: &str = "[{id:1,no:'A.01',name:'A',code:'A',arrangeInfo:[{weekDay:3,weekState:'01111100000000000000000000000000000000000000000000000',startUnit:3,endUnit:5,weekStateDigest:'1-5',rooms:'H4305'}]},{id:2,no:'B.01',name:'B',code:'B',arrangeInfo:[{weekDay:1,weekState:'01000000000000000000000000000000000000000000000000000',startUnit:1,endUnit:2,weekStateDigest:'1',rooms:'H2101'},{weekDay:3,weekState:'00010000000000000000000000000000000000000000000000000',startUnit:5,endUnit:6,weekStateDigest:'3',rooms:'H2101'}]},{id:3,no:'C.01',name:'C',code:'C',arrangeInfo:[{weekDay:3,weekState:'00000011111000000000000000000000000000000000000000000',startUnit:3,endUnit:5,weekStateDigest:'6-10',rooms:'H4305'}]},{id:4,no:'D.01',name:'D',code:'D',arrangeInfo:[{weekDay:3,weekState:'01111100000000000000000000000000000000000000000000000',startUnit:6,endUnit:8,weekStateDigest:'1-5',rooms:'H4305'}]}]";
+        let courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+        let (a, b, c, d) = (&courses[0], &courses[1], &courses[2], &courses[3]);
+
+        // same slot and same weeks, through the second arrangement of B
+        assert!(a.conflicts_with(b));
+        assert!(b.conflicts_with(a));
+        // same slot, disjoint weeks
+        assert!(!a.conflicts_with(c));
+        // adjacent units
+        assert!(!a.conflicts_with(d));
+    }
+
+    #[test]
+    fn test_parse_amounts() {
+        const AMOUNT: &str = "{'698241':{sc:70,lc:100},'698246':{sc:89,lc:100},'698257':{sc:74,lc:85},'698251':{sc:85,lc:85},'698260':{sc:39,lc:40},'698266':{sc:93,lc:93},'698275':{sc:32,lc:32}}";
+        let amounts = parse_amounts(&normalize_json(AMOUNT)).unwrap();
+        assert_eq!(amounts.len(), 7);
+        let amount = &amounts[&CourseId(698251)];
+        assert_eq!((amount.selected, amount.total), (85, 85));
+        assert!(!amounts.contains_key(&CourseId(1)));
+
+        const COURSE: &str = "[{id:698251,no:'ECON130010.01',name:'当代中国经济',code:'ECON130010'},{id:1,no:'A.01',name:'A',code:'A'}]";
+        let mut courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+        join_amounts(&mut courses, amounts);
+        assert_eq!((courses[0].amount.selected, courses[0].amount.total), (85, 85));
+        assert_eq!((courses[1].amount.selected, courses[1].amount.total), (0, 0));
+
+        parse_amounts(r#"{"not a number":{"sc":1,"lc":2}}"#).expect_err("expect error");
+    }
+
+    #[test]
+    fn test_split_by_availability() {
+        const COURSE: &str = "[{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003'},{id:698251,no:'ECON130010.01',name:'当代中国经济',code:'ECON130010'},{id:698260,no:'ECON130042.01',name:'税收学',code:'ECON130042'},{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064'}]";
+        const AMOUNT: &str = "{'698241':{sc:70,lc:100},'698251':{sc:85,lc:85},'698260':{sc:39,lc:40},'698266':{sc:93,lc:93}}";
+        let mut courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+        join_amounts(&mut courses, parse_amounts(&normalize_json(AMOUNT)).unwrap());
+        assert_eq!(courses[0].remaining(), 30);
+
+        let names = |courses: &[Course]| courses.iter().map(|course| course.name.clone()).collect::<Vec<_>>();
+        let sorted = |by: CourseSort| {
+            let mut courses = courses.clone();
+            sort_courses(&mut courses, by);
+            names(&courses)
+        };
+        assert_eq!(sorted(CourseSort::Remaining), vec!["国际金融", "税收学", "当代中国经济", "博弈论"]);
+        assert_eq!(sorted(CourseSort::Fullness), vec!["国际金融", "税收学", "当代中国经济", "博弈论"]);
+        assert_eq!(sorted(CourseSort::Code), vec!["国际金融", "当代中国经济", "税收学", "博弈论"]);
+        assert_eq!(sorted(CourseSort::Name).len(), 4);
+        // the default order is kept
+        assert_eq!(names(&courses), vec!["国际金融", "当代中国经济", "税收学", "博弈论"]);
+
+        let (available, full) = split_by_availability(courses);
+        assert_eq!(available.iter().map(|course| course.name.as_str()).collect::<Vec<_>>(), vec!["国际金融", "税收学"]);
+        assert_eq!(full.iter().map(|course| course.name.as_str()).collect::<Vec<_>>(), vec!["当代中国经济", "博弈论"]);
+    }
+
+    #[test]
+    fn test_get_course_by_id() {
+        const COURSE: &str = "[{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003'},{id:698251,no:'ECON130010.01',name:'当代中国经济',code:'ECON130010'}]";
+        let mut xk = XK::new();
+        // cached, so nothing is fetched
+        xk.courses = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+
+        assert_eq!(xk.get_course_by_id(CourseId(698251)).unwrap().unwrap().name, "当代中国经济");
+        assert!(xk.get_course_by_id(CourseId(1)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_not_withdrawable() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        const COURSE: &str = "[{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003',withdrawable:false},{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064',withdrawable:true},{id:698260,no:'ECON130042.01',name:'税收学',code:'ECON130042'}]";
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!batchOperator.action", vec![MockResponse::ok("<div>退课成功<br/></div>")]);
+
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.profile_id = Some(ProfileId(1234));
+        xk.courses = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+        assert_eq!(xk.courses.iter().map(Course::is_withdrawable).collect::<Vec<_>>(), vec![false, true, true]);
+
+        let e = xk.operate_course(CourseId(698241), false).expect_err("expect error");
+        assert!(e.to_string().contains("国际金融(ECON130003.01) cannot be dropped"));
+        assert!(server.requests().is_empty());
+
+        // the others are sent as usual
+        assert!(xk.operate_course(CourseId(698266), false).unwrap());
+        assert!(xk.operate_course(CourseId(698260), false).unwrap());
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_select_section() {
+        const COURSE: &str = "[{id:698301,no:'ECON130213.01',name:'计量经济学',code:'ECON130213',teachers:'张三'},{id:698302,no:'ECON130213.02',name:'计量经济学',code:'ECON130213',teachers:'李四'},{id:698241,no:'ECON130003.01',name:'国际金融',code:'ECON130003'}]";
+        let courses: Vec<Course> = serde_json::from_str(&normalize_json(COURSE)).unwrap();
+        let mut xk = XK::new();
+
+        // the lesson no wins over the code
+        let query = CourseQuery { no: "ECON130213.02".to_string(), code: "ECON130213".to_string(), ..Default::default() };
+        assert_eq!(xk.get_id(&query, courses.clone()).unwrap(), CourseId(698302));
+
+        let query = CourseQuery { code: "ECON130213".to_string(), ..Default::default() };
+        let e = xk.get_id(&query, courses.clone()).expect_err("expect ambiguous selection");
+        assert!(e.is_ambiguous_selection());
+        assert!(e.to_string().contains("ECON130213.01(张三), ECON130213.02(李四)"));
+
+        // a single section is fine with the code alone
+        let query = CourseQuery { code: "ECON130003".to_string(), ..Default::default() };
+        assert_eq!(xk.get_id(&query, courses.clone()).unwrap(), CourseId(698241));
+        // the sections coming back twice are listed once
+        let query = CourseQuery { code: "ECON130213".to_string(), ..Default::default() };
+        let e = xk.get_id(&query, [courses.clone(), courses.clone()].concat()).expect_err("expect ambiguous selection");
+        assert!(e.to_string().ends_with("ECON130213.01(张三), ECON130213.02(李四)"), "{}", e);
+        let query = CourseQuery { no: "ECON130213.03".to_string(), ..Default::default() };
+        xk.get_id(&query, courses).expect_err("expect not found");
+    }
+
+    #[test]
+    fn test_grab_many() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        const COURSES: &str = "var lessonJSONs = [{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064'},{id:698260,no:'ECON130042.01',name:'税收学',code:'ECON130042'}];";
+        let server = MockServer::start();
+        // 博弈论 has a seat from the second poll on, while 税收学 stays full
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![
+            MockResponse::ok(&format!("{}\nvar lessonId2Counts = {{'698266':{{sc:93,lc:93}},'698260':{{sc:40,lc:40}}}};", COURSES)),
+            MockResponse::ok(&format!("{}\nvar lessonId2Counts = {{'698266':{{sc:92,lc:93}},'698260':{{sc:40,lc:40}}}};", COURSES)),
+        ]);
+        server.route("/xk/stdElectCourse!batchOperator.action", vec![MockResponse::ok("<div>博弈论(ECON130064.01) 选课成功<br/></div>")]);
+
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.profile_id = Some(ProfileId(1234));
+        let queries = [
+            CourseQuery { no: "ECON130064.01".to_string(), ..Default::default() },
+            CourseQuery { no: "ECON130042.01".to_string(), ..Default::default() },
+        ];
+        let results = xk.grab_many(&queries, 3, Duration::ZERO, None, None).unwrap();
+        assert_eq!(results.iter().map(|(query, selected)| (query.no.as_str(), *selected)).collect::<Vec<_>>(),
+                   vec![("ECON130064.01", true), ("ECON130042.01", false)]);
+
+        let lines = server.request_lines();
+        assert_eq!(lines.iter().filter(|line| line.starts_with("POST /xk/stdElectCourse!queryLesson.action")).count(), 3);
+        // every query is sent as ajax, from the election page
+        let requests = server.requests();
+        assert!(requests.iter().filter(|r| r.starts_with("POST /xk/stdElectCourse!queryLesson.action")).all(|r| {
+            let r = r.to_ascii_lowercase();
+            r.contains("\r\nx-requested-with: xmlhttprequest\r\n") && r.contains("\r\nreferer: ") && r.contains("/xk/stdelectcourse!defaultpage.action\r\n")
+        }));
+        // only the course with a seat is selected, and only once
+        let selects: Vec<&String> = requests.iter().filter(|r| r.starts_with("POST /xk/stdElectCourse!batchOperator.action")).collect();
+        assert_eq!(selects.len(), 1);
+        assert!(selects[0].contains("operator0=698266"));
+        assert!(!selects[0].contains("698260"));
+    }
+
+    #[test]
+    fn test_grab_rate_limited() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        const BLOCKED: &str = "<html><body><h1>403 Forbidden</h1><p>很抱歉，您的访问被拦截</p></body></html>";
+        const COURSES: &str = "var lessonJSONs = [{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064'}];\nvar lessonId2Counts = {'698266':{sc:92,lc:93}};";
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![MockResponse::new(403, BLOCKED), MockResponse::ok(COURSES)]);
+        server.route("/xk/stdElectCourse!batchOperator.action", vec![MockResponse::ok("<div>博弈论(ECON130064.01) 选课成功<br/></div>")]);
+
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.profile_id = Some(ProfileId(1234));
+        let query = CourseQuery { no: "ECON130064.01".to_string(), ..Default::default() };
+        let start = Instant::now();
+        assert!(xk.grab(&query, 3, Duration::ZERO, None, None).unwrap());
+        // backed off after being blocked, even though the interval is zero
+        assert!(start.elapsed() >= RATE_LIMIT_MIN_BACKOFF, "{:?}", start.elapsed());
+        assert_eq!(server.request_lines().iter().filter(|line| line.starts_with("POST /xk/stdElectCourse!queryLesson.action")).count(), 2);
+
+        // blocked until the polls run out
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![MockResponse::new(403, BLOCKED)]);
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.profile_id = Some(ProfileId(1234));
+        assert!(xk.grab(&query, 1, Duration::ZERO, None, None).expect_err("expect rate limited").is_rate_limited());
+        // nor is a single query parsed as courses
+        assert!(xk.query_course(&query).expect_err("expect rate limited").is_rate_limited());
+    }
+
+    #[test]
+    fn test_close() {
+        use crate::fdu::test_support::{logged_in_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("/xk/logout.action", vec![MockResponse::new(500, "<html>error</html>")]);
+        let mut xk = XK::new_from_fdu(logged_in_fdu(&server, &["https://xk.fudan.edu.cn"], &[]));
+        xk.profile_id = Some(ProfileId(1234));
+        // xk fails to log out, but UIS is still logged out of
+        xk.close().expect_err("expect xk logout error");
+        let lines = server.request_lines();
+        assert_eq!(lines[lines.len() - 2..], ["GET /xk/logout.action", "GET /authserver/logout?service="]);
+    }
+
+    #[test]
+    fn test_submit_wishlist() {
+        use crate::fdu::test_support::{fixture, MockResponse, MockServer};
+        use crate::fdu::mock::mock_fdu;
+
+        const COURSES: &str = "var lessonJSONs = [{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064'},{id:698260,no:'ECON130042.01',name:'税收学',code:'ECON130042'}];\nvar lessonId2Counts = {'698266':{sc:93,lc:93},'698260':{sc:40,lc:40}};";
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![MockResponse::ok(COURSES)]);
+        server.route("/xk/stdElectCourse!saveWishList.action", vec![
+            MockResponse::ok(&fixture("xk_wishlist_result.html")),
+            MockResponse::ok("<div>博弈论(ECON130064.01) 志愿提交失败：超过志愿上限<br/></div>"),
+            MockResponse::new(403, "<html><p>很抱歉，您的访问被拦截</p></html>"),
+        ]);
+
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.submit_wishlist(&[]).expect_err("expect not logged in");
+        xk.profile_id = Some(ProfileId(1234));
+        xk.round = Some(Round::Formal);
+        // the second wish first
+        let ranked = [
+            CourseQuery { name: "税收学".to_string(), ..Default::default() },
+            CourseQuery { no: "ECON130064.01".to_string(), ..Default::default() },
+        ];
+        xk.submit_wishlist(&ranked).unwrap();
+        let requests = server.requests();
+        let submission = requests.iter().find(|r| r.starts_with("POST /xk/stdElectCourse!saveWishList.action?profileId=1234")).unwrap();
+        assert!(submission.ends_with("wish0=698260%3A1&wish1=698266%3A2"), "{}", submission);
+
+        let e = xk.submit_wishlist(&ranked[1..]).expect_err("expect rejected");
+        assert!(e.to_string().contains("超过志愿上限"));
+        // the firewall page is not taken as the results
+        assert!(xk.submit_wishlist(&ranked[1..]).expect_err("expect blocked").is_rate_limited());
+        xk.submit_wishlist(&[ranked[1].clone(), ranked[1].clone()]).expect_err("expect duplicate");
+
+        xk.round = Some(Round::AddDrop);
+        let e = xk.submit_wishlist(&ranked).expect_err("expect no wish list");
+        assert!(e.to_string().contains("does not accept wish lists"));
+    }
+
+    #[test]
+    fn test_grab_cancel() {
+        use std::time::Instant;
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        // always full
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![MockResponse::ok(
+            "var lessonJSONs = [{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064'}];\nvar lessonId2Counts = {'698266':{sc:93,lc:93}};",
+        )]);
+
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.profile_id = Some(ProfileId(1234));
+        let query = CourseQuery { no: "ECON130064.01".to_string(), ..Default::default() };
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        // cancel once the first poll is seen
+        let flag = Arc::clone(&cancel);
+        let lines = || server.request_lines().iter().filter(|line| line.starts_with("POST /xk/stdElectCourse!queryLesson.action")).count();
+        let start = Instant::now();
+        let e = thread::scope(|scope| {
+            scope.spawn(|| {
+                while lines() == 0 {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                flag.store(true, Ordering::SeqCst);
+            });
+            xk.grab(&query, 100, Duration::from_secs(10), Some(Arc::clone(&cancel)), None).expect_err("expect cancelled")
+        });
+        assert!(e.is_cancelled());
+        // it does not wait out the 10s interval
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(lines(), 1);
+    }
+
+    #[test]
+    fn test_grab_budget() {
+        use std::time::Instant;
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        const FULL: &str = "var lessonJSONs = [{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064'}];\nvar lessonId2Counts = {'698266':{sc:93,lc:93}};";
+        let query = CourseQuery { no: "ECON130064.01".to_string(), ..Default::default() };
+
+        // always full, with a long interval
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![MockResponse::ok(FULL)]);
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.profile_id = Some(ProfileId(1234));
+        let start = Instant::now();
+        let e = xk.grab(&query, 100, Duration::from_secs(10), None, Some(Duration::from_millis(300))).expect_err("expect timeout");
+        assert!(e.is_timeout(), "{}", e);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(xk.deadline.is_none());
+
+        // the server hangs in the second poll
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![
+            MockResponse::ok(FULL),
+            MockResponse::ok(FULL).delay(Duration::from_secs(10)),
+        ]);
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.profile_id = Some(ProfileId(1234));
+        let start = Instant::now();
+        let e = xk.grab(&query, 100, Duration::ZERO, None, Some(Duration::from_millis(500))).expect_err("expect timeout");
+        assert!(e.is_timeout(), "{}", e);
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_profile_id() {
+        assert_eq!(ProfileId::parse("1234").unwrap(), ProfileId(1234));
+        ProfileId::parse("0").expect_err("expect error");
+        ProfileId::parse("-1").expect_err("expect error");
+        ProfileId::parse("").expect_err("expect error");
+
+        // nothing is sent without a profile
+        let xk = XK::new();
+        assert!(xk.query_course(&CourseQuery::default()).expect_err("expect error").is_login_error());
+        assert!(xk.operate_course(CourseId(698266), true).expect_err("expect error").is_login_error());
+    }
+
+    #[test]
+    fn test_round() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        const PROFILES: &str = r#"<form><h3>2022-2023学年2学期 本科生正选</h3><input type="hidden" name="electionProfile.id" value="1234"/></form>
+            <form><h3>2022-2023学年2学期 本科生预选</h3><input type="hidden" name="electionProfile.id" value="1233"/></form>"#;
+        let server = MockServer::start();
+        server.route("POST /xk/login.action", vec![MockResponse::redirect(&server.url("/xk/home.action"))]);
+        server.route("/xk/home.action", vec![MockResponse::ok("<html>选课系统</html>")]);
+        server.route("GET /xk/stdElectCourse!defaultPage.action", vec![MockResponse::ok(PROFILES)]);
+        server.route("POST /xk/stdElectCourse!defaultPage.action", vec![MockResponse::ok("<html>选课</html>")]);
+        server.route("/xk/stdElectCourse!batchOperator.action", vec![MockResponse::ok("<div>博弈论(ECON130064.01) 选课成功<br/></div>")]);
+
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        assert_eq!(xk.round(), None);
+        xk.login("uid", "pwd").expect("login error");
+        // the latest round open is picked
+        assert_eq!((xk.round(), xk.profile_id), (Some(Round::Formal), Some(ProfileId(1234))));
+
+        // no drop in 正选, and nothing is sent
+        assert!(!xk.operate_course(CourseId(698266), false).unwrap());
+        assert!(xk.operate_course(CourseId(698266), true).unwrap());
+        assert_eq!(server.request_lines().iter().filter(|line| line.starts_with("POST /xk/stdElectCourse!batchOperator.action")).count(), 1);
+
+        xk.set_profile(Round::PreSelection).unwrap();
+        assert_eq!((xk.round(), xk.profile_id), (Some(Round::PreSelection), Some(ProfileId(1233))));
+        assert!(xk.operate_course(CourseId(698266), false).unwrap());
+        xk.set_profile(Round::AddDrop).expect_err("expect no profile");
+    }
+
+    #[test]
+    fn test_verify_selected() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("POST /xk/login.action", vec![MockResponse::redirect(&server.url("/xk/home.action"))]);
+        server.route("/xk/home.action", vec![MockResponse::ok("<html>选课系统</html>")]);
+        server.route("GET /xk/stdElectCourse!defaultPage.action", vec![MockResponse::ok(
+            r#"<form><h3>2022-2023学年2学期 本科生补退选</h3><input type="hidden" name="electionProfile.id" value="1235"/></form>"#)]);
+        server.route("POST /xk/stdElectCourse!defaultPage.action", vec![MockResponse::ok("<html>选课</html>")]);
+        server.route("/xk/stdElectCourse!batchOperator.action", vec![MockResponse::ok("<div>博弈论(ECON130064.01) 选课成功<br/></div>")]);
+        // the select is lost at first, and only the course selected before is listed
+        server.route("/xk/stdElectCourse!electedLessons.action", vec![
+            MockResponse::ok("var electedLessons = [{id:698241,no:'ECON130003.01',code:'ECON130003',name:'国际金融',teachers:'张三',arrangeInfo:[]}];"),
+            MockResponse::ok("var electedLessons = [{id:698241,no:'ECON130003.01',code:'ECON130003',name:'国际金融'},{id:698266,no:'ECON130064.01',code:'ECON130064',name:'博弈论'}];"),
+            MockResponse::ok("var electedLessons = [];"),
+        ]);
+
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.login("uid", "pwd").expect("login error");
+        assert!(xk.operate_course(CourseId(698266), true).unwrap());
+        assert!(!xk.verify_selected(CourseId(698266)).unwrap());
+        assert!(xk.verify_selected(CourseId(698266)).unwrap());
+        assert!(!xk.verify_selected(CourseId(698241)).unwrap());
+        assert!(server.request_lines().iter().any(|line| line.starts_with("GET /xk/stdElectCourse!electedLessons.action?profileId=1235")));
+
+        parse_selected_courses("<html>请先登录</html>").expect_err("expect error");
+    }
+
+    #[test]
+    fn test_login_delay() {
+        use crate::fdu::mock::{MockResponse, MockServer};
+
+        let server = MockServer::start();
+        server.route("POST /xk/login.action", vec![MockResponse::redirect(&server.url("/xk/home.action"))]);
+        server.route("/xk/home.action", vec![MockResponse::ok("<html>选课系统</html>")]);
+        server.route("GET /xk/stdElectCourse!defaultPage.action", vec![MockResponse::ok(
+            r#"<form><h3>2022-2023学年2学期 本科生正选</h3><input type="hidden" name="electionProfile.id" value="1234"/></form>"#)]);
+        server.route("POST /xk/stdElectCourse!defaultPage.action", vec![MockResponse::ok("<html>选课</html>")]);
+        let fdu = |delay: Duration| Fdu::builder()
+            .endpoint("https://xk.fudan.edu.cn", &server.url(""))
+            .request_delay("https://xk.fudan.edu.cn", delay)
+            .build().unwrap();
+
+        let start = Instant::now();
+        XK::new_from_fdu(fdu(Duration::ZERO)).login("uid", "pwd").unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500), "{:?}", start.elapsed());
+
+        let start = Instant::now();
+        XK::new_from_fdu(fdu(Duration::from_millis(400))).login("uid", "pwd").unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(800), "{:?}", start.elapsed());
+    }
+
+    #[test]
+    fn test_parse_election_window() {
+        use chrono::NaiveDate;
+        use crate::fdu::test_support::fixture;
+
+        let html = fixture("xk_default_page.html");
+        let time = |d, h, m| NaiveDate::from_ymd_opt(2023, 3, d).unwrap().and_hms_opt(h, m, 0).unwrap();
+        assert_eq!(parse_election_window(&html, ProfileId(1235)).unwrap(), Some((time(1, 12, 0), time(3, 17, 0))));
+        // not published for 正选
+        assert_eq!(parse_election_window(&html, ProfileId(1234)).unwrap(), None);
+        // no such profile
+        assert_eq!(parse_election_window(&html, ProfileId(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_download_catalog() {
+        use crate::fdu::mock::{MockResponse, MockServer};
+
+        // pages of 200, 200 and 1 courses, the second repeating the last course of the first
+        let page = |ids: std::ops::Range<i32>| {
+            let courses: Vec<String> = ids.clone().map(|id| format!("{{id:{},no:'COURSE{}.01',name:'课程{}',code:'COURSE{}'}}", id, id, id, id)).collect();
+            let amounts: Vec<String> = ids.map(|id| format!("'{}':{{sc:{},lc:100}}", id, id % 100)).collect();
+            MockResponse::ok(&format!("var lessonJSONs = [{}];\nvar lessonId2Counts = {{{}}};", courses.join(","), amounts.join(",")))
+        };
+        let xk_on = |server: &MockServer| {
+            let fdu = Fdu::builder()
+                .endpoint("https://xk.fudan.edu.cn", &server.url(""))
+                .request_delay("https://xk.fudan.edu.cn", Duration::ZERO)
+                .build().unwrap();
+            let mut xk = XK::new_from_fdu(fdu);
+            xk.profile_id = Some(ProfileId(1234));
+            xk
+        };
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![page(0..200), page(199..399), page(399..400)]);
+        let mut xk = xk_on(&server);
+
+        let mut progress = Vec::new();
+        let catalog = xk.download_catalog_with_progress(|pages, courses| progress.push((pages, courses))).unwrap();
+        assert_eq!(catalog.len(), 400);
+        assert_eq!(progress, vec![(1, 200), (2, 399), (3, 400)]);
+        assert_eq!((catalog[399].id, catalog[399].remaining()), (CourseId(399), 1));
+        let lines = server.request_lines();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].contains("pageNo=3&pageSize=200"), "{}", lines[2]);
+        // cached for `get_courses`
+        assert_eq!(xk.get_courses().unwrap().len(), 400);
+        assert_eq!(server.requests().len(), 3);
+
+        // xk ignoring the page number, giving the first page again and again
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![page(0..200)]);
+        let mut xk = xk_on(&server);
+        assert_eq!(xk.download_catalog().unwrap().len(), 200);
+        assert_eq!(server.requests().len(), 2);
+
+        // new full pages without end
+        let server = MockServer::start();
+        let start = |n: usize| (n * CATALOG_PAGE_SIZE) as i32;
+        server.route("/xk/stdElectCourse!queryLesson.action", (0..=CATALOG_MAX_PAGES).map(|n| page(start(n)..start(n + 1))).collect());
+        let mut xk = xk_on(&server);
+        let e = xk.download_catalog().expect_err("expect error");
+        assert!(e.to_string().contains("more than"), "{}", e);
+        assert_eq!(server.requests().len(), CATALOG_MAX_PAGES);
+    }
+
+    #[test]
+    fn test_already_selected() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+        use crate::fdu::test_support::fixture;
+
+        let results = parse_operation_results(&fixture("xk_already_selected.html"), &[CourseId(698266), CourseId(698241)]).unwrap();
+        assert_eq!(results, vec![
+            (CourseId(698266), OperationResult::AlreadySelected("博弈论(ECON130064.01)选课失败：该课程已经选过".to_string())),
+            (CourseId(698241), OperationResult::Success),
+        ]);
+
+        // the grab stops at once instead of polling on
+        let server = MockServer::start();
+        server.route("/xk/stdElectCourse!queryLesson.action", vec![MockResponse::ok(
+            "var lessonJSONs = [{id:698266,no:'ECON130064.01',name:'博弈论',code:'ECON130064'}];\nvar lessonId2Counts = {'698266':{sc:92,lc:93}};")]);
+        server.route("/xk/stdElectCourse!batchOperator.action", vec![MockResponse::ok("<div>博弈论(ECON130064.01) 选课失败：该课程已经选过<br/></div>")]);
+        let mut xk = XK::new_from_fdu(mock_fdu(&server, &["https://xk.fudan.edu.cn"]));
+        xk.profile_id = Some(ProfileId(1234));
+        let query = CourseQuery { no: "ECON130064.01".to_string(), ..Default::default() };
+        assert!(xk.grab(&query, 5, Duration::ZERO, None, None).unwrap());
+        assert_eq!(server.request_lines().iter().filter(|line| line.starts_with("POST /xk/stdElectCourse!batchOperator.action")).count(), 1);
+        assert!(xk.operate_course(CourseId(698266), true).unwrap());
+    }
+}
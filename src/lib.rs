@@ -2,11 +2,19 @@
 mod fdu;
 mod error;
 
+use std::cell::RefCell;
 use std::ffi::{CStr, CString};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 // Provides a lot of C-equivalent types.
 use libc::*;
 
+use crate::fdu::fdu::{Account, Fdu};
+use crate::fdu::fdu_daily;
+use crate::fdu::xk::{Course, XK};
+
 // no_mangle tells Rust compiler not to mangle the name of the function and keep the original name.
 //
 // A flag starting with # is a macro. You can roughly think of it as a preprocessor directive, like
@@ -73,6 +81,386 @@ pub extern "C" fn get_url(url: *const c_char) -> *mut c_char {
 }
 
 
+/// A handle to stop a course grab loop from another thread, e.g. when the user taps "stop" in the UI.
+/// It is the cancel flag `fdu_xk_grab` checks between polls, see `XK::grab_many`.
+///
+/// # Safety
+/// The handle belongs to C: Rust will not free it until it comes back to `fdu_grab_handle_free`,
+/// so do call it exactly once when the loop is over, and do not use the handle after that.
+#[no_mangle]
+pub extern "C" fn fdu_grab_handle_new() -> *const AtomicBool {
+    Arc::into_raw(Arc::new(AtomicBool::new(false)))
+}
+
+/// Ask the grab loop of `handle` to stop. It returns a `Cancelled` error soon after.
+///
+/// # Safety
+/// `handle` must come from `fdu_grab_handle_new` and not be freed yet. A null handle is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_grab_cancel(handle: *const AtomicBool) {
+    if handle.is_null() { return; }
+    (*handle).store(true, Ordering::SeqCst);
+}
+
+/// # Safety
+/// `handle` must come from `fdu_grab_handle_new`, and must not be used any more. A null handle is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_grab_handle_free(handle: *const AtomicBool) {
+    if handle.is_null() { return; }
+    drop(Arc::from_raw(handle));
+}
+
+thread_local! {
+    // The message of the last error on this thread, see `fdu_last_error`.
+    // It is per thread, so that a call on another thread does not overwrite it before it is read.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl ToString) {
+    // a C string cannot hold a NUL
+    let message = CString::new(message.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// The message of the last error on the calling thread, e.g. after a function returned -1, or null if there is none.
+/// Free it with `free_string`.
+#[no_mangle]
+pub extern "C" fn fdu_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last| last.borrow().clone()).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// A client handle, i.e. a boxed `Fdu` that C holds as an opaque pointer.
+///
+/// # Safety
+/// Like the grab handle, it belongs to C until it comes back to `fdu_free`, which must be called exactly once.
+/// It is only to be passed to the `fdu_*` functions taking a client handle, not to the `fdu_xk_*` ones.
+#[no_mangle]
+pub extern "C" fn fdu_new() -> *mut c_void {
+    Box::into_raw(Box::new(Fdu::new())) as *mut c_void
+}
+
+// Turn a handle back into the client, setting the last error if it is null.
+unsafe fn fdu_from_handle<'a>(handle: *mut c_void) -> Option<&'a mut Fdu> {
+    let fdu = (handle as *mut Fdu).as_mut();
+    if fdu.is_none() {
+        set_last_error("null handle");
+    }
+    fdu
+}
+
+/// Log in to UIS. Returns 0 on success, and -1 on error, see `fdu_last_error`.
+///
+/// # Safety
+/// `handle` must come from `fdu_new` and not be freed yet. `uid` and `pwd` must be NUL-terminated UTF-8 strings.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_login(handle: *mut c_void, uid: *const c_char, pwd: *const c_char) -> c_int {
+    let fdu = match fdu_from_handle(handle) {
+        Some(fdu) => fdu,
+        None => return -1,
+    };
+    let (uid, pwd) = match credentials_from_c(uid, pwd) {
+        Some(credentials) => credentials,
+        None => return -1,
+    };
+    match fdu.login(uid, pwd) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+// Read the uid and password passed from C, setting the last error if either is null or not UTF-8.
+unsafe fn credentials_from_c<'a>(uid: *const c_char, pwd: *const c_char) -> Option<(&'a str, &'a str)> {
+    if uid.is_null() || pwd.is_null() {
+        set_last_error("null uid or password");
+        return None;
+    }
+    match (CStr::from_ptr(uid).to_str(), CStr::from_ptr(pwd).to_str()) {
+        (Ok(uid), Ok(pwd)) => Some((uid, pwd)),
+        _ => {
+            set_last_error("uid or password is not valid UTF-8");
+            None
+        }
+    }
+}
+
+/// Whether the daily report (平安复旦) is ticked today: 1 if it is, 0 if not, and -1 on error, see `fdu_last_error`.
+///
+/// # Safety
+/// `handle` must come from `fdu_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_daily_has_tick(handle: *mut c_void) -> c_int {
+    let fdu = match fdu_from_handle(handle) {
+        Some(fdu) => fdu,
+        None => return -1,
+    };
+    match fdu_daily::has_tick(fdu) {
+        Ok(true) => 1,
+        Ok(false) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// # Safety
+/// `handle` must come from `fdu_new`, and must not be used any more. A null handle is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_free(handle: *mut c_void) {
+    if handle.is_null() { return; }
+    drop(Box::from_raw(handle as *mut Fdu));
+}
+
+/// An xk handle, i.e. a boxed `XK`. xk has its own login, so it is a handle apart from the `fdu_new` one.
+///
+/// # Safety
+/// It belongs to C until it comes back to `fdu_xk_free`, which must be called exactly once.
+/// It is only to be passed to the `fdu_xk_*` functions, not to the ones taking a client handle.
+#[no_mangle]
+pub extern "C" fn fdu_xk_new() -> *mut c_void {
+    Box::into_raw(Box::new(XK::new())) as *mut c_void
+}
+
+unsafe fn xk_from_handle<'a>(handle: *mut c_void) -> Option<&'a mut XK> {
+    let xk = (handle as *mut XK).as_mut();
+    if xk.is_none() {
+        set_last_error("null handle");
+    }
+    xk
+}
+
+/// Log in to xk, entering the election profile of the latest round open. Returns 0 on success, and -1 on error,
+/// see `fdu_last_error`.
+///
+/// # Safety
+/// `handle` must come from `fdu_xk_new` and not be freed yet. `uid` and `pwd` must be NUL-terminated UTF-8 strings.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_xk_login(handle: *mut c_void, uid: *const c_char, pwd: *const c_char) -> c_int {
+    let xk = match xk_from_handle(handle) {
+        Some(xk) => xk,
+        None => return -1,
+    };
+    let (uid, pwd) = match credentials_from_c(uid, pwd) {
+        Some(credentials) => credentials,
+        None => return -1,
+    };
+    match xk.login(uid, pwd) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// # Safety
+/// `handle` must come from `fdu_xk_new`, and must not be used any more. A null handle is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_xk_free(handle: *mut c_void) {
+    if handle.is_null() { return; }
+    drop(Box::from_raw(handle as *mut XK));
+}
+
+/// A course of xk for C. The strings belong to the `CCourseArray` holding it, and are freed with it.
+#[repr(C)]
+pub struct CCourse {
+    pub id: c_int,
+    /// eg. ECON130213.01
+    pub no: *mut c_char,
+    /// eg. ECON130213
+    pub code: *mut c_char,
+    /// eg. 计量经济学
+    pub name: *mut c_char,
+    /// comma separated, eg. 陈钊,王永钦
+    pub teachers: *mut c_char,
+    /// NaN if xk does not say
+    pub credits: c_double,
+    /// the seats left
+    pub remaining: c_int,
+}
+
+/// `len` courses at `ptr`, to be freed with `fdu_free_courses`. `ptr` is null on error.
+#[repr(C)]
+pub struct CCourseArray {
+    pub ptr: *mut CCourse,
+    pub len: usize,
+}
+
+// A C string of `text`, without the NULs a C string cannot hold.
+fn c_string(text: &str) -> *mut c_char {
+    CString::new(text.replace('\0', "")).unwrap_or_default().into_raw()
+}
+
+fn courses_to_c(courses: &[Course]) -> CCourseArray {
+    let courses: Box<[CCourse]> = courses.iter().map(|course| CCourse {
+        id: course.id.value(),
+        no: c_string(&course.no),
+        code: c_string(&course.code),
+        name: c_string(&course.name),
+        teachers: c_string(&course.teachers.join(",")),
+        credits: course.credits.unwrap_or(f64::NAN),
+        remaining: course.remaining(),
+    }).collect();
+    let len = courses.len();
+    CCourseArray { ptr: Box::into_raw(courses) as *mut CCourse, len }
+}
+
+/// Search the courses of xk for `name` in the name, code or teachers, ignoring case. An empty name gives all of them.
+/// On error, the array is empty with a null `ptr`, see `fdu_last_error`.
+///
+/// # Safety
+/// `handle` must come from `fdu_xk_new`, be logged in with `fdu_xk_login` and not be freed yet.
+/// `name` must be a NUL-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_query_courses(handle: *mut c_void, name: *const c_char) -> CCourseArray {
+    const EMPTY: CCourseArray = CCourseArray { ptr: std::ptr::null_mut(), len: 0 };
+    let xk = match xk_from_handle(handle) {
+        Some(xk) => xk,
+        None => return EMPTY,
+    };
+    if name.is_null() {
+        set_last_error("null name");
+        return EMPTY;
+    }
+    let name = match CStr::from_ptr(name).to_str() {
+        Ok(name) => name,
+        Err(_) => {
+            set_last_error("name is not valid UTF-8");
+            return EMPTY;
+        }
+    };
+    match xk.search_courses(name) {
+        Ok(courses) => courses_to_c(&courses),
+        Err(e) => {
+            set_last_error(e);
+            EMPTY
+        }
+    }
+}
+
+/// Free the courses and their strings.
+///
+/// # Safety
+/// `array` must come from `fdu_query_courses`, and must not be used any more. An array with a null `ptr` is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_free_courses(array: CCourseArray) {
+    if array.ptr.is_null() { return; }
+    let courses = Box::from_raw(std::ptr::slice_from_raw_parts_mut(array.ptr, array.len));
+    for course in courses.iter() {
+        for string in [course.no, course.code, course.name, course.teachers] {
+            drop(CString::from_raw(string));
+        }
+    }
+}
+
+/// The number of courses selected in the profile entered by `fdu_xk_login`, e.g. for a home screen stat, with their
+/// total credits written to `credits`. A course without credits counts for 0. Returns -1 on error, see `fdu_last_error`.
+///
+/// # Safety
+/// `handle` must come from `fdu_xk_new`, be logged in with `fdu_xk_login` and not be freed yet. `credits` must point
+/// to a writable double.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_xk_selected_credits(handle: *mut c_void, credits: *mut c_double) -> c_int {
+    let xk = match xk_from_handle(handle) {
+        Some(xk) => xk,
+        None => return -1,
+    };
+    if credits.is_null() {
+        set_last_error("null credits");
+        return -1;
+    }
+    match xk.get_selected_credits() {
+        Ok((count, total)) => {
+            *credits = total;
+            count as c_int
+        }
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// Wait for a seat of the course with lesson no `no` (eg. ECON130064.01) to open and select it, polling at most
+/// `max_polls` times `interval_ms` milliseconds apart. Returns 1 if it is selected, 0 if it is still full after the
+/// last poll, and -1 on error, see `fdu_last_error`.
+///
+/// It blocks until the loop is over, so call it on a thread of its own. `fdu_grab_cancel` on `cancel` from another
+/// thread stops it with -1 before the next poll.
+///
+/// # Safety
+/// `handle` must come from `fdu_xk_new`, be logged in with `fdu_xk_login` and not be freed yet. `no` must be a
+/// NUL-terminated UTF-8 string. `cancel` must come from `fdu_grab_handle_new` and not be freed before this returns,
+/// or be null for a loop that cannot be cancelled.
+#[no_mangle]
+pub unsafe extern "C" fn fdu_xk_grab(handle: *mut c_void, no: *const c_char, max_polls: usize, interval_ms: u64,
+                                     cancel: *const AtomicBool) -> c_int {
+    let xk = match xk_from_handle(handle) {
+        Some(xk) => xk,
+        None => return -1,
+    };
+    if no.is_null() {
+        set_last_error("null lesson no");
+        return -1;
+    }
+    let no = match CStr::from_ptr(no).to_str() {
+        Ok(no) => no,
+        Err(_) => {
+            set_last_error("lesson no is not valid UTF-8");
+            return -1;
+        }
+    };
+    // a reference of our own, as C keeps the one it holds until `fdu_grab_handle_free`
+    let cancel = (!cancel.is_null()).then(|| {
+        Arc::increment_strong_count(cancel);
+        Arc::from_raw(cancel)
+    });
+    match xk.grab_by_no(no, max_polls, Duration::from_millis(interval_ms), cancel) {
+        Ok(true) => 1,
+        Ok(false) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+// Count the bytes allocated and not freed yet on each thread, so that a test can tell if it leaks.
+// It is per thread, so that the tests running at the same time do not count each other's allocations.
+#[cfg(test)]
+mod counting_allocator {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    thread_local! {
+        static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    }
+
+    pub(crate) struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get() + layout.size() as isize));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get() - layout.size() as isize));
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    pub(crate) fn allocated() -> isize {
+        ALLOCATED.with(Cell::get)
+    }
+}
+
 // Test is an important part of the project.
 // You can run all the tests by running `cargo test`.
 //
@@ -97,4 +485,144 @@ mod tests {
             assert_eq!(add(1, 2), 3);
         }
     }
+
+    // Generate the header the same way as build.rs does, but into memory.
+    #[test]
+    fn test_header() {
+        let crate_dir = env!("CARGO_MANIFEST_DIR");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).expect("read cbindgen.toml error");
+        let mut output = Vec::new();
+        cbindgen::Builder::new().with_crate(crate_dir).with_config(config).generate()
+            .expect("generate header error")
+            .write(&mut output);
+        let header = String::from_utf8(output).unwrap();
+        assert!(header.contains("typedef struct FduGrabHandle FduGrabHandle;"));
+
+        // every exported function is declared
+        let exported: Vec<&str> = include_str!("lib.rs").lines()
+            .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+            .filter_map(|rest| rest.split('(').next())
+            .collect();
+        assert!(exported.contains(&"fdu_grab_cancel"));
+        for name in exported {
+            assert!(header.contains(&format!(" {}(", name)) || header.contains(&format!("*{}(", name)), "{} is not in libfdu.h", name);
+        }
+    }
+
+    #[test]
+    fn test_grab_handle() {
+        let handle = fdu_grab_handle_new();
+        unsafe {
+            assert!(!(*handle).load(Ordering::SeqCst));
+            fdu_grab_cancel(handle);
+            assert!((*handle).load(Ordering::SeqCst));
+            fdu_grab_handle_free(handle);
+
+            // null handles are ignored
+            fdu_grab_cancel(std::ptr::null());
+            fdu_grab_handle_free(std::ptr::null());
+        }
+    }
+
+    #[test]
+    fn test_daily_has_tick_not_logged_in() {
+        use crate::fdu::mock::{mock_fdu, MockResponse, MockServer};
+
+        let server = MockServer::start();
+        // not logged in, we get the login page instead of the json
+        server.route("/ncov/wap/fudan/get-info", vec![MockResponse::ok("<html>统一身份认证</html>")]);
+        let handle = Box::into_raw(Box::new(mock_fdu(&server, &["https://zlapp.fudan.edu.cn"]))) as *mut c_void;
+        unsafe {
+            assert_eq!(fdu_daily_has_tick(handle), -1);
+            let error = fdu_last_error();
+            assert!(!error.is_null());
+            assert!(!CString::from_raw(error).to_str().unwrap().is_empty());
+            fdu_free(handle);
+
+            assert_eq!(fdu_daily_has_tick(std::ptr::null_mut()), -1);
+            assert_eq!(CString::from_raw(fdu_last_error()).to_str().unwrap(), "null handle");
+            fdu_free(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_courses_to_c() {
+        use crate::fdu::xk::Course;
+        use counting_allocator::allocated;
+
+        let courses: Vec<Course> = serde_json::from_str(r#"[
+            {"id":698266,"no":"ECON130064.01","code":"ECON130064","name":"博弈论","teachers":"陈钊,王永钦","credits":3.0,"amount":{"lc":100,"sc":93}},
+            {"id":698260,"no":"ECON130042.01","code":"ECON130042","name":"税收\u0000学"}
+        ]"#).unwrap();
+        let before = allocated();
+        let array = courses_to_c(&courses);
+        assert!(allocated() > before);
+        unsafe {
+            assert_eq!(array.len, 2);
+            let c_courses = std::slice::from_raw_parts(array.ptr, array.len);
+            assert_eq!(c_courses[0].id, 698266);
+            assert_eq!(CStr::from_ptr(c_courses[0].teachers).to_str().unwrap(), "陈钊,王永钦");
+            assert_eq!((c_courses[0].credits, c_courses[0].remaining), (3.0, 7));
+            assert_eq!(CStr::from_ptr(c_courses[1].name).to_str().unwrap(), "税收学");
+            assert!(c_courses[1].credits.is_nan());
+            fdu_free_courses(array);
+        }
+        // everything is freed
+        assert_eq!(allocated(), before);
+
+        unsafe {
+            fdu_free_courses(courses_to_c(&[]));
+            assert_eq!(allocated(), before);
+            // not logged in, no profile entered
+            let handle = fdu_xk_new();
+            let name = CString::new("博弈").unwrap();
+            let array = fdu_query_courses(handle, name.as_ptr());
+            assert!(array.ptr.is_null());
+            drop(CString::from_raw(fdu_last_error()));
+            fdu_free_courses(array);
+            fdu_xk_free(handle);
+        }
+    }
+
+    #[test]
+    fn test_xk_selected_credits() {
+        let mut credits = -1.0;
+        unsafe {
+            // not logged in, no profile entered
+            let handle = fdu_xk_new();
+            assert_eq!(fdu_xk_selected_credits(handle, &mut credits), -1);
+            drop(CString::from_raw(fdu_last_error()));
+            assert_eq!(fdu_xk_selected_credits(handle, std::ptr::null_mut()), -1);
+            assert_eq!(CString::from_raw(fdu_last_error()).to_str().unwrap(), "null credits");
+            fdu_xk_free(handle);
+        }
+        // left as it is on error
+        assert_eq!(credits, -1.0);
+    }
+
+    #[test]
+    fn test_xk_grab() {
+        let no = CString::new("ECON130064.01").unwrap();
+        unsafe {
+            // cancelled before the first poll
+            let handle = fdu_xk_new();
+            let cancel = fdu_grab_handle_new();
+            fdu_grab_cancel(cancel);
+            assert_eq!(fdu_xk_grab(handle, no.as_ptr(), 10, 0, cancel), -1);
+            assert!(CString::from_raw(fdu_last_error()).to_str().unwrap().contains("cancelled"));
+            // the handle is still ours to free
+            assert!((*cancel).load(Ordering::SeqCst));
+            fdu_grab_handle_free(cancel);
+
+            // not logged in, no profile entered
+            assert_eq!(fdu_xk_grab(handle, no.as_ptr(), 1, 0, std::ptr::null()), -1);
+            drop(CString::from_raw(fdu_last_error()));
+            assert_eq!(fdu_xk_grab(handle, std::ptr::null(), 1, 0, std::ptr::null()), -1);
+            assert_eq!(CString::from_raw(fdu_last_error()).to_str().unwrap(), "null lesson no");
+            fdu_xk_free(handle);
+
+            assert_eq!(fdu_xk_grab(std::ptr::null_mut(), no.as_ptr(), 1, 0, std::ptr::null()), -1);
+            assert_eq!(CString::from_raw(fdu_last_error()).to_str().unwrap(), "null handle");
+        }
+    }
 }